@@ -0,0 +1,174 @@
+//! Performance of the force solvers, the integrators, the circle conversion
+//! and the writers and readers.
+//!
+//! Run with `cargo bench`. `steps/*` reports updates per second as its
+//! throughput, `interactions/*` the pairs a direct sum would evaluate per
+//! second, N(N - 1) / 2 per step whatever the solver actually does, so the
+//! backends compare on one scale. `integrators/*` times updates of leapfrog,
+//! Hermite and block steps on the direct sum at the same counts.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::time::Duration;
+use wgpu_test::{
+    create_galaxy, read_gadget, AdaptiveTimestep, Body, BodyExport, ClusterAnalysis, ExportCadence, ExportFormat, FastMultipole,
+    ForceSolver, FriendsOfFriends, GadgetFormat, GalaxyModel, GroupFinder, Integrator, LogarithmicGravity, ParticleMesh, PeriodicBox,
+    ProfileCenter, Simulation, Timestep, TimestepCriterion,
+};
+
+const BODY_COUNTS: [usize; 3] = [1_000, 5_000, 20_000];
+const GRAVITATIONAL_CONSTANT: f32 = 1.0;
+const SOFTENING: f32 = 0.05;
+/// Holds the whole halo of the default galaxy scaled up to 20k bodies.
+const BOX_SIZE: f32 = 80.0;
+
+fn galaxy(count: usize) -> Vec<Body> {
+    let default = GalaxyModel::default();
+    let total = default.disk_bodies + default.bulge_bodies + default.halo_bodies;
+    create_galaxy(&default.scaled(count as f32 / total as f32), GRAVITATIONAL_CONSTANT, SOFTENING)
+}
+
+fn solvers() -> [(&'static str, ForceSolver); 3] {
+    [
+        ("direct", ForceSolver::Direct),
+        ("particle_mesh", ForceSolver::ParticleMesh(ParticleMesh::new(128))),
+        ("fast_multipole", ForceSolver::FastMultipole(FastMultipole::new(8))),
+    ]
+}
+
+fn simulation(bodies: &[Body], solver: ForceSolver) -> Simulation {
+    let mut simulation = Simulation::from_bodies(bodies.to_vec());
+    simulation.set_gravitational_constant(GRAVITATIONAL_CONSTANT);
+    simulation.set_softening(SOFTENING);
+    simulation.set_timestep(Timestep::Fixed(1e-3)).unwrap();
+    match solver {
+        ForceSolver::ParticleMesh(_) => simulation.set_periodic_box(Some(PeriodicBox::new(BOX_SIZE))).unwrap(),
+        ForceSolver::FastMultipole(_) => simulation.set_pair_force(Box::new(LogarithmicGravity)).unwrap(),
+        ForceSolver::Direct => {}
+    }
+    simulation.set_force_solver(solver).unwrap();
+    simulation
+}
+
+fn update(c: &mut Criterion) {
+    for (group, count_pairs) in [("steps", false), ("interactions", true)] {
+        for (name, solver) in solvers() {
+            let mut benchmarks = c.benchmark_group(format!("{}/{}", group, name));
+            benchmarks.sample_size(10).measurement_time(Duration::from_secs(10));
+
+            for count in BODY_COUNTS {
+                let bodies = galaxy(count);
+                let per_step = if count_pairs { (count * (count - 1) / 2) as u64 } else { 1 };
+                benchmarks.throughput(Throughput::Elements(per_step));
+                benchmarks.bench_with_input(BenchmarkId::from_parameter(count), &bodies, |b, bodies| {
+                    let mut simulation = simulation(bodies, solver);
+                    b.iter(|| simulation.update());
+                });
+            }
+            benchmarks.finish();
+        }
+    }
+}
+
+fn integrators(c: &mut Criterion) {
+    // Block steps advance by the same 1e-3 per update as the fixed ones.
+    let block = Timestep::Block(AdaptiveTimestep::new(TimestepCriterion::FreeFall, 1e-3 / 64.0, 1e-3));
+    let integrators = [
+        ("leapfrog", Integrator::Leapfrog, None),
+        ("hermite", Integrator::Hermite, None),
+        ("block", Integrator::Leapfrog, Some(block)),
+    ];
+
+    let mut benchmarks = c.benchmark_group("integrators");
+    benchmarks.sample_size(10).measurement_time(Duration::from_secs(10));
+    for count in BODY_COUNTS {
+        let bodies = galaxy(count);
+        benchmarks.throughput(Throughput::Elements(1));
+
+        for (name, integrator, timestep) in integrators {
+            benchmarks.bench_with_input(BenchmarkId::new(name, count), &bodies, |b, bodies| {
+                // Hermite takes the direct sum without body forces, as here.
+                let mut simulation = simulation(bodies, ForceSolver::Direct);
+                simulation.set_integrator(integrator).unwrap();
+                if let Some(timestep) = timestep {
+                    simulation.set_timestep(timestep).unwrap();
+                }
+                b.iter(|| simulation.update());
+            });
+        }
+    }
+    benchmarks.finish();
+}
+
+fn circles(c: &mut Criterion) {
+    let mut benchmarks = c.benchmark_group("circles");
+    for count in BODY_COUNTS {
+        let bodies = galaxy(count);
+        benchmarks.throughput(Throughput::Elements(count as u64));
+
+        benchmarks.bench_with_input(BenchmarkId::new("to_circle", count), &bodies, |b, bodies| {
+            b.iter(|| bodies.iter().map(Body::to_circle).map(black_box).count());
+        });
+        benchmarks.bench_with_input(BenchmarkId::new("get_bodies_as_circles", count), &bodies, |b, bodies| {
+            let mut simulation = simulation(bodies, ForceSolver::Direct);
+            b.iter(|| black_box(simulation.get_bodies_as_circles()));
+        });
+    }
+    benchmarks.finish();
+}
+
+fn writers(c: &mut Criterion) {
+    let mut benchmarks = c.benchmark_group("writers");
+    for count in BODY_COUNTS {
+        let simulation = simulation(&galaxy(count), ForceSolver::Direct);
+        let mut buffer = Vec::new();
+        benchmarks.throughput(Throughput::Elements(count as u64));
+
+        let analysis = ClusterAnalysis::new(ProfileCenter::CenterOfMass);
+        let diagnostics = simulation.cluster_diagnostics(&analysis);
+        benchmarks.bench_function(BenchmarkId::new("cluster_profile_csv", count), |b| {
+            b.iter(|| {
+                buffer.clear();
+                diagnostics.write_csv(&mut buffer).unwrap();
+                diagnostics.write_profile_csv(&mut buffer).unwrap();
+                black_box(&buffer);
+            });
+        });
+
+        let groups = simulation.find_groups(&GroupFinder::FriendsOfFriends(FriendsOfFriends::new(SOFTENING)));
+        benchmarks.bench_function(BenchmarkId::new("group_membership_csv", count), |b| {
+            b.iter(|| {
+                buffer.clear();
+                groups.write_membership_csv(&mut buffer).unwrap();
+                black_box(&buffer);
+            });
+        });
+
+        for (name, format) in [("body_export_csv", ExportFormat::Csv), ("body_export_json_lines", ExportFormat::JsonLines)] {
+            let export = BodyExport::new(format, ExportCadence::EveryUpdates(1));
+            benchmarks.bench_function(BenchmarkId::new(name, count), |b| {
+                b.iter(|| {
+                    buffer.clear();
+                    export.write_snapshot(&mut buffer, simulation.bodies(), simulation.time()).unwrap();
+                    black_box(&buffer);
+                });
+            });
+        }
+
+        benchmarks.bench_function(BenchmarkId::new("gadget_write", count), |b| {
+            b.iter(|| {
+                buffer.clear();
+                simulation.write_gadget_snapshot(&mut buffer, GadgetFormat::Format2).unwrap();
+                black_box(&buffer);
+            });
+        });
+        let mut snapshot = Vec::new();
+        simulation.write_gadget_snapshot(&mut snapshot, GadgetFormat::Format2).unwrap();
+        benchmarks.bench_function(BenchmarkId::new("gadget_read", count), |b| {
+            b.iter(|| black_box(read_gadget(snapshot.as_slice(), 1.0).unwrap().bodies.len()));
+        });
+    }
+    benchmarks.finish();
+}
+
+criterion_group!(benches, update, integrators, circles, writers);
+criterion_main!(benches);
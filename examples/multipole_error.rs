@@ -0,0 +1,77 @@
+//! Cost and accuracy of the fast multipole solver against direct summation.
+//!
+//! Run with `cargo run --release --example multipole_error`. Prints one CSV row
+//! per body count and expansion order: the solver's wall time, the direct sum's
+//! time (extrapolated from the sampled bodies past 10⁴ bodies) and the relative
+//! RMS acceleration error over a sample of bodies.
+
+use cgmath::Vector2;
+use std::time::Instant;
+use rand::Rng;
+use wgpu_test::{seeded_rng, Body, FastMultipole, SimulationRng};
+
+const BODY_COUNTS: [usize; 4] = [1_000, 10_000, 100_000, 1_000_000];
+const ORDERS: [usize; 5] = [2, 4, 8, 12, 16];
+const SAMPLES: usize = 1_000;
+const FULL_DIRECT_LIMIT: usize = 10_000;
+
+fn uniform_disc(count: usize, rng: &mut SimulationRng) -> Vec<Body> {
+    (0..count)
+        .map(|_| {
+            let radius = rng.gen::<f32>().sqrt();
+            let angle = rng.gen::<f32>() * std::f32::consts::TAU;
+            Body::new(Vector2::new(radius * angle.cos(), radius * angle.sin()), 1.0 / count as f32, 1.0)
+        })
+        .collect()
+}
+
+/// Logarithmic-gravity acceleration of `bodies[i]` from all the others, in f64.
+fn direct_acceleration(bodies: &[Body], i: usize) -> Vector2<f64> {
+    let position = bodies[i].position.cast::<f64>().unwrap();
+    let mut acceleration = Vector2::new(0.0, 0.0);
+    for (j, other) in bodies.iter().enumerate() {
+        if j != i {
+            let separation = other.position.cast::<f64>().unwrap() - position;
+            acceleration += separation * (other.mass as f64 / (separation.x * separation.x + separation.y * separation.y));
+        }
+    }
+    acceleration
+}
+
+fn main() {
+    let mut rng = seeded_rng(1);
+    println!("bodies,order,multipole_seconds,direct_seconds,relative_rms_error");
+
+    for &count in BODY_COUNTS.iter() {
+        let bodies = uniform_disc(count, &mut rng);
+        let samples = (0..SAMPLES.min(count)).map(|n| n * count / SAMPLES.min(count)).collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let reference = samples.iter().map(|&i| direct_acceleration(&bodies, i)).collect::<Vec<_>>();
+        let direct_seconds = if count <= FULL_DIRECT_LIMIT {
+            let start = Instant::now();
+            for i in 0..count {
+                std::hint::black_box(direct_acceleration(&bodies, i));
+            }
+            start.elapsed().as_secs_f64()
+        } else {
+            start.elapsed().as_secs_f64() * count as f64 / samples.len() as f64
+        };
+
+        for &order in ORDERS.iter() {
+            let start = Instant::now();
+            let accelerations = FastMultipole::new(order).accelerations(&bodies, 1.0, 0.0);
+            let multipole_seconds = start.elapsed().as_secs_f64();
+
+            let (mut error, mut norm) = (0.0, 0.0);
+            for (&i, exact) in samples.iter().zip(reference.iter()) {
+                let approximation = accelerations[i].cast::<f64>().unwrap();
+                let difference = approximation - exact;
+                error += difference.x * difference.x + difference.y * difference.y;
+                norm += exact.x * exact.x + exact.y * exact.y;
+            }
+
+            println!("{},{},{:.4},{:.4},{:.3e}", count, order, multipole_seconds, direct_seconds, (error / norm).sqrt());
+        }
+    }
+}
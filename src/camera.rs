@@ -1,4 +1,3 @@
-use std::fmt::Debug;
 use std::num::NonZeroU64;
 use wgpu::{BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, Device, ShaderStages, Surface, SurfaceConfiguration};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
@@ -26,9 +25,7 @@ impl Camera {
         let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
         let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
 
-        let projection_matrix = OPENGL_TO_WGPU_MATRIX * proj * view;
-
-        return projection_matrix;
+        OPENGL_TO_WGPU_MATRIX * proj * view
     }
 }
 
@@ -50,19 +47,19 @@ impl CameraUniform {
     }
 }
 
-pub fn setup_camera(surface: &Surface, device: &Device, surface_config: &SurfaceConfiguration) -> (Camera, CameraUniform, BindGroup, BindGroupLayout, Buffer) {
+pub fn setup_camera(_surface: &Surface, device: &Device, surface_config: &SurfaceConfiguration) -> (Camera, CameraUniform, BindGroup, BindGroupLayout, Buffer) {
     let camera = Camera {
         // position the camera 1 unit up and 2 units back
         // +z is out of the screen
-        eye: (0.0, 0.0, -25.0).into(),
+        eye: (0.0, 0.0, -500.0).into(),
         // have it look at the origin
         target: (0.0, 0.0, 0.0).into(),
         // which way is "up"
         up: cgmath::Vector3::unit_y(),
         aspect: surface_config.width as f32 / surface_config.height as f32,
         fovy: 45.0,
-        znear: 0.1,
-        zfar: 100.0,
+        znear: 2.0,
+        zfar: 2000.0,
     };
 
     let mut camera_uniform = CameraUniform::new(&camera);
@@ -1,5 +1,4 @@
-use std::num::NonZeroU32;
-use wgpu::{BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBinding, BufferBindingType, BufferUsages, Device, ShaderStages};
+use wgpu::{Buffer, BufferUsages, Device};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 
 #[repr(C)]
@@ -67,15 +66,15 @@ const CIRCLES: &[Circle] = &[
         color: 0xFF00FFFF,
     },
     Circle {
-        world_pos: [3.5355339059, 3.5355339059, 0.0],  // 45-degree angle
+        world_pos: [3.535_534, 3.535_534, 0.0],  // 45-degree angle
         radius: 0.1,
         color: 0x00FFFFFF,
     },
 ];
 
+#[allow(dead_code)]
 fn pack_rgba_into_u32(r: u8, g: u8, b: u8, a: u8) -> u32 {
-    let result = (r as u32) << 24 | (g as u32) << 16 | (b as u32) << 8 | a as u32;
-    result
+    (r as u32) << 24 | (g as u32) << 16 | (b as u32) << 8 | a as u32
 }
 
 pub fn initialize_circle_and_vertex_bufs(device: &Device) -> (Vec<Circle>, Buffer) {
@@ -97,5 +96,5 @@ pub fn initialize_circle_and_vertex_bufs(device: &Device) -> (Vec<Circle>, Buffe
         usage: BufferUsages::VERTEX,
     });
 
-    return (circles, circle_buffer);
+    (circles, circle_buffer)
 }
\ No newline at end of file
@@ -3,18 +3,14 @@ mod drawing;
 mod nbody_sim;
 
 pub use nbody_sim::*;
-use std::fmt::Debug;
-use std::mem;
-use std::num::NonZeroU64;
 use std::sync::Arc;
-use wgpu::{Adapter, Backends, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Buffer, BufferAddress, BufferBinding, BufferBindingType, BufferUsages, Color, ColorTargetState, ColorWrites, CompositeAlphaMode, Device, DeviceDescriptor, Features, FragmentState, include_wgsl, Instance, InstanceDescriptor, InstanceFlags, Label, Limits, LoadOp, Operations, PipelineLayoutDescriptor, PowerPreference, PrimitiveState, PrimitiveTopology, Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions, ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, StoreOp, Surface, SurfaceConfiguration, TextureFormat, TextureUsages, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState};
-use wgpu::LoadOp::Load;
+use wgpu::{Adapter, Backends, BindGroup, BlendState, Buffer, BufferUsages, Color, ColorTargetState, ColorWrites, Device, DeviceDescriptor, Features, FragmentState, include_wgsl, Instance, InstanceDescriptor, InstanceFlags, LoadOp, Operations, PipelineLayoutDescriptor, PowerPreference, PrimitiveState, PrimitiveTopology, Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions, StoreOp, Surface, SurfaceConfiguration, TextureUsages, VertexState};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::dpi::PhysicalSize;
 use winit::window::Window;
 use camera::Camera;
 use crate::camera::CameraUniform;
-use crate::drawing::Circle;
+use crate::drawing::{Circle, Vertex};
 
 pub struct State {
     pub window: Arc<Window>,
@@ -27,12 +23,16 @@ pub struct State {
     pub instances: Vec<Circle>,
     pub instance_buffer: Buffer,
 
+    pub outline: Vec<Vertex>,
+    pub outline_buffer: Buffer,
+
     pub camera: Camera,
     pub camera_uniform: CameraUniform,
     pub camera_bind_group: BindGroup,
     pub camera_buffer: Buffer,
 
     pub render_pipeline: RenderPipeline,
+    pub outline_pipeline: RenderPipeline,
 }
 
 impl State {
@@ -109,6 +109,43 @@ impl State {
                 multiview: None,
             });
 
+        let outline_pipeline = device.create_render_pipeline(
+            &RenderPipelineDescriptor {
+                label: Some("Outline Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vs_outline",
+                    buffers: &[
+                        drawing::Vertex::desc()
+                    ],
+                },
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: Default::default(),
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: "fs_outline",
+                    targets: &[
+                        Some(ColorTargetState {
+                            format: surface_config.format,
+                            blend: Some(BlendState::ALPHA_BLENDING),
+                            write_mask: ColorWrites::ALL,
+                        })
+                    ],
+                }),
+                multiview: None,
+            });
+
+        let outline_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Outline Buffer"),
+            contents: &[],
+            usage: BufferUsages::VERTEX,
+        });
+
         Self {
             window,
             surface,
@@ -124,12 +161,16 @@ impl State {
             instances,
             instance_buffer,
 
+            outline: Vec::new(),
+            outline_buffer,
+
             camera,
             camera_uniform,
             camera_bind_group,
             camera_buffer,
 
             render_pipeline,
+            outline_pipeline,
         }
     }
 
@@ -161,6 +202,19 @@ impl State {
         });
     }
 
+    /// Line-list vertices drawn over the circles, such as the periodic box.
+    pub fn update_outline(&mut self, new_outline: Vec<Vertex>) {
+        if new_outline.is_empty() && self.outline.is_empty() {
+            return;
+        }
+        self.outline_buffer = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Outline Buffer"),
+            contents: bytemuck::cast_slice(new_outline.as_slice()),
+            usage: BufferUsages::VERTEX,
+        });
+        self.outline = new_outline;
+    }
+
     pub fn render(&mut self) {
         let frame = self.surface.get_current_texture().unwrap();
         let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
@@ -192,24 +246,28 @@ impl State {
         // render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
         render_pass.draw(0..3, 0..self.instances.len() as u32);
+
+        if !self.outline.is_empty() {
+            render_pass.set_pipeline(&self.outline_pipeline);
+            render_pass.set_vertex_buffer(0, self.outline_buffer.slice(..));
+            render_pass.draw(0..self.outline.len() as u32, 0..1);
+        }
         drop(render_pass);
 
         self.queue.submit(Some(encoder.finish()));
         frame.present();
-        return;
     }
 }
 
 pub fn setup_surface(instance: &Instance, window: Arc<Window>, adapter: &Adapter, device: &Device) -> (Surface<'static>, SurfaceConfiguration) {
     let surface = instance.create_surface(window.clone()).unwrap();
 
-    let surface_capabilities = surface.get_capabilities(&adapter);
+    let surface_capabilities = surface.get_capabilities(adapter);
     let surface_format = surface_capabilities
         .formats
         .iter()
         .copied()
-        .filter(|f| f.is_srgb())
-        .next()
+        .find(|f| f.is_srgb())
         .unwrap_or(surface_capabilities.formats[0]);
 
     let window_size = window.inner_size();
@@ -224,6 +282,6 @@ pub fn setup_surface(instance: &Instance, window: Arc<Window>, adapter: &Adapter
         view_formats: vec![],
     };
 
-    surface.configure(&device, &surface_config);
+    surface.configure(device, &surface_config);
     (surface, surface_config)
 }
\ No newline at end of file
@@ -10,7 +10,7 @@ async fn main() {
     let event_loop = winit::event_loop::EventLoopBuilder::new().build().unwrap();
     let window = winit::window::WindowBuilder::default().build(&event_loop).unwrap();
     let out_window_id = window.id();
-    let mut state = Arc::new(Mutex::new(State::new(Arc::new(window)).await));
+    let state = Arc::new(Mutex::new(State::new(Arc::new(window)).await));
 
     let mut simulation = Simulation::new(5000, 0.05);
 
@@ -19,7 +19,9 @@ async fn main() {
         let state = state_thread;
         loop {
             simulation.update();
-            state.lock().unwrap().update_circles(simulation.get_bodies_as_circles());
+            let mut state = state.lock().unwrap();
+            state.update_circles(simulation.get_bodies_as_circles());
+            state.update_outline(simulation.get_box_outline());
         }
     });
 
@@ -29,10 +31,8 @@ async fn main() {
             if window_id == out_window_id
             => {
                 match event {
-                    WindowEvent::Resized(size) => {
-                        if size.width != 0 && size.height != 0 {
-                            state.lock().unwrap().resize(size);
-                        }
+                    WindowEvent::Resized(size) if size.width != 0 && size.height != 0 => {
+                        state.lock().unwrap().resize(size);
                     }
                     WindowEvent::CloseRequested | WindowEvent::KeyboardInput {
                         event: KeyEvent {
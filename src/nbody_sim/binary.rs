@@ -0,0 +1,189 @@
+use cgmath::{InnerSpace, Vector2};
+use crate::nbody_sim::{Body, OrbitalElements};
+
+const DEFAULT_HIERARCHY_RATIO: f32 = 3.0;
+
+/// Finds bound pairs and hierarchical triples. A binary is a pair of massive
+/// mutual nearest neighbours with negative two-body energy; a triple is a
+/// binary and a single body that are mutual nearest neighbours once the binary
+/// counts as one body at its centre of mass, bound to each other, with the
+/// outer pericentre at least `hierarchy_ratio` times the inner apocentre.
+/// Energies are of the isolated pair under the given pair potential; the
+/// elements are the osculating Kepler orbit.
+#[derive(Copy, Clone, Debug)]
+pub struct BinaryFinder {
+    /// Widest separation a binary may have.
+    pub max_separation: f32,
+    pub hierarchy_ratio: f32,
+}
+
+/// `secondary` is on the orbit `elements` around `primary`, the heavier of the two.
+#[derive(Copy, Clone, Debug)]
+pub struct Binary {
+    pub primary: usize,
+    pub secondary: usize,
+    pub elements: OrbitalElements,
+    /// Energy needed to unbind the pair, positive for a binary.
+    pub binding_energy: f32,
+}
+
+/// `outer` on the orbit `elements` around the centre of mass of `inner`.
+#[derive(Copy, Clone, Debug)]
+pub struct Triple {
+    pub inner: Binary,
+    pub outer: usize,
+    pub elements: OrbitalElements,
+    pub binding_energy: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Multiples {
+    pub binaries: Vec<Binary>,
+    pub triples: Vec<Triple>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinaryEventKind {
+    Formed,
+    Disrupted,
+}
+
+/// A binary that appeared or went away during a run, as it was when first or
+/// last found.
+#[derive(Copy, Clone, Debug)]
+pub struct BinaryEvent {
+    pub time: f64,
+    pub kind: BinaryEventKind,
+    pub binary: Binary,
+}
+
+impl BinaryFinder {
+    pub fn new() -> Self {
+        BinaryFinder {
+            max_separation: f32::INFINITY,
+            hierarchy_ratio: DEFAULT_HIERARCHY_RATIO,
+        }
+    }
+
+    /// `separation` gives the vector between two bodies, so a periodic box can
+    /// use the nearest image, and `potential` their potential energy.
+    pub fn find(
+        &self,
+        bodies: &[Body],
+        g: f32,
+        separation: impl Fn(&Body, &Body) -> Vector2<f32>,
+        potential: impl Fn(&Body, &Body) -> f32,
+    ) -> Multiples {
+        let binaries = mutual_nearest_neighbours(bodies, &separation, self.max_separation)
+            .into_iter()
+            .filter_map(|(a, b)| {
+                let (primary, secondary) = if bodies[b].mass > bodies[a].mass { (b, a) } else { (a, b) };
+                let (elements, binding_energy) = two_body_orbit(&bodies[primary], &bodies[secondary], g, &separation, &potential);
+                (binding_energy > 0.0).then_some(Binary { primary, secondary, elements, binding_energy })
+            })
+            .collect::<Vec<_>>();
+
+        // Singles and binaries as one list of bodies, binaries last.
+        let mut paired = vec![false; bodies.len()];
+        for binary in binaries.iter() {
+            paired[binary.primary] = true;
+            paired[binary.secondary] = true;
+        }
+        let singles = (0..bodies.len()).filter(|&i| !paired[i] && !bodies[i].test_particle).collect::<Vec<_>>();
+        let particles = singles.iter()
+            .map(|&i| bodies[i])
+            .chain(binaries.iter().map(|binary| {
+                let secondary = Body {
+                    position: bodies[binary.primary].position + separation(&bodies[binary.primary], &bodies[binary.secondary]),
+                    ..bodies[binary.secondary]
+                };
+                centre_of_mass(&bodies[binary.primary], &secondary)
+            }))
+            .collect::<Vec<_>>();
+
+        let mut triples = Vec::new();
+        for (a, b) in mutual_nearest_neighbours(&particles, &separation, f32::INFINITY) {
+            // Only a single and a binary; two singles would have been a binary.
+            if a >= singles.len() || b < singles.len() {
+                continue;
+            }
+            let inner = binaries[b - singles.len()];
+            let (elements, binding_energy) = two_body_orbit(&particles[b], &particles[a], g, &separation, &potential);
+            let pericentre = elements.semi_major_axis * (1.0 - elements.eccentricity);
+            let apocentre = inner.elements.semi_major_axis * (1.0 + inner.elements.eccentricity);
+            if binding_energy > 0.0 && pericentre >= self.hierarchy_ratio * apocentre {
+                triples.push(Triple { inner, outer: singles[a], elements, binding_energy });
+            }
+        }
+
+        Multiples { binaries, triples }
+    }
+}
+
+impl Default for BinaryFinder {
+    fn default() -> Self {
+        BinaryFinder::new()
+    }
+}
+
+impl Binary {
+    /// Whether the two describe the same pair of bodies.
+    pub fn same_pair(&self, other: &Binary) -> bool {
+        (self.primary, self.secondary) == (other.primary, other.secondary)
+            || (self.primary, self.secondary) == (other.secondary, other.primary)
+    }
+}
+
+/// Elements of `secondary` around `primary` and the pair's binding energy.
+fn two_body_orbit(
+    primary: &Body,
+    secondary: &Body,
+    g: f32,
+    separation: impl Fn(&Body, &Body) -> Vector2<f32>,
+    potential: impl Fn(&Body, &Body) -> f32,
+) -> (OrbitalElements, f32) {
+    let position = separation(primary, secondary);
+    let speed = secondary.speed - primary.speed;
+    let mass = primary.mass + secondary.mass;
+    let reduced_mass = primary.mass * secondary.mass / mass;
+
+    let energy = 0.5 * reduced_mass * speed.magnitude2() + potential(primary, secondary);
+    (OrbitalElements::from_state_vectors(position, speed, g * mass), -energy)
+}
+
+/// Massive bodies that are each other's nearest massive neighbour and closer
+/// than `max_distance`, lower index first.
+pub(crate) fn mutual_nearest_neighbours(bodies: &[Body], separation: impl Fn(&Body, &Body) -> Vector2<f32>, max_distance: f32) -> Vec<(usize, usize)> {
+    let massive = (0..bodies.len()).filter(|&i| !bodies[i].test_particle).collect::<Vec<_>>();
+    let nearest = |i: usize| {
+        massive.iter()
+            .filter(|&&j| j != i)
+            .map(|&j| (j, separation(&bodies[i], &bodies[j]).magnitude2()))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    };
+
+    let mut pairs = Vec::new();
+    for &i in massive.iter() {
+        if let Some((j, distance2)) = nearest(i) {
+            if i < j && distance2 < max_distance * max_distance && nearest(j).map(|(k, _)| k) == Some(i) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+/// A single body standing in for `a` and `b`, with their total mass and
+/// mass-weighted position, speed, acceleration and jerk.
+pub(crate) fn centre_of_mass(a: &Body, b: &Body) -> Body {
+    let mass = a.mass + b.mass;
+    let average = |x: Vector2<f32>, y: Vector2<f32>| (x * a.mass + y * b.mass) / mass;
+    Body {
+        position: average(a.position, b.position),
+        speed: average(a.speed, b.speed),
+        acceleration: average(a.acceleration, b.acceleration),
+        jerk: average(a.jerk, b.jerk),
+        mass,
+        ..*a
+    }
+}
@@ -1,6 +1,6 @@
-use std::ops::{Add, Sub};
-use cgmath::{MetricSpace, Vector2, Vector3};
+use cgmath::{InnerSpace, Vector2};
 use crate::drawing::Circle;
+use crate::nbody_sim::PeriodicBox;
 
 #[derive(Copy, Clone)]
 pub struct Body {
@@ -8,10 +8,34 @@ pub struct Body {
     pub mass: f32,
     pub speed: Vector2<f32>,
     pub acceleration: Vector2<f32>,
+    /// Time derivative of `acceleration`.
+    pub jerk: Vector2<f32>,
     pub density: f32,
+    /// Used by the electrostatic and screened pair forces; zero by default.
+    pub charge: f32,
+    /// Physical radius for aerodynamic drag. Zero, the default, means the body
+    /// feels no drag.
+    pub size: f32,
+    /// Density of the body's material for aerodynamic drag, independent of the
+    /// drawing `density`; one by default.
+    pub material_density: f32,
+    /// Block-timestep level: the body is advanced with `max_dt / 2^rung`.
+    pub rung: u32,
+    /// Massless tracer: feels the massive bodies but pulls on nothing.
+    pub test_particle: bool,
+    /// Identifies the body for its whole run, unlike its index, which shifts
+    /// when bodies before it are removed. `Simulation` numbers bodies as they
+    /// are added; zero until then.
+    pub id: u64,
 }
 
-const G: f32 = 50.0;
+/// Gravitational constant of the spiral cluster demo; `Simulation` can override it.
+pub const G: f32 = 50.0;
+
+/// Test particles have no mass to size them by, so they are drawn at the size
+/// of a spiral cluster body, translucent light blue.
+const TEST_PARTICLE_RADIUS: f32 = 1.0;
+const TEST_PARTICLE_COLOR: u32 = 0x4FC3F7B0;
 
 impl Body {
     pub fn new(position: Vector2<f32>, mass: f32, density: f32) -> Self {
@@ -20,7 +44,14 @@ impl Body {
             mass,
             speed: Vector2 { x: 0.0, y: 0.0 },
             acceleration: Vector2 { x: 0.0, y: 0.0 },
+            jerk: Vector2 { x: 0.0, y: 0.0 },
             density,
+            charge: 0.0,
+            size: 0.0,
+            material_density: 1.0,
+            rung: 0,
+            test_particle: false,
+            id: 0,
         }
     }
 
@@ -30,22 +61,49 @@ impl Body {
             mass,
             speed,
             acceleration: Vector2 { x: 0.0, y: 0.0 },
+            jerk: Vector2 { x: 0.0, y: 0.0 },
             density,
+            charge: 0.0,
+            size: 0.0,
+            material_density: 1.0,
+            rung: 0,
+            test_particle: false,
+            id: 0,
         }
     }
 
-    pub fn update(&mut self, dt: f32) {
-        // Update velocity and position using Euler method
+    pub fn new_test_particle(position: Vector2<f32>, speed: Vector2<f32>) -> Self {
+        Body {
+            test_particle: true,
+            ..Body::new_sp(position, 0.0, speed, 1.0)
+        }
+    }
+
+    pub fn kick(&mut self, dt: f32) {
         self.speed += self.acceleration * dt;
+    }
+
+    pub fn drift(&mut self, dt: f32, periodic_box: Option<&PeriodicBox>) {
         self.position += self.speed * dt;
+        self.wrap(periodic_box);
+    }
 
-        self.acceleration = Vector2 {
-            x: 0.0,
-            y: 0.0,
-        };
+    /// Moves the body back inside the box after it crossed an edge.
+    pub fn wrap(&mut self, periodic_box: Option<&PeriodicBox>) {
+        if let Some(periodic_box) = periodic_box {
+            self.position = periodic_box.wrap(self.position);
+        }
     }
 
     pub fn to_circle(&self) -> Circle {
+        if self.test_particle {
+            return Circle {
+                world_pos: [self.position.x, self.position.y, 0.0],
+                radius: TEST_PARTICLE_RADIUS,
+                color: TEST_PARTICLE_COLOR,
+            };
+        }
+
         Circle {
             world_pos: [self.position.x, self.position.y, 0.0],
             radius: self.mass / self.density,
@@ -53,19 +111,19 @@ impl Body {
         }
     }
 
-    pub fn compute_acceleration_to_other_body(&self, other: &Body) -> f32 {
-        // println!("{:?}, {:?}", self.position.x, other.position.x);
-        let dx = other.position.x - self.position.x;
+    /// Acceleration and jerk this body gets from a unit mass at `other`'s
+    /// position and speed under gravitational constant `g`, Plummer-softened
+    /// by `softening`. Scale by the mass of `other` for the pull it feels, or
+    /// negate and scale by this body's mass for the pull on `other`.
+    pub fn compute_acceleration_to_other_body(&self, other: &Body, g: f32, softening: f32) -> (Vector2<f32>, Vector2<f32>) {
+        let dx = other.position - self.position;
+        let dv = other.speed - self.speed;
 
-        let dy = other.position.y - self.position.y;
+        let distance = dx.magnitude2() + softening * softening;
+        let inv_distance3 = 1.0 / (distance * distance.sqrt());
 
-        let distance = dx * dx + dy * dy;
-
-        // println!("distance: {distance} {dx} {dy}");
-        if distance.floor() == 1.0 {
-            return 0.0;
-        }
-        let a = (G * self.mass * other.mass) / distance;
-        return a;
+        let acceleration = dx * (g * inv_distance3);
+        let jerk = (dv - dx * (3.0 * dx.dot(dv) / distance)) * (g * inv_distance3);
+        (acceleration, jerk)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,119 @@
+use cgmath::{InnerSpace, Vector2};
+use std::f32::consts::PI;
+use crate::nbody_sim::math::erf;
+use crate::nbody_sim::Body;
+
+/// A force each body feels on its own from its surroundings rather than from
+/// the other bodies, free to depend on the body's speed and parameters. It is
+/// evaluated alongside gravity with the speed at the start of the kick, so the
+/// step must stay well below the force's damping time. Contributes no jerk.
+pub trait BodyForce: Send + Sync {
+    fn acceleration(&self, body: &Body, g: f32) -> Vector2<f32>;
+}
+
+/// How the gas a `GasDrag` acts through moves.
+#[derive(Copy, Clone, Debug)]
+pub enum GasVelocity {
+    Uniform(Vector2<f32>),
+    /// Counter-clockwise rotation around `center`, slower than the circular
+    /// speed about `central_mass` by the fraction `headwind` because the gas is
+    /// partly held up by its pressure.
+    Keplerian {
+        center: Vector2<f32>,
+        central_mass: f32,
+        headwind: f32,
+    },
+}
+
+/// Aerodynamic drag on bodies with a nonzero `size`, a = -(v - v_gas) / t_s.
+/// Bodies smaller than 9/4 of the gas mean free path are in the Epstein regime,
+/// t_s = ρ_s s / (ρ_g v_th); larger ones in the Stokes regime,
+/// t_s = 4 ρ_s s² / (9 ρ_g v_th λ). `Body::material_density` is ρ_s.
+#[derive(Copy, Clone, Debug)]
+pub struct GasDrag {
+    pub gas_density: f32,
+    pub thermal_speed: f32,
+    pub mean_free_path: f32,
+    pub velocity: GasVelocity,
+}
+
+impl GasDrag {
+    /// Time for the drag to damp `body`'s speed relative to the gas, or `None`
+    /// if it feels no drag.
+    pub fn stopping_time(&self, body: &Body) -> Option<f32> {
+        if body.size <= 0.0 || body.test_particle {
+            return None;
+        }
+
+        let epstein = body.material_density * body.size / (self.gas_density * self.thermal_speed);
+        if body.size < 2.25 * self.mean_free_path {
+            Some(epstein)
+        } else {
+            Some(epstein * 4.0 * body.size / (9.0 * self.mean_free_path))
+        }
+    }
+
+    pub fn gas_velocity(&self, position: Vector2<f32>, g: f32) -> Vector2<f32> {
+        match self.velocity {
+            GasVelocity::Uniform(velocity) => velocity,
+            GasVelocity::Keplerian { center, central_mass, headwind } => {
+                let offset = position - center;
+                let r = offset.magnitude();
+                if r == 0.0 {
+                    return Vector2::new(0.0, 0.0);
+                }
+                let speed = (g * central_mass / r).sqrt() * (1.0 - headwind);
+                Vector2::new(-offset.y, offset.x) * (speed / r)
+            }
+        }
+    }
+}
+
+impl BodyForce for GasDrag {
+    fn acceleration(&self, body: &Body, g: f32) -> Vector2<f32> {
+        match self.stopping_time(body) {
+            Some(stopping_time) => -(body.speed - self.gas_velocity(body.position, g)) / stopping_time,
+            None => Vector2::new(0.0, 0.0),
+        }
+    }
+}
+
+/// Chandrasekhar dynamical friction on a body moving through the cored
+/// isothermal halo of `LogarithmicHaloPotential` with the same `center`,
+/// `circular_speed` and `core_radius` (taken as round):
+/// a = -4π G² M ρ ln Λ / v³ [erf(X) - 2X/√π e^(-X²)] v, with X = v / (√2 σ)
+/// and σ = v0 / √2. Scales with the body's own mass, so test particles feel none.
+#[derive(Copy, Clone, Debug)]
+pub struct DynamicalFriction {
+    pub center: Vector2<f32>,
+    pub circular_speed: f32,
+    pub core_radius: f32,
+    pub coulomb_logarithm: f32,
+}
+
+impl DynamicalFriction {
+    /// ρ = v0² (3 rc² + r²) / (4π G (rc² + r²)²)
+    pub fn halo_density(&self, position: Vector2<f32>, g: f32) -> f32 {
+        let r2 = (position - self.center).magnitude2();
+        let rc2 = self.core_radius * self.core_radius;
+        let v2 = self.circular_speed * self.circular_speed;
+        v2 * (3.0 * rc2 + r2) / (4.0 * PI * g * (rc2 + r2) * (rc2 + r2))
+    }
+}
+
+impl BodyForce for DynamicalFriction {
+    fn acceleration(&self, body: &Body, g: f32) -> Vector2<f32> {
+        let speed = body.speed.magnitude();
+        if body.mass == 0.0 || speed == 0.0 {
+            return Vector2::new(0.0, 0.0);
+        }
+
+        let dispersion = self.circular_speed / 2.0_f32.sqrt();
+        let x = speed / (2.0_f32.sqrt() * dispersion);
+        let slower_fraction = erf(x as f64) as f32 - 2.0 * x / PI.sqrt() * (-x * x).exp();
+        let magnitude = 4.0 * PI * g * g * body.mass * self.halo_density(body.position, g) * self.coulomb_logarithm * slower_fraction
+            / (speed * speed * speed);
+
+        body.speed * -magnitude
+    }
+}
@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fmt;
+use crate::nbody_sim::{ForceLaw, ForceSolver, Integrator};
+
+/// A combination of settings `Simulation` cannot run with or be set up from.
+/// The setter or constructor that would have produced it returns the error and
+/// leaves the simulation as it was.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigurationError {
+    /// `ForceSolver::ParticleMesh` without a periodic box to lay the mesh over.
+    ParticleMeshWithoutPeriodicBox,
+    /// `ForceSolver::ParticleMesh` with a pair force other than `Gravity`, the
+    /// only law its Poisson solve evaluates.
+    ParticleMeshWithoutNewtonianGravity,
+    /// `ForceSolver::FastMultipole` with a pair force other than
+    /// `LogarithmicGravity`, the only law it evaluates.
+    FastMultipoleWithoutLogarithmicGravity,
+    /// `Integrator::Hermite` with a force that has no jerk.
+    HermiteWithoutJerk,
+    /// `Integrator::Hermite` under a cosmology, whose expansion it does not apply.
+    HermiteWithCosmology,
+    /// Regularization with a pair force other than `Gravity` or a solver other
+    /// than `ForceSolver::Direct`; its pairs orbit under Newtonian gravity.
+    RegularizationWithoutDirectGravity,
+    /// Regularization with `Integrator::Hermite` or `Timestep::Block`, which
+    /// do not advance its pairs.
+    RegularizationWithoutLeapfrog,
+    /// Reversing the velocities under a cosmology, whose comoving momenta do
+    /// not retrace their steps while the universe keeps expanding.
+    ReversalWithCosmology,
+    /// A `GalaxyCollision` inclination other than 0 or π, which a planar
+    /// disk cannot take.
+    IntermediateInclination,
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigurationError::ParticleMeshWithoutPeriodicBox => write!(f, "the particle-mesh solver needs a periodic box"),
+            ConfigurationError::ParticleMeshWithoutNewtonianGravity => write!(f, "the particle-mesh solver needs gravity as the pair force"),
+            ConfigurationError::FastMultipoleWithoutLogarithmicGravity => write!(f, "the fast multipole solver needs logarithmic gravity as the pair force"),
+            ConfigurationError::HermiteWithoutJerk => write!(f, "the Hermite integrator needs forces with a jerk"),
+            ConfigurationError::HermiteWithCosmology => write!(f, "the Hermite integrator does not apply the cosmological expansion"),
+            ConfigurationError::RegularizationWithoutDirectGravity => write!(f, "regularization needs gravity as the pair force and the direct solver"),
+            ConfigurationError::RegularizationWithoutLeapfrog => write!(f, "regularization needs leapfrog on fixed or adaptive steps"),
+            ConfigurationError::ReversalWithCosmology => write!(f, "velocities cannot be reversed under a cosmology"),
+            ConfigurationError::IntermediateInclination => write!(f, "a planar disk is either prograde or retrograde"),
+        }
+    }
+}
+
+impl Error for ConfigurationError {}
+
+/// The settings that have to agree with each other, as a setter would leave them.
+#[derive(Copy, Clone)]
+pub(crate) struct Configuration {
+    pub integrator: Integrator,
+    /// Whether the timestep is `Timestep::Block`.
+    pub block: bool,
+    pub force_solver: ForceSolver,
+    pub periodic_box: bool,
+    pub pair_force: ForceLaw,
+    pub cosmology: bool,
+    pub post_newtonian: bool,
+    pub regularization: bool,
+    pub body_forces: bool,
+}
+
+impl Configuration {
+    pub fn check(&self) -> Result<(), ConfigurationError> {
+        let mesh = matches!(self.force_solver, ForceSolver::ParticleMesh(_));
+        let multipole = matches!(self.force_solver, ForceSolver::FastMultipole(_));
+        if mesh && !self.periodic_box {
+            return Err(ConfigurationError::ParticleMeshWithoutPeriodicBox);
+        }
+        if mesh && self.pair_force != ForceLaw::Newtonian {
+            return Err(ConfigurationError::ParticleMeshWithoutNewtonianGravity);
+        }
+        if multipole && self.pair_force != ForceLaw::Logarithmic {
+            return Err(ConfigurationError::FastMultipoleWithoutLogarithmicGravity);
+        }
+        if self.integrator == Integrator::Hermite && (mesh || multipole || self.post_newtonian || self.body_forces) {
+            return Err(ConfigurationError::HermiteWithoutJerk);
+        }
+        if self.integrator == Integrator::Hermite && self.cosmology {
+            return Err(ConfigurationError::HermiteWithCosmology);
+        }
+        if self.regularization && (self.pair_force != ForceLaw::Newtonian || !matches!(self.force_solver, ForceSolver::Direct)) {
+            return Err(ConfigurationError::RegularizationWithoutDirectGravity);
+        }
+        if self.regularization && (self.integrator == Integrator::Hermite || self.block) {
+            return Err(ConfigurationError::RegularizationWithoutLeapfrog);
+        }
+        Ok(())
+    }
+}
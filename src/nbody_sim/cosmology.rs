@@ -0,0 +1,101 @@
+use crate::nbody_sim::Body;
+
+/// Intervals of the Simpson rules behind the time and step-factor integrals.
+const INTEGRATION_STEPS: usize = 128;
+const SCALE_FACTOR_TOLERANCE: f64 = 1e-12;
+
+/// Friedmann background the comoving integration expands with. Time is in the
+/// same units as `1 / hubble_constant`; curvature is whatever `omega_matter`
+/// and `omega_lambda` leave over, zero for `flat`.
+#[derive(Copy, Clone, Debug)]
+pub struct Cosmology {
+    pub hubble_constant: f64,
+    pub omega_matter: f64,
+    pub omega_lambda: f64,
+}
+
+/// Bodies as they were when the integration reached one of the requested
+/// output redshifts.
+#[derive(Clone)]
+pub struct CosmologicalOutput {
+    pub redshift: f64,
+    pub scale_factor: f64,
+    pub time: f64,
+    pub bodies: Vec<Body>,
+}
+
+impl Cosmology {
+    /// ΛCDM with Ω_Λ = 1 - Ω_m.
+    pub fn flat(hubble_constant: f64, omega_matter: f64) -> Self {
+        Cosmology {
+            hubble_constant,
+            omega_matter,
+            omega_lambda: 1.0 - omega_matter,
+        }
+    }
+
+    fn omega_curvature(&self) -> f64 {
+        1.0 - self.omega_matter - self.omega_lambda
+    }
+
+    /// Hubble rate H(a) = H0 sqrt(Ω_m a⁻³ + Ω_k a⁻² + Ω_Λ).
+    pub fn hubble(&self, scale_factor: f64) -> f64 {
+        let a = scale_factor;
+        self.hubble_constant * (self.omega_matter / (a * a * a) + self.omega_curvature() / (a * a) + self.omega_lambda).sqrt()
+    }
+
+    /// Cosmic time since a = 0, ∫ da / (a H). Integrated in u = sqrt(a), where
+    /// the integrand stays finite at the big bang.
+    pub fn time(&self, scale_factor: f64) -> f64 {
+        simpson(0.0, scale_factor.sqrt(), |u| {
+            if u == 0.0 {
+                return 0.0;
+            }
+            let a = u * u;
+            2.0 / (u * self.hubble(a))
+        })
+    }
+
+    /// Scale factor at cosmic time `time`, by Newton iteration on `time`.
+    pub fn scale_factor(&self, time: f64) -> f64 {
+        // Matter-dominated guess, a = (3/2 H0 sqrt(Ω_m) t)^(2/3).
+        let mut a = (1.5 * self.hubble_constant * self.omega_matter.sqrt() * time).powf(2.0 / 3.0).max(1e-8);
+        for _ in 0..50 {
+            let step = (time - self.time(a)) * a * self.hubble(a);
+            a = (a + step).max(0.5 * a);
+            if step.abs() < SCALE_FACTOR_TOLERANCE * a {
+                break;
+            }
+        }
+        a
+    }
+
+    /// ∫ dt / a² between two scale factors: what the canonical momentum is
+    /// multiplied by to drift the comoving position.
+    pub fn drift_factor(&self, from: f64, to: f64) -> f64 {
+        simpson(from, to, |a| 1.0 / (a * a * a * self.hubble(a)))
+    }
+
+    /// ∫ dt / a between two scale factors: what the comoving acceleration is
+    /// multiplied by to kick the canonical momentum.
+    pub fn kick_factor(&self, from: f64, to: f64) -> f64 {
+        simpson(from, to, |a| 1.0 / (a * a * self.hubble(a)))
+    }
+}
+
+pub fn redshift_to_scale_factor(redshift: f64) -> f64 {
+    1.0 / (1.0 + redshift)
+}
+
+pub fn scale_factor_to_redshift(scale_factor: f64) -> f64 {
+    1.0 / scale_factor - 1.0
+}
+
+fn simpson(from: f64, to: f64, f: impl Fn(f64) -> f64) -> f64 {
+    let h = (to - from) / INTEGRATION_STEPS as f64;
+    let mut sum = f(from) + f(to);
+    for i in 1..INTEGRATION_STEPS {
+        sum += f(from + i as f64 * h) * if i % 2 == 1 { 4.0 } else { 2.0 };
+    }
+    sum * h / 3.0
+}
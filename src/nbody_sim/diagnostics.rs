@@ -0,0 +1,241 @@
+use cgmath::{InnerSpace, Vector2};
+use std::f32::consts::PI;
+use std::io::{self, Write};
+use crate::nbody_sim::Body;
+
+const DEFAULT_PROFILE_BINS: usize = 20;
+/// γ in the Coulomb logarithm ln(γ N) of the relaxation time, the value for
+/// equal-mass clusters.
+const DEFAULT_COULOMB_GAMMA: f32 = 0.11;
+/// Neighbours behind each body's local density for the density centre.
+const DENSITY_NEIGHBOURS: usize = 6;
+/// Mass fractions of the reported Lagrangian radii.
+pub const LAGRANGIAN_FRACTIONS: [f32; 3] = [0.1, 0.5, 0.9];
+
+/// What the radii and profiles of `ClusterAnalysis` are measured from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProfileCenter {
+    CenterOfMass,
+    /// Casertano-Hut density centre: bodies weighted by the surface density
+    /// within their sixth nearest neighbour, which follows the core rather
+    /// than the escapers.
+    DensityCenter,
+}
+
+/// Settings for the cluster diagnostics, see `Simulation::cluster_diagnostics`.
+#[derive(Copy, Clone, Debug)]
+pub struct ClusterAnalysis {
+    pub center: ProfileCenter,
+    /// Equal-width annuli of the profiles.
+    pub bins: usize,
+    /// Outer edge of the profiles; the farthest body if `None`.
+    pub outer_radius: Option<f32>,
+    pub coulomb_gamma: f32,
+}
+
+/// Annulus of a radial profile.
+#[derive(Copy, Clone, Debug)]
+pub struct ProfileBin {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub surface_density: f32,
+    /// Mass-weighted RMS speed about the cluster's mean velocity, both
+    /// components together; zero for an empty annulus.
+    pub velocity_dispersion: f32,
+}
+
+/// State of the massive bodies as one cluster at `time`.
+#[derive(Clone, Debug)]
+pub struct ClusterDiagnostics {
+    pub time: f64,
+    pub center: Vector2<f32>,
+    /// Radii holding the `LAGRANGIAN_FRACTIONS` of the mass.
+    pub lagrangian_radii: [f32; 3],
+    /// Spitzer's half-mass relaxation time,
+    /// 0.138 N^½ r_h^3/2 / (m^½ G^½ ln(γ N)), with the projected half-mass radius.
+    pub relaxation_time: f32,
+    /// Kinetic energy in the centre-of-mass frame.
+    pub kinetic_energy: f32,
+    /// Energy of the bodies' pull on each other, without external potentials.
+    pub potential_energy: f32,
+    /// 2K / |W|, 1 in virial equilibrium.
+    pub virial_ratio: f32,
+    pub profile: Vec<ProfileBin>,
+}
+
+impl ClusterAnalysis {
+    pub fn new(center: ProfileCenter) -> Self {
+        ClusterAnalysis {
+            center,
+            bins: DEFAULT_PROFILE_BINS,
+            outer_radius: None,
+            coulomb_gamma: DEFAULT_COULOMB_GAMMA,
+        }
+    }
+
+    /// Diagnostics of the massive `bodies` at `time`, given the potential
+    /// energy of their pull on each other. `separation` gives the vector
+    /// between two bodies, so a periodic box can use the nearest image.
+    pub fn analyze(
+        &self,
+        bodies: &[Body],
+        g: f32,
+        potential_energy: f32,
+        time: f64,
+        separation: impl Fn(&Body, &Body) -> Vector2<f32>,
+    ) -> ClusterDiagnostics {
+        let massive = bodies.iter().filter(|body| !body.test_particle).collect::<Vec<_>>();
+        let mass = massive.iter().map(|body| body.mass).sum::<f32>();
+        if massive.is_empty() || mass <= 0.0 {
+            return ClusterDiagnostics {
+                time,
+                center: Vector2::new(0.0, 0.0),
+                lagrangian_radii: [0.0; 3],
+                relaxation_time: 0.0,
+                kinetic_energy: 0.0,
+                potential_energy,
+                virial_ratio: 0.0,
+                profile: Vec::new(),
+            };
+        }
+
+        // Offsets from the first body keep a cluster straddling a periodic
+        // boundary in one piece.
+        let origin = massive[0];
+        let offsets = massive.iter().map(|body| separation(origin, body)).collect::<Vec<_>>();
+        let speed = massive.iter().map(|body| body.speed * body.mass).sum::<Vector2<f32>>() / mass;
+
+        let center = match self.center {
+            ProfileCenter::CenterOfMass => {
+                massive.iter().zip(offsets.iter()).map(|(body, &offset)| offset * body.mass).sum::<Vector2<f32>>() / mass
+            }
+            ProfileCenter::DensityCenter => density_center(&massive, &offsets),
+        };
+
+        let mut by_radius = massive.iter()
+            .zip(offsets.iter())
+            .map(|(body, &offset)| ((offset - center).magnitude(), *body))
+            .collect::<Vec<_>>();
+        by_radius.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut lagrangian_radii = [0.0; 3];
+        for (radius, &fraction) in lagrangian_radii.iter_mut().zip(LAGRANGIAN_FRACTIONS.iter()) {
+            let mut enclosed = 0.0;
+            for &(distance, body) in by_radius.iter() {
+                enclosed += body.mass;
+                *radius = distance;
+                if enclosed >= fraction * mass {
+                    break;
+                }
+            }
+        }
+
+        let n = massive.len() as f32;
+        let half_mass_radius = lagrangian_radii[1];
+        let coulomb_logarithm = (self.coulomb_gamma * n).ln().max(1.0);
+        let relaxation_time = 0.138 * n.sqrt() * half_mass_radius.powf(1.5) / ((mass / n * g).sqrt() * coulomb_logarithm);
+
+        let kinetic_energy = massive.iter().map(|body| 0.5 * body.mass * (body.speed - speed).magnitude2()).sum::<f32>();
+        let virial_ratio = if potential_energy != 0.0 { 2.0 * kinetic_energy / potential_energy.abs() } else { 0.0 };
+
+        let outer_radius = self.outer_radius.unwrap_or(by_radius.last().map_or(0.0, |&(distance, _)| distance));
+        let profile = profile(&by_radius, speed, outer_radius, self.bins.max(1));
+
+        ClusterDiagnostics {
+            time,
+            center: origin.position + center,
+            lagrangian_radii,
+            relaxation_time,
+            kinetic_energy,
+            potential_energy,
+            virial_ratio,
+            profile,
+        }
+    }
+}
+
+impl ClusterDiagnostics {
+    pub fn write_csv_header(mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "time,center_x,center_y,lagrangian_10,lagrangian_50,lagrangian_90,relaxation_time,kinetic_energy,potential_energy,virial_ratio")
+    }
+
+    /// One line of the time series started by `write_csv_header`.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        let [r10, r50, r90] = self.lagrangian_radii;
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{}",
+            self.time, self.center.x, self.center.y, r10, r50, r90,
+            self.relaxation_time, self.kinetic_energy, self.potential_energy, self.virial_ratio,
+        )
+    }
+
+    pub fn write_profile_csv_header(mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "time,bin,inner_radius,outer_radius,surface_density,velocity_dispersion")
+    }
+
+    /// One line per annulus, so successive snapshots append to one file.
+    pub fn write_profile_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        for (n, bin) in self.profile.iter().enumerate() {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                self.time, n, bin.inner_radius, bin.outer_radius, bin.surface_density, bin.velocity_dispersion,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Density-weighted mean of the offsets, each body's surface density taken
+/// from the circle reaching its `DENSITY_NEIGHBOURS`th nearest neighbour.
+fn density_center(bodies: &[&Body], offsets: &[Vector2<f32>]) -> Vector2<f32> {
+    let k = DENSITY_NEIGHBOURS.min(bodies.len() - 1);
+    if k == 0 {
+        return offsets[0];
+    }
+
+    let mut weighted = Vector2::new(0.0, 0.0);
+    let mut total = 0.0;
+    for (i, &offset) in offsets.iter().enumerate() {
+        let mut distances = offsets.iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(j, &other)| ((other - offset).magnitude2(), bodies[j].mass))
+            .collect::<Vec<_>>();
+        distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // The k-th neighbour sits on the edge, so only the k - 1 inside count.
+        let enclosed = distances[..k - 1].iter().map(|&(_, mass)| mass).sum::<f32>();
+        let density = enclosed.max(f32::MIN_POSITIVE) / (PI * distances[k - 1].0.max(f32::MIN_POSITIVE));
+        weighted += offset * density;
+        total += density;
+    }
+    weighted / total
+}
+
+fn profile(by_radius: &[(f32, &Body)], speed: Vector2<f32>, outer_radius: f32, bins: usize) -> Vec<ProfileBin> {
+    let width = outer_radius / bins as f32;
+    let mut mass = vec![0.0; bins];
+    let mut dispersion = vec![0.0; bins];
+    for &(distance, body) in by_radius.iter() {
+        // Bodies right on the outer edge go in the last annulus.
+        let bin = if width > 0.0 { (distance / width) as usize } else { 0 };
+        let bin = if distance <= outer_radius { bin.min(bins - 1) } else { continue };
+        mass[bin] += body.mass;
+        dispersion[bin] += body.mass * (body.speed - speed).magnitude2();
+    }
+
+    (0..bins)
+        .map(|n| {
+            let (inner, outer) = (n as f32 * width, (n + 1) as f32 * width);
+            let area = PI * (outer * outer - inner * inner);
+            ProfileBin {
+                inner_radius: inner,
+                outer_radius: outer,
+                surface_density: if area > 0.0 { mass[n] / area } else { 0.0 },
+                velocity_dispersion: if mass[n] > 0.0 { (dispersion[n] / mass[n]).sqrt() } else { 0.0 },
+            }
+        })
+        .collect()
+}
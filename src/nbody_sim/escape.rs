@@ -0,0 +1,84 @@
+use cgmath::{InnerSpace, Vector2};
+use crate::nbody_sim::Body;
+
+/// When a body counts as having left the cluster, measured from the centre of
+/// mass of the massive bodies.
+#[derive(Copy, Clone, Debug)]
+pub enum EscapeCriterion {
+    /// Positive energy per unit mass relative to the cluster, while farther out
+    /// than `half_mass_radii` times the half-mass radius.
+    Unbound { half_mass_radii: f32 },
+    /// Farther out than the radius, bound or not.
+    Radius(f32),
+}
+
+/// A body removed as an escaper, as it was when removed.
+#[derive(Copy, Clone)]
+pub struct Escaper {
+    pub time: f64,
+    /// Index of the body just before it was removed; later bodies moved down.
+    pub index: usize,
+    pub body: Body,
+    pub distance: f32,
+    /// Kinetic energy relative to the cluster's mean velocity plus potential
+    /// energy, per unit mass.
+    pub specific_energy: f32,
+}
+
+/// Totals over the escapers removed since escaper removal was turned on.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MassLoss {
+    pub escapers: usize,
+    pub mass: f32,
+    /// Of the mass present when removal was turned on.
+    pub fraction: f32,
+    /// Mean mass lost per unit time since removal was turned on.
+    pub rate: f32,
+}
+
+impl EscapeCriterion {
+    /// Indices and distances of the bodies outside the escape radius, and the
+    /// mean velocity of the massive bodies. Which of them are unbound is up to
+    /// the caller, who knows the potential. `separation` gives the vector
+    /// between two bodies, so a periodic box can use the nearest image.
+    pub(crate) fn distant_bodies(&self, bodies: &[Body], separation: impl Fn(&Body, &Body) -> Vector2<f32>) -> (Vector2<f32>, Vec<(usize, f32)>) {
+        let massive = (0..bodies.len()).filter(|&i| !bodies[i].test_particle).collect::<Vec<_>>();
+        let mass = massive.iter().map(|&i| bodies[i].mass).sum::<f32>();
+        if massive.is_empty() || mass <= 0.0 {
+            return (Vector2::new(0.0, 0.0), Vec::new());
+        }
+
+        let origin = &bodies[massive[0]];
+        let center = massive.iter().map(|&i| separation(origin, &bodies[i]) * bodies[i].mass).sum::<Vector2<f32>>() / mass;
+        let speed = massive.iter().map(|&i| bodies[i].speed * bodies[i].mass).sum::<Vector2<f32>>() / mass;
+        let distances = bodies.iter().map(|body| (separation(origin, body) - center).magnitude()).collect::<Vec<_>>();
+
+        let radius = match *self {
+            EscapeCriterion::Radius(radius) => radius,
+            EscapeCriterion::Unbound { half_mass_radii } => {
+                let mut by_radius = massive.iter().map(|&i| (distances[i], bodies[i].mass)).collect::<Vec<_>>();
+                by_radius.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let mut enclosed = 0.0;
+                let mut half_mass_radius = 0.0;
+                for (distance, body_mass) in by_radius {
+                    enclosed += body_mass;
+                    half_mass_radius = distance;
+                    if enclosed >= 0.5 * mass {
+                        break;
+                    }
+                }
+                half_mass_radii * half_mass_radius
+            }
+        };
+
+        let distant = distances.into_iter()
+            .enumerate()
+            .filter(|&(_, distance)| distance > radius)
+            .collect();
+        (speed, distant)
+    }
+
+    pub fn requires_unbound(&self) -> bool {
+        matches!(self, EscapeCriterion::Unbound { .. })
+    }
+}
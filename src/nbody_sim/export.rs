@@ -0,0 +1,169 @@
+use std::io::{self, Write};
+use crate::nbody_sim::Body;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A header line, then one line per body per snapshot.
+    Csv,
+    /// One JSON object per body per snapshot.
+    JsonLines,
+}
+
+/// The columns to write. `id` is the body's `Body::id`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExportFields {
+    pub id: bool,
+    pub time: bool,
+    pub position: bool,
+    pub velocity: bool,
+    pub acceleration: bool,
+    pub mass: bool,
+    pub density: bool,
+    pub test_particle: bool,
+}
+
+/// How often `Simulation` writes a snapshot while exporting.
+#[derive(Copy, Clone, Debug)]
+pub enum ExportCadence {
+    EveryUpdates(u32),
+    /// After the first update at least this much simulated time after the
+    /// last snapshot.
+    EveryTime(f64),
+}
+
+/// Body states written as CSV or JSON lines, one snapshot at a time, so runs
+/// of any length stream to a file without being held in memory.
+#[derive(Copy, Clone, Debug)]
+pub struct BodyExport {
+    pub format: ExportFormat,
+    pub fields: ExportFields,
+    pub cadence: ExportCadence,
+}
+
+impl ExportFields {
+    pub fn all() -> Self {
+        ExportFields {
+            id: true,
+            time: true,
+            position: true,
+            velocity: true,
+            acceleration: true,
+            mass: true,
+            density: true,
+            test_particle: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        ExportFields {
+            id: false,
+            time: false,
+            position: false,
+            velocity: false,
+            acceleration: false,
+            mass: false,
+            density: false,
+            test_particle: false,
+        }
+    }
+
+    fn columns(&self) -> Vec<&'static str> {
+        let mut columns = Vec::new();
+        let groups: [(bool, &[&'static str]); 8] = [
+            (self.id, &["id"]),
+            (self.time, &["t"]),
+            (self.position, &["x", "y"]),
+            (self.velocity, &["vx", "vy"]),
+            (self.acceleration, &["ax", "ay"]),
+            (self.mass, &["mass"]),
+            (self.density, &["density"]),
+            (self.test_particle, &["test_particle"]),
+        ];
+        for (selected, names) in groups {
+            if selected {
+                columns.extend_from_slice(names);
+            }
+        }
+        columns
+    }
+}
+
+impl Default for ExportFields {
+    fn default() -> Self {
+        ExportFields::all()
+    }
+}
+
+impl BodyExport {
+    pub fn new(format: ExportFormat, cadence: ExportCadence) -> Self {
+        BodyExport {
+            format,
+            fields: ExportFields::all(),
+            cadence,
+        }
+    }
+
+    /// The CSV header line; JSON lines have none.
+    pub fn write_header(&self, mut writer: impl Write) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Csv => writeln!(writer, "{}", self.fields.columns().join(",")),
+            ExportFormat::JsonLines => Ok(()),
+        }
+    }
+
+    pub fn write_snapshot(&self, mut writer: impl Write, bodies: &[Body], time: f64) -> io::Result<()> {
+        let columns = self.fields.columns();
+        for body in bodies.iter() {
+            let values = self.values(body, time);
+            match self.format {
+                ExportFormat::Csv => writeln!(writer, "{}", values.join(","))?,
+                ExportFormat::JsonLines => {
+                    let pairs = columns.iter()
+                        .zip(values)
+                        .map(|(column, value)| format!("\"{}\":{}", column, value))
+                        .collect::<Vec<_>>();
+                    writeln!(writer, "{{{}}}", pairs.join(","))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Values in the order of `ExportFields::columns`, formatted for the
+    /// output: JSON has no NaN or infinity and gets `null` instead.
+    fn values(&self, body: &Body, time: f64) -> Vec<String> {
+        let json = self.format == ExportFormat::JsonLines;
+        let finite = |text: String, finite: bool| if finite || !json { text } else { "null".to_string() };
+        // Each at its own precision, so an f32 0.1 prints as 0.1.
+        let number = |value: f64| finite(value.to_string(), value.is_finite());
+        let float = |value: f32| finite(value.to_string(), value.is_finite());
+
+        let fields = &self.fields;
+        let mut values = Vec::new();
+        if fields.id {
+            values.push(body.id.to_string());
+        }
+        if fields.time {
+            values.push(number(time));
+        }
+        if fields.position {
+            values.extend([float(body.position.x), float(body.position.y)]);
+        }
+        if fields.velocity {
+            values.extend([float(body.speed.x), float(body.speed.y)]);
+        }
+        if fields.acceleration {
+            values.extend([float(body.acceleration.x), float(body.acceleration.y)]);
+        }
+        if fields.mass {
+            values.push(float(body.mass));
+        }
+        if fields.density {
+            values.push(float(body.density));
+        }
+        if fields.test_particle {
+            values.push(if json { body.test_particle.to_string() } else { (body.test_particle as u8).to_string() });
+        }
+        values
+    }
+}
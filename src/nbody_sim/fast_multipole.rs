@@ -0,0 +1,286 @@
+use cgmath::Vector2;
+use num_complex::Complex;
+use crate::nbody_sim::Body;
+
+const DEFAULT_LEAF_SIZE: usize = 32;
+/// Interaction lists start two levels below the root.
+const MIN_LEVEL: u32 = 2;
+const MAX_LEVEL: u32 = 10;
+
+/// Fast multipole method for two-dimensional (logarithmic) gravity, the law of
+/// `LogarithmicGravity`. Bodies are binned into a uniform quadtree whose depth
+/// gives about `leaf_size` bodies per leaf; far boxes interact through complex
+/// multipole and local expansions truncated after `order` terms, neighbouring
+/// leaves through the softened direct sum. The error falls roughly as 2^-order.
+#[derive(Copy, Clone, Debug)]
+pub struct FastMultipole {
+    pub order: usize,
+    pub leaf_size: usize,
+}
+
+impl FastMultipole {
+    pub fn new(order: usize) -> Self {
+        FastMultipole {
+            order,
+            leaf_size: DEFAULT_LEAF_SIZE,
+        }
+    }
+
+    /// Acceleration of every body from all the others under gravitational
+    /// constant `g`. Test particles have no mass and act on nothing.
+    pub fn accelerations(&self, bodies: &[Body], g: f32, softening: f32) -> Vec<Vector2<f32>> {
+        if bodies.is_empty() {
+            return Vec::new();
+        }
+
+        let p = self.order.max(1);
+        let tree = Tree::new(bodies, self.levels(bodies.len()));
+        let binomial = binomial_table(2 * p);
+        let positions = bodies.iter()
+            .map(|body| Complex::new(body.position.x as f64, body.position.y as f64))
+            .collect::<Vec<_>>();
+
+        // Leaf multipoles: a_0 log(z - c) + Σ a_k / (z - c)^k.
+        let leaves = tree.levels;
+        let mut multipoles = (0..=leaves).map(|level| vec![Complex::new(0.0, 0.0); tree.boxes(level) * (p + 1)]).collect::<Vec<_>>();
+        for (leaf, members) in tree.leaves.iter().enumerate() {
+            let center = tree.center(leaves, leaf);
+            let coefficients = &mut multipoles[leaves as usize][leaf * (p + 1)..(leaf + 1) * (p + 1)];
+            for &i in members.iter() {
+                let mass = bodies[i].mass as f64;
+                let offset = positions[i] - center;
+                let mut power = Complex::new(1.0, 0.0);
+                coefficients[0] += mass;
+                for (k, coefficient) in coefficients.iter_mut().enumerate().skip(1) {
+                    power *= offset;
+                    *coefficient -= power * (mass / k as f64);
+                }
+            }
+        }
+
+        // Multipole to multipole, leaves up to the coarsest level with an interaction list.
+        for level in (MIN_LEVEL..leaves).rev() {
+            let (coarse, fine) = multipoles.split_at_mut(level as usize + 1);
+            let (parents, children) = (&mut coarse[level as usize], &fine[0]);
+            for child in 0..tree.boxes(level + 1) {
+                let parent = tree.parent(level + 1, child);
+                let shift = tree.center(level + 1, child) - tree.center(level, parent);
+                let a = &children[child * (p + 1)..(child + 1) * (p + 1)];
+                let b = &mut parents[parent * (p + 1)..(parent + 1) * (p + 1)];
+
+                let powers = powers(shift, p);
+                b[0] += a[0];
+                for l in 1..=p {
+                    let mut term = -a[0] * powers[l] / l as f64;
+                    for k in 1..=l {
+                        term += a[k] * powers[l - k] * binomial[l - 1][k - 1];
+                    }
+                    b[l] += term;
+                }
+            }
+        }
+
+        // Multipole to local over each box's interaction list, then local to
+        // local down to the leaves. Only the derivative is needed, so the
+        // constant term is skipped.
+        let mut locals = (0..=leaves).map(|level| vec![Complex::new(0.0, 0.0); tree.boxes(level) * (p + 1)]).collect::<Vec<_>>();
+        for level in MIN_LEVEL..=leaves {
+            for target in 0..tree.boxes(level) {
+                let target_center = tree.center(level, target);
+                for source in tree.interaction_list(level, target) {
+                    let inverse = 1.0 / (tree.center(level, source) - target_center);
+                    let a = &multipoles[level as usize][source * (p + 1)..(source + 1) * (p + 1)];
+
+                    let inverse_powers = powers(inverse, p);
+                    let terms = (0..=p)
+                        .map(|k| if k % 2 == 0 { a[k] * inverse_powers[k] } else { -a[k] * inverse_powers[k] })
+                        .collect::<Vec<_>>();
+
+                    let b = &mut locals[level as usize][target * (p + 1)..(target + 1) * (p + 1)];
+                    for l in 1..=p {
+                        let mut sum = -a[0] / l as f64;
+                        for k in 1..=p {
+                            sum += terms[k] * binomial[l + k - 1][k - 1];
+                        }
+                        b[l] += sum * inverse_powers[l];
+                    }
+                }
+            }
+
+            if level < leaves {
+                let (coarse, fine) = locals.split_at_mut(level as usize + 1);
+                let (parents, children) = (&coarse[level as usize], &mut fine[0]);
+                for child in 0..tree.boxes(level + 1) {
+                    let parent = tree.parent(level + 1, child);
+                    let shift = tree.center(level + 1, child) - tree.center(level, parent);
+                    let c = &parents[parent * (p + 1)..(parent + 1) * (p + 1)];
+                    let b = &mut children[child * (p + 1)..(child + 1) * (p + 1)];
+
+                    let powers = powers(shift, p);
+                    for m in 1..=p {
+                        for k in m..=p {
+                            b[m] += c[k] * powers[k - m] * binomial[k][m];
+                        }
+                    }
+                }
+            }
+        }
+
+        // Far field from the leaf expansions, near field pair by pair.
+        let g = g as f64;
+        let softening2 = (softening as f64) * (softening as f64);
+        let mut accelerations = vec![Vector2::new(0.0, 0.0); bodies.len()];
+        for (leaf, members) in tree.leaves.iter().enumerate() {
+            let center = tree.center(leaves, leaf);
+            let c = &locals[leaves as usize][leaf * (p + 1)..(leaf + 1) * (p + 1)];
+            let neighbours = tree.neighbours(leaves, leaf);
+
+            for &i in members.iter() {
+                // a = -G conj(φ'(z)) for φ(z) = Σ m log(z - z_j).
+                let offset = positions[i] - center;
+                let mut derivative = Complex::new(0.0, 0.0);
+                for k in (1..=p).rev() {
+                    derivative = derivative * offset + c[k] * k as f64;
+                }
+                let mut acceleration = -derivative.conj() * g;
+
+                for &neighbour in neighbours.iter() {
+                    for &j in tree.leaves[neighbour].iter() {
+                        if j == i {
+                            continue;
+                        }
+                        let separation = positions[j] - positions[i];
+                        acceleration += separation * (g * bodies[j].mass as f64 / (separation.norm_sqr() + softening2));
+                    }
+                }
+
+                accelerations[i] = Vector2::new(acceleration.re as f32, acceleration.im as f32);
+            }
+        }
+
+        accelerations
+    }
+
+    /// Depth of the uniform tree for `bodies` bodies.
+    fn levels(&self, bodies: usize) -> u32 {
+        let mut level = MIN_LEVEL;
+        while level < MAX_LEVEL && bodies > self.leaf_size.max(1) << (2 * level) {
+            level += 1;
+        }
+        level
+    }
+}
+
+/// Uniform quadtree over the bodies' bounding square. Boxes at `level` form a
+/// 2^level × 2^level grid, numbered row by row.
+struct Tree {
+    levels: u32,
+    corner: Complex<f64>,
+    size: f64,
+    leaves: Vec<Vec<usize>>,
+}
+
+impl Tree {
+    fn new(bodies: &[Body], levels: u32) -> Self {
+        let (mut min, mut max) = (bodies[0].position, bodies[0].position);
+        for body in bodies.iter() {
+            min = Vector2::new(min.x.min(body.position.x), min.y.min(body.position.y));
+            max = Vector2::new(max.x.max(body.position.x), max.y.max(body.position.y));
+        }
+        // Padded so bodies on the far edge still fall inside the last leaf.
+        let size = ((max.x - min.x).max(max.y - min.y) as f64).max(f64::EPSILON) * 1.000_001;
+        let corner = Complex::new(min.x as f64, min.y as f64);
+
+        let mut tree = Tree {
+            levels,
+            corner,
+            size,
+            leaves: vec![Vec::new(); 1 << (2 * levels)],
+        };
+
+        let side = tree.side(levels);
+        let last = (1 << levels) - 1;
+        for (i, body) in bodies.iter().enumerate() {
+            let ix = (((body.position.x as f64 - corner.re) / side) as usize).min(last);
+            let iy = (((body.position.y as f64 - corner.im) / side) as usize).min(last);
+            tree.leaves[iy * (last + 1) + ix].push(i);
+        }
+
+        tree
+    }
+
+    fn boxes(&self, level: u32) -> usize {
+        1 << (2 * level)
+    }
+
+    fn side(&self, level: u32) -> f64 {
+        self.size / (1u64 << level) as f64
+    }
+
+    fn center(&self, level: u32, index: usize) -> Complex<f64> {
+        let per_side = 1 << level;
+        let side = self.side(level);
+        self.corner + Complex::new(((index % per_side) as f64 + 0.5) * side, ((index / per_side) as f64 + 0.5) * side)
+    }
+
+    fn parent(&self, level: u32, index: usize) -> usize {
+        let per_side = 1 << level;
+        let (x, y) = (index % per_side, index / per_side);
+        (y / 2) * (per_side / 2) + x / 2
+    }
+
+    /// The box and the up to eight boxes touching it.
+    fn neighbours(&self, level: u32, index: usize) -> Vec<usize> {
+        let per_side = 1i64 << level;
+        let (x, y) = ((index as i64) % per_side, (index as i64) / per_side);
+        let mut neighbours = Vec::with_capacity(9);
+        for ny in (y - 1).max(0)..=(y + 1).min(per_side - 1) {
+            for nx in (x - 1).max(0)..=(x + 1).min(per_side - 1) {
+                neighbours.push((ny * per_side + nx) as usize);
+            }
+        }
+        neighbours
+    }
+
+    /// Children of the parent's neighbours that are not neighbours themselves:
+    /// well separated, but not yet handled at a coarser level.
+    fn interaction_list(&self, level: u32, index: usize) -> Vec<usize> {
+        let per_side = 1i64 << level;
+        let (x, y) = ((index as i64) % per_side, (index as i64) / per_side);
+        let mut list = Vec::with_capacity(27);
+        for parent in self.neighbours(level - 1, self.parent(level, index)) {
+            let (px, py) = ((parent as i64) % (per_side / 2), (parent as i64) / (per_side / 2));
+            for cy in 2 * py..2 * py + 2 {
+                for cx in 2 * px..2 * px + 2 {
+                    if (cx - x).abs() > 1 || (cy - y).abs() > 1 {
+                        list.push((cy * per_side + cx) as usize);
+                    }
+                }
+            }
+        }
+        list
+    }
+}
+
+/// `z^0` to `z^p`.
+fn powers(z: Complex<f64>, p: usize) -> Vec<Complex<f64>> {
+    let mut powers = Vec::with_capacity(p + 1);
+    let mut power = Complex::new(1.0, 0.0);
+    for _ in 0..=p {
+        powers.push(power);
+        power *= z;
+    }
+    powers
+}
+
+/// Pascal's triangle up to row `n`, `table[n][k]` being n choose k.
+fn binomial_table(n: usize) -> Vec<Vec<f64>> {
+    let mut table = vec![vec![0.0; n + 1]; n + 1];
+    for row in 0..=n {
+        table[row][0] = 1.0;
+        for k in 1..=row {
+            table[row][k] = table[row - 1][k - 1] + table[row - 1][k];
+        }
+    }
+    table
+}
@@ -0,0 +1,211 @@
+use cgmath::{InnerSpace, Vector2};
+use crate::nbody_sim::Body;
+
+/// Simulation-wide settings a pair force may use.
+#[derive(Copy, Clone, Debug)]
+pub struct ForceParameters {
+    pub gravitational_constant: f32,
+    pub softening: f32,
+}
+
+/// Laws the solvers that skip the pair force evaluate on their own, so a
+/// simulation can check its pair force agrees with them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ForceLaw {
+    /// `Gravity`.
+    Newtonian,
+    /// `LogarithmicGravity`.
+    Logarithmic,
+    Other,
+}
+
+/// The interaction between two bodies that `Simulation::update` sums over
+/// every pair. Test particles never act as a source.
+pub trait PairForce: Send + Sync {
+    /// Acceleration and jerk `body` gets from `other`.
+    fn acceleration(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> (Vector2<f32>, Vector2<f32>);
+
+    fn potential_energy(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> f32;
+
+    /// `c` for forces whose potential energy is `c / r` at long range, which lets
+    /// a periodic box add the Ewald correction for the distant images.
+    fn inverse_distance_coupling(&self, _body: &Body, _other: &Body, _parameters: &ForceParameters) -> Option<f32> {
+        None
+    }
+
+    fn law(&self) -> ForceLaw {
+        ForceLaw::Other
+    }
+
+    /// Acceleration and jerk of `a` and of `b` from each other. Override when
+    /// both halves share most of the work.
+    fn pair_acceleration(&self, a: &Body, b: &Body, parameters: &ForceParameters) -> [(Vector2<f32>, Vector2<f32>); 2] {
+        [self.acceleration(a, b, parameters), self.acceleration(b, a, parameters)]
+    }
+}
+
+/// Newtonian gravity, using the simulation's gravitational constant and softening.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Gravity;
+
+impl PairForce for Gravity {
+    fn acceleration(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> (Vector2<f32>, Vector2<f32>) {
+        let (a, jerk) = body.compute_acceleration_to_other_body(other, parameters.gravitational_constant, parameters.softening);
+        (a * other.mass, jerk * other.mass)
+    }
+
+    fn potential_energy(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> f32 {
+        let distance = (other.position - body.position).magnitude2() + parameters.softening * parameters.softening;
+        -parameters.gravitational_constant * body.mass * other.mass / distance.sqrt()
+    }
+
+    fn inverse_distance_coupling(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> Option<f32> {
+        Some(-parameters.gravitational_constant * body.mass * other.mass)
+    }
+
+    fn law(&self) -> ForceLaw {
+        ForceLaw::Newtonian
+    }
+
+    fn pair_acceleration(&self, a: &Body, b: &Body, parameters: &ForceParameters) -> [(Vector2<f32>, Vector2<f32>); 2] {
+        let (acceleration, jerk) = a.compute_acceleration_to_other_body(b, parameters.gravitational_constant, parameters.softening);
+        [(acceleration * b.mass, jerk * b.mass), (acceleration * -a.mass, jerk * -a.mass)]
+    }
+}
+
+/// Two-dimensional gravity: the pull falls off as 1/r and the potential is
+/// G m_i m_j ln r, softened like `Gravity`. This is the law the fast multipole
+/// solver evaluates.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LogarithmicGravity;
+
+impl PairForce for LogarithmicGravity {
+    fn acceleration(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> (Vector2<f32>, Vector2<f32>) {
+        let dx = other.position - body.position;
+        let dv = other.speed - body.speed;
+        let distance2 = dx.magnitude2() + parameters.softening * parameters.softening;
+
+        let scale = parameters.gravitational_constant * other.mass / distance2;
+        (dx * scale, (dv - dx * (2.0 * dx.dot(dv) / distance2)) * scale)
+    }
+
+    fn potential_energy(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> f32 {
+        let distance2 = (other.position - body.position).magnitude2() + parameters.softening * parameters.softening;
+        0.5 * parameters.gravitational_constant * body.mass * other.mass * distance2.ln()
+    }
+
+    fn law(&self) -> ForceLaw {
+        ForceLaw::Logarithmic
+    }
+}
+
+/// Plummer-softened electrostatics, F = k q_i q_j / r². Like charges repel and
+/// opposite charges attract.
+#[derive(Copy, Clone, Debug)]
+pub struct Coulomb {
+    pub coulomb_constant: f32,
+}
+
+impl PairForce for Coulomb {
+    fn acceleration(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> (Vector2<f32>, Vector2<f32>) {
+        let separation = body.position - other.position;
+        let distance2 = separation.magnitude2() + parameters.softening * parameters.softening;
+        let distance = distance2.sqrt();
+
+        let strength = self.coulomb_constant * body.charge * other.charge;
+        let magnitude = strength / (distance2 * distance);
+        let slope = -3.0 * magnitude / distance;
+        central_force_acceleration(body, other, magnitude, slope * separation.magnitude() / distance)
+    }
+
+    fn potential_energy(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> f32 {
+        let distance2 = (other.position - body.position).magnitude2() + parameters.softening * parameters.softening;
+        self.coulomb_constant * body.charge * other.charge / distance2.sqrt()
+    }
+
+    fn inverse_distance_coupling(&self, body: &Body, other: &Body, _parameters: &ForceParameters) -> Option<f32> {
+        Some(self.coulomb_constant * body.charge * other.charge)
+    }
+}
+
+/// 12-6 Lennard-Jones, U = 4ε((σ/r)¹² - (σ/r)⁶), cut off at `cutoff` and shifted
+/// so the potential is continuous there. Acts regardless of charge.
+#[derive(Copy, Clone, Debug)]
+pub struct LennardJones {
+    pub epsilon: f32,
+    pub sigma: f32,
+    pub cutoff: f32,
+}
+
+impl LennardJones {
+    fn unshifted_potential(&self, distance: f32) -> f32 {
+        let s6 = (self.sigma / distance).powi(6);
+        4.0 * self.epsilon * (s6 * s6 - s6)
+    }
+}
+
+impl PairForce for LennardJones {
+    fn acceleration(&self, body: &Body, other: &Body, _parameters: &ForceParameters) -> (Vector2<f32>, Vector2<f32>) {
+        let distance = (body.position - other.position).magnitude();
+        if distance >= self.cutoff {
+            return (Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0));
+        }
+
+        let s6 = (self.sigma / distance).powi(6);
+        let distance2 = distance * distance;
+        let magnitude = 24.0 * self.epsilon * (2.0 * s6 * s6 - s6) / distance2;
+        let slope = 24.0 * self.epsilon * (-28.0 * s6 * s6 + 8.0 * s6) / (distance2 * distance);
+        central_force_acceleration(body, other, magnitude, slope)
+    }
+
+    fn potential_energy(&self, body: &Body, other: &Body, _parameters: &ForceParameters) -> f32 {
+        let distance = (body.position - other.position).magnitude();
+        if distance >= self.cutoff {
+            return 0.0;
+        }
+        self.unshifted_potential(distance) - self.unshifted_potential(self.cutoff)
+    }
+}
+
+/// Screened Coulomb (Yukawa) interaction, U = k q_i q_j e^(-r/λ) / r. A
+/// negative `coupling` makes like charges attract, as in the nuclear form.
+#[derive(Copy, Clone, Debug)]
+pub struct Yukawa {
+    pub coupling: f32,
+    pub screening_length: f32,
+}
+
+impl PairForce for Yukawa {
+    fn acceleration(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> (Vector2<f32>, Vector2<f32>) {
+        let separation = body.position - other.position;
+        let distance = (separation.magnitude2() + parameters.softening * parameters.softening).sqrt();
+        let lambda = self.screening_length;
+
+        let strength = self.coupling * body.charge * other.charge * (-distance / lambda).exp();
+        let magnitude = strength * (1.0 + distance / lambda) / distance.powi(3);
+        let slope = -strength * (3.0 + 3.0 * distance / lambda + distance * distance / (lambda * lambda)) / distance.powi(4);
+        central_force_acceleration(body, other, magnitude, slope * separation.magnitude() / distance)
+    }
+
+    fn potential_energy(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> f32 {
+        let distance = ((other.position - body.position).magnitude2() + parameters.softening * parameters.softening).sqrt();
+        self.coupling * body.charge * other.charge * (-distance / self.screening_length).exp() / distance
+    }
+}
+
+/// Turns a central force F = f(r) (x_body - x_other), with df/dr = `slope`, into
+/// the acceleration and jerk of `body`. Massless bodies get nothing, since only
+/// gravity gives them a finite acceleration.
+fn central_force_acceleration(body: &Body, other: &Body, magnitude: f32, slope: f32) -> (Vector2<f32>, Vector2<f32>) {
+    if body.mass == 0.0 {
+        return (Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0));
+    }
+
+    let separation = body.position - other.position;
+    let relative_speed = body.speed - other.speed;
+    let distance = separation.magnitude();
+
+    let force = separation * magnitude;
+    let force_rate = relative_speed * magnitude + separation * (slope * separation.dot(relative_speed) / distance);
+    (force / body.mass, force_rate / body.mass)
+}
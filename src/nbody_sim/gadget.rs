@@ -0,0 +1,371 @@
+use cgmath::Vector2;
+use std::io::{self, ErrorKind, Read, Write};
+use crate::nbody_sim::Body;
+
+const HEADER_BYTES: usize = 256;
+const PARTICLE_TYPES: usize = 6;
+/// Massive bodies are written as halo particles.
+const BODY_TYPE: usize = 1;
+/// Gadget has no massless type, so test particles are written as boundary
+/// particles with zero mass.
+const TEST_PARTICLE_TYPE: usize = 5;
+/// Blocks of a format-1 file, which has no labels to tell them apart.
+const FORMAT_1_BLOCKS: [&[u8; 4]; 5] = [b"HEAD", b"POS ", b"VEL ", b"ID  ", b"MASS"];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GadgetFormat {
+    /// Blocks in the fixed order HEAD, POS, VEL, ID, MASS.
+    Format1,
+    /// Every block preceded by a record holding its four-character label, as
+    /// written with `SnapFormat=2`.
+    Format2,
+}
+
+/// The header fields that describe the run rather than the particles; counts
+/// and the mass table come from the bodies.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GadgetHeader {
+    /// The scale factor in cosmological snapshots, otherwise the time.
+    pub time: f64,
+    pub redshift: f64,
+    /// Side of the periodic box, 0 for open boundaries.
+    pub box_size: f64,
+    pub omega_matter: f64,
+    pub omega_lambda: f64,
+    /// h, which the reading tools divide lengths and masses by.
+    pub hubble_param: f64,
+}
+
+#[derive(Clone)]
+pub struct GadgetSnapshot {
+    pub header: GadgetHeader,
+    /// In ID order, each with its ID as `Body::id`.
+    pub bodies: Vec<Body>,
+}
+
+impl Default for GadgetHeader {
+    fn default() -> Self {
+        GadgetHeader {
+            time: 0.0,
+            redshift: 0.0,
+            box_size: 0.0,
+            omega_matter: 0.0,
+            omega_lambda: 0.0,
+            hubble_param: 1.0,
+        }
+    }
+}
+
+/// Writes `bodies` as a single-file little-endian snapshot in single
+/// precision, with the z components zero and `Body::id` as the ID, 32-bit
+/// unless an ID needs more. With a box, positions are shifted into Gadget's
+/// [0, BoxSize).
+/// Massive bodies are type 1, test particles type 5; a type whose bodies all
+/// have one mass gets it in the header's mass table instead of the MASS block.
+pub fn write_gadget(mut writer: impl Write, header: &GadgetHeader, bodies: &[Body], format: GadgetFormat) -> io::Result<()> {
+    let mut order = (0..bodies.len()).filter(|&i| !bodies[i].test_particle).collect::<Vec<_>>();
+    let massive = order.len();
+    order.extend((0..bodies.len()).filter(|&i| bodies[i].test_particle));
+
+    let mut counts = [0u32; PARTICLE_TYPES];
+    counts[BODY_TYPE] = massive as u32;
+    counts[TEST_PARTICLE_TYPE] = (bodies.len() - massive) as u32;
+    let mut mass_table = [0.0; PARTICLE_TYPES];
+    let massive_masses = order[..massive].iter().map(|&i| bodies[i].mass).collect::<Vec<_>>();
+    if massive_masses.first().is_some_and(|&mass| mass > 0.0 && massive_masses.iter().all(|&other| other == mass)) {
+        mass_table[BODY_TYPE] = massive_masses[0] as f64;
+    }
+
+    let mut head = Vec::with_capacity(HEADER_BYTES);
+    counts.iter().for_each(|count| head.extend(count.to_le_bytes()));
+    mass_table.iter().for_each(|mass| head.extend(mass.to_le_bytes()));
+    head.extend(header.time.to_le_bytes());
+    head.extend(header.redshift.to_le_bytes());
+    head.extend([0u8; 8]); // flag_sfr, flag_feedback
+    counts.iter().for_each(|count| head.extend(count.to_le_bytes()));
+    head.extend([0u8; 4]); // flag_cooling
+    head.extend(1i32.to_le_bytes()); // num_files
+    for value in [header.box_size, header.omega_matter, header.omega_lambda, header.hubble_param] {
+        head.extend(value.to_le_bytes());
+    }
+    head.resize(HEADER_BYTES, 0);
+    write_block(&mut writer, format, b"HEAD", &head)?;
+
+    let box_size = header.box_size as f32;
+    let vectors = |vector: &dyn Fn(&Body) -> Vector2<f32>| {
+        let mut data = Vec::with_capacity(12 * order.len());
+        for &i in order.iter() {
+            let value = vector(&bodies[i]);
+            for component in [value.x, value.y, 0.0] {
+                data.extend(component.to_le_bytes());
+            }
+        }
+        data
+    };
+    write_block(&mut writer, format, b"POS ", &vectors(&|body| from_centred(body.position, box_size)))?;
+    write_block(&mut writer, format, b"VEL ", &vectors(&|body| body.speed))?;
+
+    let ids = match order.iter().all(|&i| bodies[i].id <= u32::MAX as u64) {
+        true => order.iter().flat_map(|&i| (bodies[i].id as u32).to_le_bytes()).collect::<Vec<_>>(),
+        false => order.iter().flat_map(|&i| bodies[i].id.to_le_bytes()).collect::<Vec<_>>(),
+    };
+    write_block(&mut writer, format, b"ID  ", &ids)?;
+
+    let masses = order.iter()
+        .enumerate()
+        .filter(|&(n, _)| n >= massive || mass_table[BODY_TYPE] == 0.0)
+        .flat_map(|(_, &i)| bodies[i].mass.to_le_bytes())
+        .collect::<Vec<_>>();
+    if !masses.is_empty() {
+        write_block(&mut writer, format, b"MASS", &masses)?;
+    }
+    Ok(())
+}
+
+/// Reads a format-1 or format-2 snapshot of either byte order and precision.
+/// Every particle type becomes bodies of `density`, massless ones test
+/// particles; the z components are dropped, and with a box positions are
+/// shifted from [0, BoxSize) to the simulation's box about the origin. Blocks
+/// other than POS, VEL, ID and MASS are skipped. Each file of a multi-file
+/// snapshot reads on its own.
+pub fn read_gadget(reader: impl Read, density: f32) -> io::Result<GadgetSnapshot> {
+    let mut records = Records::new(reader)?;
+    let Some((label, head)) = records.next_block()? else {
+        return Err(invalid("empty snapshot"));
+    };
+    if &label != b"HEAD" || head.len() < HEADER_BYTES {
+        return Err(invalid("snapshot does not start with a header"));
+    }
+
+    let mut fields = Fields { bytes: &head, big_endian: records.big_endian };
+    let counts = (0..PARTICLE_TYPES).map(|_| fields.u32() as usize).collect::<Vec<_>>();
+    let mass_table = (0..PARTICLE_TYPES).map(|_| fields.f64()).collect::<Vec<_>>();
+    let time = fields.f64();
+    let redshift = fields.f64();
+    fields.skip(40); // flags, total counts, flag_cooling, num_files
+    let header = GadgetHeader {
+        time,
+        redshift,
+        box_size: fields.f64(),
+        omega_matter: fields.f64(),
+        omega_lambda: fields.f64(),
+        hubble_param: fields.f64(),
+    };
+
+    let total = counts.iter().sum::<usize>();
+    let variable_masses = (0..PARTICLE_TYPES).filter(|&t| mass_table[t] == 0.0).map(|t| counts[t]).sum::<usize>();
+    let mut positions = None;
+    let mut speeds = vec![0.0; 3 * total];
+    let mut ids = (0..total as u64).collect::<Vec<_>>();
+    let mut masses = Vec::new();
+    while let Some((label, data)) = records.next_block()? {
+        let big_endian = records.big_endian;
+        match &label {
+            b"POS " => positions = Some(floats(&data, 3 * total, big_endian)?),
+            b"VEL " => speeds = floats(&data, 3 * total, big_endian)?,
+            b"ID  " => ids = integers(&data, total, big_endian)?,
+            // A format-1 file without variable masses has no MASS block, so
+            // the block after the IDs is something else.
+            b"MASS" if variable_masses > 0 => masses = floats(&data, variable_masses, big_endian)?,
+            _ => {}
+        }
+    }
+    let positions = positions.ok_or_else(|| invalid("snapshot has no POS block"))?;
+    if masses.len() != variable_masses {
+        return Err(invalid("snapshot has no MASS block for its variable-mass types"));
+    }
+
+    let mut bodies = Vec::with_capacity(total);
+    let mut variable = masses.into_iter();
+    for (t, &count) in counts.iter().enumerate() {
+        for _ in 0..count {
+            let n = bodies.len();
+            let mass = if mass_table[t] == 0.0 { variable.next().unwrap_or(0.0) } else { mass_table[t] } as f32;
+            let position = to_centred(Vector2::new(positions[3 * n] as f32, positions[3 * n + 1] as f32), header.box_size as f32);
+            let speed = Vector2::new(speeds[3 * n] as f32, speeds[3 * n + 1] as f32);
+            let body = match mass > 0.0 {
+                true => Body::new_sp(position, mass, speed, density),
+                false => Body::new_test_particle(position, speed),
+            };
+            bodies.push(Body { id: ids[n], ..body });
+        }
+    }
+    bodies.sort_by_key(|body| body.id);
+
+    Ok(GadgetSnapshot { header, bodies })
+}
+
+/// A box position in Gadget's [0, BoxSize) from the simulation's
+/// [-BoxSize/2, BoxSize/2). Positions outside a box are left alone.
+fn from_centred(position: Vector2<f32>, box_size: f32) -> Vector2<f32> {
+    if box_size <= 0.0 {
+        return position;
+    }
+    // rem_euclid can round up to the box size itself.
+    let corner = |x: f32| Some((x + 0.5 * box_size).rem_euclid(box_size)).filter(|&x| x < box_size).unwrap_or(0.0);
+    Vector2::new(corner(position.x), corner(position.y))
+}
+
+fn to_centred(position: Vector2<f32>, box_size: f32) -> Vector2<f32> {
+    match box_size > 0.0 {
+        true => position - Vector2::new(0.5 * box_size, 0.5 * box_size),
+        false => position,
+    }
+}
+
+/// A Fortran record: the data between two copies of its length.
+fn write_record(writer: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    let length = u32::try_from(data.len()).map_err(|_| invalid("block too large for a Gadget record"))?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(data)?;
+    writer.write_all(&length.to_le_bytes())
+}
+
+fn write_block(writer: &mut impl Write, format: GadgetFormat, label: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    if format == GadgetFormat::Format2 {
+        // The label record also gives the size of the block record after it.
+        let mut record = label.to_vec();
+        record.extend((data.len() as u32 + 8).to_le_bytes());
+        write_record(writer, &record)?;
+    }
+    write_record(writer, data)
+}
+
+/// Blocks of a snapshot being read, with the format and byte order told by the
+/// length of the first record: 256 for the format-1 header, 8 for a format-2
+/// label.
+struct Records<R> {
+    reader: R,
+    big_endian: bool,
+    format: GadgetFormat,
+    /// Length of the next record when already read, as for the first one.
+    next_length: Option<u32>,
+    blocks_read: usize,
+}
+
+impl<R: Read> Records<R> {
+    fn new(mut reader: R) -> io::Result<Self> {
+        let mut marker = [0; 4];
+        reader.read_exact(&mut marker)?;
+        let (format, big_endian, length) = match (u32::from_le_bytes(marker), u32::from_be_bytes(marker)) {
+            (256, _) => (GadgetFormat::Format1, false, 256),
+            (_, 256) => (GadgetFormat::Format1, true, 256),
+            (8, _) => (GadgetFormat::Format2, false, 8),
+            (_, 8) => (GadgetFormat::Format2, true, 8),
+            _ => return Err(invalid("not a Gadget snapshot")),
+        };
+        Ok(Records { reader, big_endian, format, next_length: Some(length), blocks_read: 0 })
+    }
+
+    /// The next block and its label, `None` at the end of the file.
+    fn next_block(&mut self) -> io::Result<Option<([u8; 4], Vec<u8>)>> {
+        let label = match self.format {
+            GadgetFormat::Format1 => match FORMAT_1_BLOCKS.get(self.blocks_read) {
+                Some(&&label) => label,
+                None => *b"    ",
+            },
+            GadgetFormat::Format2 => {
+                let Some(record) = self.read_record()? else {
+                    return Ok(None);
+                };
+                record.get(..4).and_then(|label| label.try_into().ok()).ok_or_else(|| invalid("short block label"))?
+            }
+        };
+        let Some(data) = self.read_record()? else {
+            return match self.format {
+                GadgetFormat::Format1 => Ok(None),
+                GadgetFormat::Format2 => Err(invalid("block label at the end of the file")),
+            };
+        };
+        self.blocks_read += 1;
+        Ok(Some((label, data)))
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let length = match self.next_length.take() {
+            Some(length) => length,
+            None => match self.read_u32() {
+                Ok(length) => length,
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(error) => return Err(error),
+            },
+        };
+        let mut data = vec![0; length as usize];
+        self.reader.read_exact(&mut data)?;
+        if self.read_u32()? != length {
+            return Err(invalid("record markers do not match"));
+        }
+        Ok(Some(data))
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+}
+
+/// Header fields read in order.
+struct Fields<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl Fields<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (field, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        ordered(field, self.big_endian)
+    }
+
+    fn skip(&mut self, bytes: usize) {
+        self.bytes = &self.bytes[bytes..];
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn f64(&mut self) -> f64 {
+        f64::from_le_bytes(self.take())
+    }
+}
+
+/// `count` values of a block in single or double precision, told apart by
+/// its size.
+fn floats(data: &[u8], count: usize, big_endian: bool) -> io::Result<Vec<f64>> {
+    match width(data, count)? {
+        4 => Ok(data.chunks_exact(4).map(|chunk| f32::from_le_bytes(ordered(chunk, big_endian)) as f64).collect()),
+        _ => Ok(data.chunks_exact(8).map(|chunk| f64::from_le_bytes(ordered(chunk, big_endian))).collect()),
+    }
+}
+
+/// `count` 32- or 64-bit IDs.
+fn integers(data: &[u8], count: usize, big_endian: bool) -> io::Result<Vec<u64>> {
+    match width(data, count)? {
+        4 => Ok(data.chunks_exact(4).map(|chunk| u32::from_le_bytes(ordered(chunk, big_endian)) as u64).collect()),
+        _ => Ok(data.chunks_exact(8).map(|chunk| u64::from_le_bytes(ordered(chunk, big_endian))).collect()),
+    }
+}
+
+fn width(data: &[u8], count: usize) -> io::Result<usize> {
+    match (data.len(), count) {
+        (0, 0) => Ok(4),
+        (length, count) if length == 4 * count => Ok(4),
+        (length, count) if length == 8 * count => Ok(8),
+        _ => Err(invalid("block size does not match the particle counts")),
+    }
+}
+
+/// A chunk in little-endian order.
+fn ordered<const N: usize>(chunk: &[u8], big_endian: bool) -> [u8; N] {
+    let mut bytes: [u8; N] = chunk.try_into().unwrap();
+    if big_endian {
+        bytes.reverse();
+    }
+    bytes
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,284 @@
+use cgmath::{InnerSpace, Vector2};
+use rand::Rng;
+use std::f32::consts::TAU;
+use crate::nbody_sim::{Body, ConfigurationError, G, seeded_rng, Simulation};
+
+/// Rendered radius of every galaxy body, independent of its mass.
+const BODY_RADIUS: f32 = 0.05;
+
+/// A disk + bulge + halo galaxy. The disk has an exponential surface density,
+/// the bulge and halo are Hernquist profiles truncated at `halo_cutoff` halo
+/// scale lengths. Positions and velocity dispersions are drawn from `seed`, so
+/// the same model always gives the same bodies.
+///
+/// The bulge and halo radii are drawn from the three-dimensional Hernquist
+/// mass M(<r) and laid out in the plane, so the mass inside a circle of radius
+/// r is the 3D M(<r), not the Hernquist surface density's. That puts more mass
+/// near the centre than a projected Hernquist sphere has.
+#[derive(Copy, Clone, Debug)]
+pub struct GalaxyModel {
+    pub disk_mass: f32,
+    pub disk_scale_length: f32,
+    pub disk_bodies: usize,
+    pub bulge_mass: f32,
+    pub bulge_scale_length: f32,
+    pub bulge_bodies: usize,
+    pub halo_mass: f32,
+    pub halo_scale_length: f32,
+    pub halo_bodies: usize,
+    pub halo_cutoff: f32,
+    pub seed: u64,
+}
+
+impl Default for GalaxyModel {
+    fn default() -> Self {
+        GalaxyModel {
+            disk_mass: 1.0,
+            disk_scale_length: 1.0,
+            disk_bodies: 1500,
+            bulge_mass: 0.3,
+            bulge_scale_length: 0.2,
+            bulge_bodies: 300,
+            halo_mass: 3.0,
+            halo_scale_length: 3.0,
+            halo_bodies: 700,
+            halo_cutoff: 4.0,
+            seed: 0,
+        }
+    }
+}
+
+impl GalaxyModel {
+    pub fn total_mass(&self) -> f32 {
+        self.disk_mass + self.bulge_mass + self.halo_mass
+    }
+
+    /// The same galaxy with `ratio` times the mass and body count, and lengths
+    /// scaled by sqrt(ratio) so the mean surface density is unchanged.
+    pub fn scaled(&self, ratio: f32) -> Self {
+        let length = ratio.sqrt();
+        let count = |n: usize| ((n as f32 * ratio).round() as usize).max(1);
+        GalaxyModel {
+            disk_mass: self.disk_mass * ratio,
+            disk_scale_length: self.disk_scale_length * length,
+            disk_bodies: count(self.disk_bodies),
+            bulge_mass: self.bulge_mass * ratio,
+            bulge_scale_length: self.bulge_scale_length * length,
+            bulge_bodies: count(self.bulge_bodies),
+            halo_mass: self.halo_mass * ratio,
+            halo_scale_length: self.halo_scale_length * length,
+            halo_bodies: count(self.halo_bodies),
+            halo_cutoff: self.halo_cutoff,
+            seed: self.seed,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum EncounterOrbit {
+    Parabolic,
+    /// Bound orbit with eccentricity in [0, 1).
+    Elliptic { eccentricity: f32 },
+}
+
+/// Two galaxies on a Keplerian encounter orbit around their common centre of
+/// mass. The secondary is `galaxy.scaled(mass_ratio)`, drawn from the next
+/// seed after the primary's so the two are not copies of each other.
+///
+/// The simulation is planar and cannot tilt a disk out of the plane, so each
+/// inclination is 0, a prograde disk, or π, a retrograde one mirrored in the
+/// x-axis. Anything in between is rejected.
+#[derive(Copy, Clone, Debug)]
+pub struct GalaxyCollision {
+    pub galaxy: GalaxyModel,
+    pub mass_ratio: f32,
+    pub orbit: EncounterOrbit,
+    pub pericentre: f32,
+    pub initial_separation: f32,
+    pub inclinations: [f32; 2],
+    pub gravitational_constant: f32,
+    pub softening: f32,
+}
+
+impl Default for GalaxyCollision {
+    fn default() -> Self {
+        GalaxyCollision {
+            galaxy: GalaxyModel::default(),
+            mass_ratio: 1.0,
+            orbit: EncounterOrbit::Parabolic,
+            pericentre: 4.0,
+            initial_separation: 16.0,
+            inclinations: [0.0, 0.0],
+            gravitational_constant: G,
+            softening: 0.05,
+        }
+    }
+}
+
+impl GalaxyCollision {
+    /// Fails on an inclination other than 0 or π.
+    pub fn create_bodies(&self) -> Result<Vec<Body>, ConfigurationError> {
+        let flips = self.inclinations.map(mirrored);
+        let flips = [flips[0]?, flips[1]?];
+        let secondary = GalaxyModel {
+            seed: self.galaxy.seed.wrapping_add(1),
+            ..self.galaxy.scaled(self.mass_ratio)
+        };
+        let models = [self.galaxy, secondary];
+        let masses = [models[0].total_mass(), models[1].total_mass()];
+        let (offset, relative_speed) = self.relative_orbit(masses[0] + masses[1]);
+
+        let mut bodies = Vec::new();
+        for (i, model) in models.iter().enumerate() {
+            // Each galaxy sits opposite the other around the centre of mass.
+            let share = if i == 0 { -masses[1] } else { masses[0] } / (masses[0] + masses[1]);
+            let mut galaxy = create_galaxy(model, self.gravitational_constant, self.softening);
+
+            for body in galaxy.iter_mut() {
+                if flips[i] {
+                    body.position.y = -body.position.y;
+                    body.speed.y = -body.speed.y;
+                }
+                body.position += offset * share;
+                body.speed += relative_speed * share;
+            }
+            bodies.append(&mut galaxy);
+        }
+
+        Ok(bodies)
+    }
+
+    /// Separation and relative velocity of the secondary with respect to the
+    /// primary on the way in to pericentre.
+    fn relative_orbit(&self, total_mass: f32) -> (Vector2<f32>, Vector2<f32>) {
+        let mu = self.gravitational_constant * total_mass;
+        let eccentricity = match self.orbit {
+            EncounterOrbit::Parabolic => 1.0,
+            EncounterOrbit::Elliptic { eccentricity } => eccentricity,
+        };
+
+        let semi_latus_rectum = self.pericentre * (1.0 + eccentricity);
+        let mut separation = self.initial_separation.max(self.pericentre);
+        if eccentricity < 1.0 {
+            separation = separation.min(semi_latus_rectum / (1.0 - eccentricity));
+        }
+
+        let cos_anomaly = if eccentricity > 0.0 {
+            ((semi_latus_rectum / separation - 1.0) / eccentricity).clamp(-1.0, 1.0)
+        } else {
+            1.0
+        };
+        let true_anomaly = -cos_anomaly.acos();
+
+        let direction = Vector2::new(true_anomaly.cos(), true_anomaly.sin());
+        let normal = Vector2::new(-direction.y, direction.x);
+        let speed_scale = (mu / semi_latus_rectum).sqrt();
+        let speed = direction * (speed_scale * eccentricity * true_anomaly.sin())
+            + normal * (speed_scale * (1.0 + eccentricity * true_anomaly.cos()));
+
+        (direction * separation, speed)
+    }
+}
+
+/// One galaxy centred on the origin and at rest. Disk bodies get the circular speed of the
+/// full self-consistent potential; bulge and halo bodies get isotropic
+/// Gaussian velocities whose dispersion satisfies the local virial balance.
+pub fn create_galaxy(model: &GalaxyModel, gravitational_constant: f32, softening: f32) -> Vec<Body> {
+    let mut rng = seeded_rng(model.seed);
+    let mut bodies = Vec::with_capacity(model.disk_bodies + model.bulge_bodies + model.halo_bodies);
+
+    // An exponential disk's radius follows a Gamma(2) distribution: the sum of two
+    // exponential variates.
+    place_component(&mut bodies, &mut rng, model.disk_bodies, model.disk_mass, |rng| {
+        -model.disk_scale_length * (rng.gen_range(f32::EPSILON..1.0f32).ln() + rng.gen_range(f32::EPSILON..1.0f32).ln())
+    });
+    place_component(&mut bodies, &mut rng, model.bulge_bodies, model.bulge_mass, |rng| {
+        sample_hernquist_radius(rng, model.bulge_scale_length, f32::INFINITY)
+    });
+    place_component(&mut bodies, &mut rng, model.halo_bodies, model.halo_mass, |rng| {
+        sample_hernquist_radius(rng, model.halo_scale_length, model.halo_cutoff)
+    });
+
+    // Sampling noise leaves the centre of mass off the origin; put it back
+    // before deriving velocities from the forces.
+    let total_mass = bodies.iter().map(|body| body.mass).sum::<f32>();
+    let centre = bodies.iter().map(|body| body.position * body.mass).sum::<Vector2<f32>>() / total_mass;
+    for body in bodies.iter_mut() {
+        body.position -= centre;
+    }
+
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(gravitational_constant);
+    simulation.set_softening(softening);
+    simulation.compute_accelerations();
+    let mut bodies = simulation.bodies().to_vec();
+
+    for (i, body) in bodies.iter_mut().enumerate() {
+        let radius = body.position.magnitude();
+        if radius == 0.0 {
+            continue;
+        }
+        let outward = body.position / radius;
+        let inward_pull = (-body.acceleration.dot(outward)).max(0.0);
+
+        if i < model.disk_bodies {
+            let speed = (radius * inward_pull).sqrt();
+            body.speed = Vector2::new(-outward.y, outward.x) * speed;
+        } else {
+            let dispersion = (radius * inward_pull / 2.0).sqrt();
+            body.speed = Vector2::new(gaussian(&mut rng), gaussian(&mut rng)) * dispersion;
+        }
+        body.acceleration = Vector2::new(0.0, 0.0);
+        body.jerk = Vector2::new(0.0, 0.0);
+    }
+
+    let drift = bodies.iter().map(|body| body.speed * body.mass).sum::<Vector2<f32>>() / total_mass;
+    for body in bodies.iter_mut() {
+        body.speed -= drift;
+    }
+
+    bodies
+}
+
+/// Scatters `count` equal-mass bodies at random angles and radii drawn by `sample_radius`.
+fn place_component<R: Rng>(bodies: &mut Vec<Body>, rng: &mut R, count: usize, mass: f32, sample_radius: impl Fn(&mut R) -> f32) {
+    let body_mass = mass / count.max(1) as f32;
+    for _ in 0..count {
+        let radius = sample_radius(rng);
+        let angle = rng.gen_range(0.0..TAU);
+        let position = Vector2::new(angle.cos(), angle.sin()) * radius;
+        bodies.push(Body::new(position, body_mass, body_mass / BODY_RADIUS));
+    }
+}
+
+/// Inverts the Hernquist cumulative mass M(<r) ∝ r² / (r + a)², truncated at
+/// `cutoff` scale lengths.
+fn sample_hernquist_radius<R: Rng>(rng: &mut R, scale_length: f32, cutoff: f32) -> f32 {
+    let max_fraction = if cutoff.is_finite() { cutoff / (1.0 + cutoff) } else { 1.0 };
+    let root = rng.gen_range(0.0..max_fraction.min(0.999));
+    scale_length * root / (1.0 - root)
+}
+
+fn gaussian<R: Rng>(rng: &mut R) -> f32 {
+    let u1 = rng.gen_range(f32::EPSILON..1.0f32);
+    let u2 = rng.gen_range(0.0..TAU);
+    (-2.0 * u1.ln()).sqrt() * u2.cos()
+}
+
+/// Whether a disk at `inclination` is mirrored, that is retrograde. Only a
+/// flat prograde or retrograde disk stays in equilibrium in the plane.
+fn mirrored(inclination: f32) -> Result<bool, ConfigurationError> {
+    match inclination.sin().abs() < 1e-6 {
+        true => Ok(inclination.cos() < 0.0),
+        false => Err(ConfigurationError::IntermediateInclination),
+    }
+}
+
+impl Simulation {
+    pub fn new_galaxy_collision(setup: &GalaxyCollision) -> Result<Self, ConfigurationError> {
+        let mut simulation = Simulation::from_bodies(setup.create_bodies()?);
+        simulation.set_gravitational_constant(setup.gravitational_constant);
+        simulation.set_softening(setup.softening);
+        Ok(simulation)
+    }
+}
@@ -0,0 +1,240 @@
+use cgmath::{InnerSpace, Vector2};
+use std::io::{self, Write};
+use crate::nbody_sim::Body;
+
+const DEFAULT_MIN_MEMBERS: usize = 8;
+/// Colours groups are drawn in, heaviest group first, cycling after the last.
+const GROUP_COLORS: [u32; 8] = [
+    0xEF5350FF, 0x66BB6AFF, 0x42A5F5FF, 0xFFCA28FF,
+    0xAB47BCFF, 0x26C6DAFF, 0xFF7043FF, 0xD4E157FF,
+];
+/// Bodies outside every group are drawn dim grey.
+const UNGROUPED_COLOR: u32 = 0x80808060;
+
+/// Clump finders over the massive bodies. Both compare every pair of bodies,
+/// O(N²), and drop groups with fewer than `min_members` members.
+#[derive(Copy, Clone, Debug)]
+pub enum GroupFinder {
+    FriendsOfFriends(FriendsOfFriends),
+    Density(DensityGroups),
+}
+
+/// Bodies closer than `linking_length` are friends, and friends of friends
+/// are in the same group.
+#[derive(Copy, Clone, Debug)]
+pub struct FriendsOfFriends {
+    pub linking_length: f32,
+    pub min_members: usize,
+}
+
+/// DBSCAN: a body with at least `min_neighbours` bodies, itself included,
+/// within `radius` is a core body. Core bodies within `radius` of each other
+/// share a group, which also takes in the other bodies within `radius` of its
+/// core; the rest are noise. Unlike friends-of-friends, thin bridges of bodies
+/// between clumps do not merge them.
+#[derive(Copy, Clone, Debug)]
+pub struct DensityGroups {
+    pub radius: f32,
+    pub min_neighbours: usize,
+    pub min_members: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub members: Vec<usize>,
+    pub mass: f32,
+    /// Centre of mass.
+    pub center: Vector2<f32>,
+    pub speed: Vector2<f32>,
+    /// Mass-weighted RMS speed about `speed`, both components together.
+    pub velocity_dispersion: f32,
+    /// Distance of the farthest member from `center`.
+    pub radius: f32,
+}
+
+/// Groups, heaviest first, and the group of every body.
+#[derive(Clone, Debug, Default)]
+pub struct Groups {
+    pub groups: Vec<Group>,
+    pub membership: Vec<Option<usize>>,
+}
+
+impl FriendsOfFriends {
+    pub fn new(linking_length: f32) -> Self {
+        FriendsOfFriends {
+            linking_length,
+            min_members: DEFAULT_MIN_MEMBERS,
+        }
+    }
+}
+
+impl DensityGroups {
+    pub fn new(radius: f32, min_neighbours: usize) -> Self {
+        DensityGroups {
+            radius,
+            min_neighbours,
+            min_members: DEFAULT_MIN_MEMBERS,
+        }
+    }
+}
+
+impl GroupFinder {
+    /// `separation` gives the vector between two bodies, so a periodic box can
+    /// use the nearest image.
+    pub fn find(&self, bodies: &[Body], separation: impl Fn(&Body, &Body) -> Vector2<f32>) -> Groups {
+        let massive = (0..bodies.len()).filter(|&i| !bodies[i].test_particle).collect::<Vec<_>>();
+        let radius = match self {
+            GroupFinder::FriendsOfFriends(finder) => finder.linking_length,
+            GroupFinder::Density(finder) => finder.radius,
+        };
+
+        // Neighbours within the radius, as positions in `massive`.
+        let mut neighbours = vec![Vec::new(); massive.len()];
+        for a in 0..massive.len() {
+            for b in a + 1..massive.len() {
+                if separation(&bodies[massive[a]], &bodies[massive[b]]).magnitude2() < radius * radius {
+                    neighbours[a].push(b);
+                    neighbours[b].push(a);
+                }
+            }
+        }
+
+        let (labels, min_members) = match self {
+            GroupFinder::FriendsOfFriends(finder) => (connected(&neighbours, |_| true), finder.min_members),
+            GroupFinder::Density(finder) => {
+                let core = |a: usize| neighbours[a].len() + 1 >= finder.min_neighbours;
+                let mut labels = connected(&neighbours, core);
+                // Border bodies join the group of a core neighbour.
+                for a in 0..massive.len() {
+                    if !core(a) {
+                        labels[a] = neighbours[a].iter().find(|&&b| core(b)).and_then(|&b| labels[b]);
+                    }
+                }
+                (labels, finder.min_members)
+            }
+        };
+
+        let mut members = Vec::<Vec<usize>>::new();
+        for (a, label) in labels.into_iter().enumerate() {
+            if let Some(label) = label {
+                if members.len() <= label {
+                    members.resize(label + 1, Vec::new());
+                }
+                members[label].push(massive[a]);
+            }
+        }
+
+        let mut groups = members.into_iter()
+            .filter(|members| members.len() >= min_members.max(1))
+            .map(|members| Group::new(bodies, members, &separation))
+            .collect::<Vec<_>>();
+        groups.sort_by(|a, b| b.mass.total_cmp(&a.mass));
+
+        let mut membership = vec![None; bodies.len()];
+        for (n, group) in groups.iter().enumerate() {
+            for &i in group.members.iter() {
+                membership[i] = Some(n);
+            }
+        }
+
+        Groups { groups, membership }
+    }
+}
+
+impl Group {
+    fn new(bodies: &[Body], members: Vec<usize>, separation: impl Fn(&Body, &Body) -> Vector2<f32>) -> Self {
+        // Offsets from the first member keep a group straddling a periodic
+        // boundary in one piece.
+        let origin = &bodies[members[0]];
+        let offsets = members.iter().map(|&i| separation(origin, &bodies[i])).collect::<Vec<_>>();
+
+        let mass = members.iter().map(|&i| bodies[i].mass).sum::<f32>();
+        let mut offset = Vector2::new(0.0, 0.0);
+        let mut speed = Vector2::new(0.0, 0.0);
+        for (&i, &member_offset) in members.iter().zip(offsets.iter()) {
+            offset += member_offset * bodies[i].mass;
+            speed += bodies[i].speed * bodies[i].mass;
+        }
+        offset /= mass;
+        speed /= mass;
+
+        let dispersion = members.iter()
+            .map(|&i| bodies[i].mass * (bodies[i].speed - speed).magnitude2())
+            .sum::<f32>() / mass;
+        let radius = offsets.iter()
+            .map(|&member_offset| (member_offset - offset).magnitude())
+            .fold(0.0, f32::max);
+
+        Group {
+            members,
+            mass,
+            center: origin.position + offset,
+            speed,
+            velocity_dispersion: dispersion.sqrt(),
+            radius,
+        }
+    }
+}
+
+impl Groups {
+    /// One line per group: index, member count, mass, centre, mean speed,
+    /// velocity dispersion and radius, after a header line.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "group,members,mass,center_x,center_y,speed_x,speed_y,velocity_dispersion,radius")?;
+        for (n, group) in self.groups.iter().enumerate() {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{}",
+                n, group.members.len(), group.mass, group.center.x, group.center.y,
+                group.speed.x, group.speed.y, group.velocity_dispersion, group.radius,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// One line per body with its group, empty for bodies in none.
+    pub fn write_membership_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "body,group")?;
+        for (i, group) in self.membership.iter().enumerate() {
+            match group {
+                Some(group) => writeln!(writer, "{},{}", i, group)?,
+                None => writeln!(writer, "{},", i)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Colour of every body, by group, for `Simulation::set_body_colors`.
+    pub fn colors(&self) -> Vec<u32> {
+        self.membership.iter()
+            .map(|group| group.map_or(UNGROUPED_COLOR, |n| GROUP_COLORS[n % GROUP_COLORS.len()]))
+            .collect()
+    }
+}
+
+/// Labels the connected components of the graph restricted to the vertices
+/// `include` accepts; the others get no label.
+fn connected(neighbours: &[Vec<usize>], include: impl Fn(usize) -> bool) -> Vec<Option<usize>> {
+    let mut labels = vec![None; neighbours.len()];
+    let mut next = 0;
+    let mut stack = Vec::new();
+
+    for start in 0..neighbours.len() {
+        if labels[start].is_some() || !include(start) {
+            continue;
+        }
+        labels[start] = Some(next);
+        stack.push(start);
+        while let Some(a) = stack.pop() {
+            for &b in neighbours[a].iter() {
+                if labels[b].is_none() && include(b) {
+                    labels[b] = Some(next);
+                    stack.push(b);
+                }
+            }
+        }
+        next += 1;
+    }
+
+    labels
+}
@@ -0,0 +1,36 @@
+use cgmath::Vector2;
+use crate::nbody_sim::Body;
+
+/// Scheme `Simulation::update` advances the bodies with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    /// Second-order kick-drift-kick leapfrog: a half kick, a full drift and a
+    /// half kick with the forces at the new positions. Supports every `Timestep`.
+    Leapfrog,
+    /// Fourth-order Hermite predictor-corrector using acceleration and jerk.
+    /// Runs on a shared step; `Timestep::Block` is treated like `Adaptive`.
+    Hermite,
+}
+
+/// Taylor-expands position and speed to the end of the step from the current
+/// acceleration and jerk.
+pub(crate) fn hermite_predict(body: &mut Body, dt: f32) {
+    let dt2 = dt * dt;
+    body.position += body.speed * dt + body.acceleration * (dt2 / 2.0) + body.jerk * (dt2 * dt / 6.0);
+    body.speed += body.acceleration * dt + body.jerk * (dt2 / 2.0);
+}
+
+/// Corrects a predicted body whose acceleration and jerk were re-evaluated at
+/// the predicted state, using `old` as the state at the start of the step.
+/// Returns the second and third acceleration derivatives at the end of the step.
+pub(crate) fn hermite_correct(body: &mut Body, old: &Body, dt: f32) -> (Vector2<f32>, Vector2<f32>) {
+    let dt2 = dt * dt;
+    let da = old.acceleration - body.acceleration;
+
+    body.speed = old.speed + (old.acceleration + body.acceleration) * (dt / 2.0) + (old.jerk - body.jerk) * (dt2 / 12.0);
+    body.position = old.position + (old.speed + body.speed) * (dt / 2.0) + da * (dt2 / 12.0);
+
+    let snap = (da * -6.0 - (old.jerk * 4.0 + body.jerk * 2.0) * dt) / dt2;
+    let crackle = (da * 12.0 + (old.jerk + body.jerk) * (6.0 * dt)) / (dt2 * dt);
+    (snap + crackle * dt, crackle)
+}
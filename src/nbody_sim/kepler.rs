@@ -0,0 +1,160 @@
+use cgmath::{InnerSpace, Vector2};
+use std::f32::consts::{PI, TAU};
+
+/// Keplerian elements of a two-body orbit. The simulation is planar, so the
+/// orbit lies in the x-y plane: `inclination` is 0 for counter-clockwise
+/// (prograde) and π for clockwise orbits, and the ascending node is fixed on the
+/// +x axis. Angles are in radians. Hyperbolic orbits have a negative
+/// `semi_major_axis` and use the hyperbolic mean anomaly.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub inclination: f32,
+    pub longitude_of_ascending_node: f32,
+    pub argument_of_periapsis: f32,
+    pub mean_anomaly: f32,
+}
+
+impl OrbitalElements {
+    pub fn circular(radius: f32, phase: f32) -> Self {
+        OrbitalElements {
+            semi_major_axis: radius,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            mean_anomaly: phase,
+        }
+    }
+
+    /// Elements of a body at `position` moving with `speed` relative to its
+    /// primary, where `mu` is G times the summed mass of both.
+    pub fn from_state_vectors(position: Vector2<f32>, speed: Vector2<f32>, mu: f32) -> Self {
+        let angular_momentum = position.x * speed.y - position.y * speed.x;
+        let retrograde = angular_momentum < 0.0;
+
+        // Mirror clockwise orbits so the rest of the maths only deals with
+        // counter-clockwise ones.
+        let (position, speed) = if retrograde {
+            (Vector2::new(position.x, -position.y), Vector2::new(speed.x, -speed.y))
+        } else {
+            (position, speed)
+        };
+        let h = angular_momentum.abs();
+
+        let r = position.magnitude();
+        let energy = 0.5 * speed.magnitude2() - mu / r;
+        let semi_major_axis = -mu / (2.0 * energy);
+
+        // Eccentricity vector points at periapsis.
+        let eccentricity_vector = Vector2::new(speed.y * h, -speed.x * h) / mu - position / r;
+        let eccentricity = eccentricity_vector.magnitude();
+
+        let true_longitude = position.y.atan2(position.x);
+        let argument_of_periapsis = if eccentricity > 1e-6 {
+            eccentricity_vector.y.atan2(eccentricity_vector.x)
+        } else {
+            0.0
+        };
+        let true_anomaly = true_longitude - argument_of_periapsis;
+
+        let mean_anomaly = if eccentricity < 1.0 {
+            let eccentric_anomaly = 2.0 * (((1.0 - eccentricity) / (1.0 + eccentricity)).sqrt() * (true_anomaly / 2.0).tan()).atan();
+            eccentric_anomaly - eccentricity * eccentric_anomaly.sin()
+        } else {
+            let hyperbolic_anomaly = 2.0 * (((eccentricity - 1.0) / (eccentricity + 1.0)).sqrt() * (true_anomaly / 2.0).tan()).atanh();
+            eccentricity * hyperbolic_anomaly.sinh() - hyperbolic_anomaly
+        };
+
+        OrbitalElements {
+            semi_major_axis,
+            eccentricity,
+            inclination: if retrograde { PI } else { 0.0 },
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: wrap_angle(argument_of_periapsis),
+            mean_anomaly: if eccentricity < 1.0 { wrap_angle(mean_anomaly) } else { mean_anomaly },
+        }
+    }
+
+    /// Position and speed relative to the primary. Only the sign of
+    /// cos(inclination) matters in the plane.
+    pub fn to_state_vectors(&self, mu: f32) -> (Vector2<f32>, Vector2<f32>) {
+        let e = self.eccentricity;
+        let a = self.semi_major_axis;
+
+        let (true_anomaly, r) = if e < 1.0 {
+            let eccentric_anomaly = solve_kepler(self.mean_anomaly, e);
+            let true_anomaly = 2.0 * (((1.0 + e) / (1.0 - e)).sqrt() * (eccentric_anomaly / 2.0).tan()).atan();
+            (true_anomaly, a * (1.0 - e * eccentric_anomaly.cos()))
+        } else {
+            let hyperbolic_anomaly = solve_hyperbolic_kepler(self.mean_anomaly, e);
+            let true_anomaly = 2.0 * (((e + 1.0) / (e - 1.0)).sqrt() * (hyperbolic_anomaly / 2.0).tanh()).atan();
+            (true_anomaly, a * (1.0 - e * hyperbolic_anomaly.cosh()))
+        };
+
+        let semi_latus_rectum = a * (1.0 - e * e);
+        let h = (mu * semi_latus_rectum).sqrt();
+
+        let angle = self.longitude_of_ascending_node + self.argument_of_periapsis + true_anomaly;
+        let position = Vector2::new(angle.cos(), angle.sin()) * r;
+        let radial_speed = mu / h * e * true_anomaly.sin();
+        let transverse_speed = h / r;
+        let speed = Vector2::new(
+            radial_speed * angle.cos() - transverse_speed * angle.sin(),
+            radial_speed * angle.sin() + transverse_speed * angle.cos(),
+        );
+
+        if self.inclination.cos() < 0.0 {
+            let mirror = |v: Vector2<f32>| Vector2::new(v.x, -v.y);
+            (mirror(position), mirror(speed))
+        } else {
+            (position, speed)
+        }
+    }
+
+    /// Orbital period, infinite for unbound orbits.
+    pub fn period(&self, mu: f32) -> f32 {
+        if self.eccentricity >= 1.0 {
+            return f32::INFINITY;
+        }
+        TAU * (self.semi_major_axis.powi(3) / mu).sqrt()
+    }
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    angle.rem_euclid(TAU)
+}
+
+/// Solves M = E - e sin E for the eccentric anomaly E.
+fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mean_anomaly = wrap_angle(mean_anomaly);
+    let mut eccentric_anomaly = if eccentricity > 0.8 { PI } else { mean_anomaly };
+
+    for _ in 0..50 {
+        let f = eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - mean_anomaly;
+        let step = f / (1.0 - eccentricity * eccentric_anomaly.cos());
+        eccentric_anomaly -= step;
+        if step.abs() < 1e-7 {
+            break;
+        }
+    }
+
+    eccentric_anomaly
+}
+
+/// Solves M = e sinh H - H for the hyperbolic anomaly H.
+fn solve_hyperbolic_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mut hyperbolic_anomaly = (mean_anomaly / eccentricity).asinh();
+
+    for _ in 0..50 {
+        let f = eccentricity * hyperbolic_anomaly.sinh() - hyperbolic_anomaly - mean_anomaly;
+        let step = f / (eccentricity * hyperbolic_anomaly.cosh() - 1.0);
+        hyperbolic_anomaly -= step;
+        if step.abs() < 1e-7 {
+            break;
+        }
+    }
+
+    hyperbolic_anomaly
+}
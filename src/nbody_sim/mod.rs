@@ -1,5 +1,54 @@
+mod binary;
 mod body;
+mod body_force;
+mod configuration;
+mod cosmology;
+mod diagnostics;
+mod escape;
+mod export;
+mod fast_multipole;
+mod force;
+mod gadget;
+mod galaxy;
+mod group;
+mod integrator;
+mod kepler;
+mod math;
+mod particle_mesh;
+mod periodic;
+mod post_newtonian;
+mod potential;
+mod regularization;
+mod rng;
 mod simulation;
+mod solar_system;
+mod solver;
+mod timestep;
+mod validation;
 
+pub use binary::*;
 pub use body::*;
-pub use simulation::*;
\ No newline at end of file
+pub use body_force::*;
+pub use configuration::*;
+pub use cosmology::*;
+pub use diagnostics::*;
+pub use escape::*;
+pub use export::*;
+pub use fast_multipole::*;
+pub use force::*;
+pub use gadget::*;
+pub use galaxy::*;
+pub use group::*;
+pub use integrator::*;
+pub use kepler::*;
+pub use particle_mesh::*;
+pub use periodic::*;
+pub use post_newtonian::*;
+pub use potential::*;
+pub use regularization::*;
+pub use rng::*;
+pub use simulation::*;
+pub use solar_system::*;
+pub use solver::*;
+pub use timestep::*;
+pub use validation::*;
\ No newline at end of file
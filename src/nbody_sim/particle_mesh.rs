@@ -0,0 +1,176 @@
+use cgmath::Vector2;
+use rustfft::{Fft, FftPlanner};
+use rustfft::num_complex::Complex;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::nbody_sim::{Body, PeriodicBox};
+
+/// Particle-mesh gravity: cloud-in-cell mass assignment onto a `grid_size`²
+/// mesh over the periodic box, an FFT Poisson solve and cloud-in-cell
+/// interpolation of the mesh forces back to the bodies. Forces below a couple
+/// of cells are smoothed out.
+#[derive(Copy, Clone, Debug)]
+pub struct ParticleMesh {
+    pub grid_size: usize,
+}
+
+impl ParticleMesh {
+    pub fn new(grid_size: usize) -> Self {
+        ParticleMesh { grid_size }
+    }
+
+    /// Acceleration of every body from the massive ones and their periodic
+    /// images, with a neutralising background.
+    pub fn accelerations(&self, bodies: &[Body], periodic_box: &PeriodicBox, g: f32) -> Vec<Vector2<f32>> {
+        let n = self.grid_size;
+        let potential = self.potential_modes(bodies, periodic_box, g);
+        let k = |i: usize| wavenumber(i, n, periodic_box.size);
+
+        let mut force_x = vec![Complex::new(0.0, 0.0); n * n];
+        let mut force_y = vec![Complex::new(0.0, 0.0); n * n];
+        for iy in 0..n {
+            for ix in 0..n {
+                // a = -∇φ; the Nyquist mode has no sign and is left out.
+                let i = iy * n + ix;
+                if ix != n / 2 {
+                    force_x[i] = potential[i] * Complex::new(0.0, -k(ix));
+                }
+                if iy != n / 2 {
+                    force_y[i] = potential[i] * Complex::new(0.0, -k(iy));
+                }
+            }
+        }
+
+        let inverse = FftPlanner::new().plan_fft_inverse(n);
+        fft_2d(&mut force_x, n, &inverse);
+        fft_2d(&mut force_y, n, &inverse);
+        let normalisation = 1.0 / (n * n) as f32;
+
+        bodies.iter()
+            .map(|body| {
+                let mut acceleration = Vector2::new(0.0, 0.0);
+                for (index, weight) in self.cloud_in_cell(body.position, periodic_box) {
+                    acceleration += Vector2::new(force_x[index].re, force_y[index].re) * weight;
+                }
+                acceleration * normalisation
+            })
+            .collect()
+    }
+
+    /// Potential energy of the massive bodies' pull on each other through the
+    /// mesh: ½ Σ m φ, less each body's pull on its own cloud. Like the forces,
+    /// it is only accurate with several bodies to a cell.
+    pub fn potential_energy(&self, bodies: &[Body], periodic_box: &PeriodicBox, g: f32) -> f32 {
+        let n = self.grid_size;
+        let cell = periodic_box.size / n as f32;
+        let inverse = FftPlanner::new().plan_fft_inverse(n);
+        let normalisation = 1.0 / (n * n) as f32;
+
+        let mut potential = self.potential_modes(bodies, periodic_box, g);
+        fft_2d(&mut potential, n, &inverse);
+
+        // The potential of a unit mass on mesh point 0, to take the self-energy out.
+        let mut kernel = vec![Complex::new(1.0 / (cell * cell), 0.0); n * n];
+        self.solve_poisson(&mut kernel, periodic_box, g);
+        fft_2d(&mut kernel, n, &inverse);
+        let offset = |a: usize, b: usize| (a / n + n - b / n) % n * n + (a % n + n - b % n) % n;
+
+        bodies.iter()
+            .filter(|body| !body.test_particle)
+            .map(|body| {
+                let cloud = self.cloud_in_cell(body.position, periodic_box);
+                let mut phi = 0.0;
+                let mut own = 0.0;
+                for &(a, weight) in cloud.iter() {
+                    phi += potential[a].re * weight;
+                    for &(b, other) in cloud.iter() {
+                        own += kernel[offset(a, b)].re * weight * other;
+                    }
+                }
+                0.5 * body.mass * (phi - body.mass * own) * normalisation
+            })
+            .sum()
+    }
+
+    /// Fourier modes of the mesh potential of the massive bodies.
+    fn potential_modes(&self, bodies: &[Body], periodic_box: &PeriodicBox, g: f32) -> Vec<Complex<f32>> {
+        let n = self.grid_size;
+        let cell = periodic_box.size / n as f32;
+
+        let mut density = vec![Complex::new(0.0, 0.0); n * n];
+        for body in bodies.iter().filter(|body| !body.test_particle) {
+            for (index, weight) in self.cloud_in_cell(body.position, periodic_box) {
+                density[index].re += body.mass * weight / (cell * cell);
+            }
+        }
+        fft_2d(&mut density, n, &FftPlanner::new().plan_fft_forward(n));
+        self.solve_poisson(&mut density, periodic_box, g);
+        density
+    }
+
+    /// Turns surface density modes into potential modes in place. A sheet of
+    /// surface density Σ under the 1/r² law has φ(k) = -2πG Σ(k) / |k|, and
+    /// the neutralising background takes out the mean. The assignment and the
+    /// interpolation each smooth by the cloud-in-cell window, which is divided
+    /// back out.
+    fn solve_poisson(&self, modes: &mut [Complex<f32>], periodic_box: &PeriodicBox, g: f32) {
+        let n = self.grid_size;
+        let cell = periodic_box.size / n as f32;
+        let window = |k: f32| {
+            let x = 0.5 * k * cell;
+            if x == 0.0 { 1.0 } else { (x.sin() / x).powi(2) }
+        };
+        modes[0] = Complex::new(0.0, 0.0);
+        for iy in 0..n {
+            for ix in 0..n {
+                if ix == 0 && iy == 0 {
+                    continue;
+                }
+                let (kx, ky) = (wavenumber(ix, n, periodic_box.size), wavenumber(iy, n, periodic_box.size));
+                modes[iy * n + ix] *= -2.0 * PI * g / (kx * kx + ky * ky).sqrt() / (window(kx) * window(ky)).powi(2);
+            }
+        }
+    }
+
+    /// The four mesh points sharing a body and their weights, cells being
+    /// centred on `(i + 0.5) * cell - size / 2`.
+    fn cloud_in_cell(&self, position: Vector2<f32>, periodic_box: &PeriodicBox) -> [(usize, f32); 4] {
+        let n = self.grid_size;
+        let position = periodic_box.wrap(position);
+        let cell = periodic_box.size / n as f32;
+        let u = (position.x + 0.5 * periodic_box.size) / cell - 0.5;
+        let v = (position.y + 0.5 * periodic_box.size) / cell - 0.5;
+        let (fx, fy) = (u - u.floor(), v - v.floor());
+        let x0 = (u.floor() as i64).rem_euclid(n as i64) as usize;
+        let y0 = (v.floor() as i64).rem_euclid(n as i64) as usize;
+        let (x1, y1) = ((x0 + 1) % n, (y0 + 1) % n);
+
+        [
+            (y0 * n + x0, (1.0 - fx) * (1.0 - fy)),
+            (y0 * n + x1, fx * (1.0 - fy)),
+            (y1 * n + x0, (1.0 - fx) * fy),
+            (y1 * n + x1, fx * fy),
+        ]
+    }
+}
+
+/// Signed wavenumber of mesh index `i` along an axis of `n` points.
+fn wavenumber(i: usize, n: usize, size: f32) -> f32 {
+    2.0 * PI * (if i <= n / 2 { i as f32 } else { i as f32 - n as f32 }) / size
+}
+
+/// Row-major 2D transform: rows, then columns through a transpose.
+fn fft_2d(buffer: &mut [Complex<f32>], n: usize, fft: &Arc<dyn Fft<f32>>) {
+    fft.process(buffer);
+    transpose(buffer, n);
+    fft.process(buffer);
+    transpose(buffer, n);
+}
+
+fn transpose(buffer: &mut [Complex<f32>], n: usize) {
+    for y in 0..n {
+        for x in y + 1..n {
+            buffer.swap(y * n + x, x * n + y);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use cgmath::Vector2;
+use std::f64::consts::PI;
+use crate::nbody_sim::math::{erf, erfc};
+
+/// Points per axis of the Ewald correction table, covering one quadrant.
+const EWALD_TABLE_SIZE: usize = 33;
+const EWALD_REAL_IMAGES: i32 = 3;
+const EWALD_RECIPROCAL_MODES: i32 = 8;
+
+/// Square domain of side `size` centred on the origin, wrapping in x and y.
+/// Bodies interact with the nearest image of every other body; the optional
+/// Ewald correction adds the pull of all the further images for pair forces
+/// with a 1/r potential.
+#[derive(Clone, Debug)]
+pub struct PeriodicBox {
+    pub size: f32,
+    ewald: Option<EwaldTable>,
+}
+
+impl PeriodicBox {
+    pub fn new(size: f32) -> Self {
+        PeriodicBox {
+            size,
+            ewald: None,
+        }
+    }
+
+    pub fn with_ewald(size: f32) -> Self {
+        PeriodicBox {
+            size,
+            ewald: Some(EwaldTable::new(size)),
+        }
+    }
+
+    pub fn has_ewald(&self) -> bool {
+        self.ewald.is_some()
+    }
+
+    /// Maps a position back into [-size/2, size/2).
+    pub fn wrap(&self, position: Vector2<f32>) -> Vector2<f32> {
+        let half = 0.5 * self.size;
+        Vector2::new(
+            (position.x + half).rem_euclid(self.size) - half,
+            (position.y + half).rem_euclid(self.size) - half,
+        )
+    }
+
+    /// Shortest separation equivalent to `separation` under the periodicity.
+    pub fn minimum_image(&self, separation: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new(
+            separation.x - self.size * (separation.x / self.size).round(),
+            separation.y - self.size * (separation.y / self.size).round(),
+        )
+    }
+
+    /// Potential and gradient of the infinite periodic 1/r sum minus the nearest
+    /// image's 1/r, at minimum-image separation `separation`. A pair with
+    /// potential `c / r` gains `c` times the potential and `-c / m` times the
+    /// gradient as acceleration.
+    pub fn ewald_correction(&self, separation: Vector2<f32>) -> Option<(f32, Vector2<f32>)> {
+        self.ewald.as_ref().map(|table| table.lookup(separation))
+    }
+
+    /// Second derivatives `[xx, xy, yy]` of the same correction potential. A
+    /// pair with potential `c / r` gains `-c / m` times the Hessian applied to
+    /// the relative velocity as jerk.
+    pub fn ewald_hessian(&self, separation: Vector2<f32>) -> Option<[f32; 3]> {
+        self.ewald.as_ref().map(|table| table.lookup_hessian(separation))
+    }
+}
+
+/// Ewald correction tabulated over [0, size/2]² and mirrored into the other
+/// quadrants: the potential is even in x and y, the gradient odd in each, and
+/// the Hessian's off-diagonal term odd in both.
+#[derive(Clone, Debug)]
+struct EwaldTable {
+    spacing: f32,
+    potential: Vec<f32>,
+    gradient: Vec<Vector2<f32>>,
+    hessian: Vec<[f32; 3]>,
+}
+
+impl EwaldTable {
+    fn new(size: f32) -> Self {
+        let spacing = 0.5 * size / (EWALD_TABLE_SIZE - 1) as f32;
+        let mut potential = Vec::with_capacity(EWALD_TABLE_SIZE * EWALD_TABLE_SIZE);
+        let mut gradient = Vec::with_capacity(EWALD_TABLE_SIZE * EWALD_TABLE_SIZE);
+        let mut hessian = Vec::with_capacity(EWALD_TABLE_SIZE * EWALD_TABLE_SIZE);
+
+        for iy in 0..EWALD_TABLE_SIZE {
+            for ix in 0..EWALD_TABLE_SIZE {
+                let (phi, grad, second) = ewald_sum(ix as f64 * spacing as f64, iy as f64 * spacing as f64, size as f64);
+                potential.push(phi as f32);
+                gradient.push(Vector2::new(grad[0] as f32, grad[1] as f32));
+                hessian.push(second.map(|value| value as f32));
+            }
+        }
+
+        EwaldTable {
+            spacing,
+            potential,
+            gradient,
+            hessian,
+        }
+    }
+
+    fn lookup(&self, separation: Vector2<f32>) -> (f32, Vector2<f32>) {
+        let mut potential = 0.0;
+        let mut gradient = Vector2::new(0.0, 0.0);
+        for (i, weight) in self.weights(separation) {
+            potential += self.potential[i] * weight;
+            gradient += self.gradient[i] * weight;
+        }
+
+        (potential, Vector2::new(gradient.x * separation.x.signum(), gradient.y * separation.y.signum()))
+    }
+
+    fn lookup_hessian(&self, separation: Vector2<f32>) -> [f32; 3] {
+        let mut hessian = [0.0; 3];
+        for (i, weight) in self.weights(separation) {
+            for (sum, value) in hessian.iter_mut().zip(self.hessian[i]) {
+                *sum += value * weight;
+            }
+        }
+
+        [hessian[0], hessian[1] * separation.x.signum() * separation.y.signum(), hessian[2]]
+    }
+
+    /// Table indices and bilinear weights of the four points around `separation`
+    /// folded into the first quadrant.
+    fn weights(&self, separation: Vector2<f32>) -> [(usize, f32); 4] {
+        let last = (EWALD_TABLE_SIZE - 1) as f32;
+        let u = (separation.x.abs() / self.spacing).min(last);
+        let v = (separation.y.abs() / self.spacing).min(last);
+        let ix = (u.floor() as usize).min(EWALD_TABLE_SIZE - 2);
+        let iy = (v.floor() as usize).min(EWALD_TABLE_SIZE - 2);
+        let (fx, fy) = (u - ix as f32, v - iy as f32);
+
+        let index = |x: usize, y: usize| y * EWALD_TABLE_SIZE + x;
+        [
+            (index(ix, iy), (1.0 - fx) * (1.0 - fy)),
+            (index(ix + 1, iy), fx * (1.0 - fy)),
+            (index(ix, iy + 1), (1.0 - fx) * fy),
+            (index(ix + 1, iy + 1), fx * fy),
+        ]
+    }
+}
+
+/// Ewald sum of 1/r over a square lattice of point sources in the plane with a
+/// neutralising background, minus the nearest 1/r, at offset (x, y).
+/// Returns the potential, its gradient and its Hessian as `[xx, xy, yy]`.
+fn ewald_sum(x: f64, y: f64, size: f64) -> (f64, [f64; 2], [f64; 3]) {
+    let alpha = 2.0 / size;
+    let area = size * size;
+    let mut potential = 0.0;
+    let mut gradient = [0.0, 0.0];
+    let mut hessian = [0.0, 0.0, 0.0];
+
+    // Adds a radial term with first and second derivatives `slope` and `curvature`.
+    let add_radial = |gradient: &mut [f64; 2], hessian: &mut [f64; 3], dx: f64, dy: f64, r: f64, slope: f64, curvature: f64| {
+        let (ux, uy) = (dx / r, dy / r);
+        gradient[0] += slope * ux;
+        gradient[1] += slope * uy;
+        hessian[0] += curvature * ux * ux + slope / r * (1.0 - ux * ux);
+        hessian[1] += (curvature - slope / r) * ux * uy;
+        hessian[2] += curvature * uy * uy + slope / r * (1.0 - uy * uy);
+    };
+
+    for nx in -EWALD_REAL_IMAGES..=EWALD_REAL_IMAGES {
+        for ny in -EWALD_REAL_IMAGES..=EWALD_REAL_IMAGES {
+            let dx = x + nx as f64 * size;
+            let dy = y + ny as f64 * size;
+            let r = (dx * dx + dy * dy).sqrt();
+            let gaussian = 2.0 * alpha / PI.sqrt() * (-alpha * alpha * r * r).exp();
+
+            if nx == 0 && ny == 0 {
+                // erfc(αr)/r - 1/r = -erf(αr)/r, finite at the origin, where
+                // its Hessian is isotropic.
+                if r < 1e-9 {
+                    potential -= 2.0 * alpha / PI.sqrt();
+                    let curvature = 4.0 * alpha.powi(3) / (3.0 * PI.sqrt());
+                    hessian[0] += curvature;
+                    hessian[2] += curvature;
+                    continue;
+                }
+                potential -= erf(alpha * r) / r;
+                let slope = erf(alpha * r) / (r * r) - gaussian / r;
+                let curvature = -2.0 * erf(alpha * r) / r.powi(3) + 2.0 * gaussian / (r * r) + 2.0 * alpha * alpha * gaussian;
+                add_radial(&mut gradient, &mut hessian, dx, dy, r, slope, curvature);
+            } else {
+                potential += erfc(alpha * r) / r;
+                let slope = -erfc(alpha * r) / (r * r) - gaussian / r;
+                let curvature = 2.0 * erfc(alpha * r) / r.powi(3) + 2.0 * gaussian / (r * r) + 2.0 * alpha * alpha * gaussian;
+                add_radial(&mut gradient, &mut hessian, dx, dy, r, slope, curvature);
+            }
+        }
+    }
+
+    for mx in -EWALD_RECIPROCAL_MODES..=EWALD_RECIPROCAL_MODES {
+        for my in -EWALD_RECIPROCAL_MODES..=EWALD_RECIPROCAL_MODES {
+            if mx == 0 && my == 0 {
+                continue;
+            }
+            let kx = 2.0 * PI * mx as f64 / size;
+            let ky = 2.0 * PI * my as f64 / size;
+            let k = (kx * kx + ky * ky).sqrt();
+            let weight = 2.0 * PI / (area * k) * erfc(k / (2.0 * alpha));
+            let phase = kx * x + ky * y;
+
+            potential += weight * phase.cos();
+            gradient[0] -= weight * kx * phase.sin();
+            gradient[1] -= weight * ky * phase.sin();
+            hessian[0] -= weight * kx * kx * phase.cos();
+            hessian[1] -= weight * kx * ky * phase.cos();
+            hessian[2] -= weight * ky * ky * phase.cos();
+        }
+    }
+
+    (potential, gradient, hessian)
+}
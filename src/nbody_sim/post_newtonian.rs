@@ -0,0 +1,91 @@
+use cgmath::{InnerSpace, Vector2};
+use crate::nbody_sim::Body;
+
+/// First post-Newtonian (Einstein-Infeld-Hoffmann) corrections to point-mass
+/// gravity, for relativistic precession of close orbits. `speed_of_light` is in
+/// the simulation's length and time units; the corrections scale as 1/c².
+#[derive(Copy, Clone, Debug)]
+pub struct PostNewtonian {
+    pub speed_of_light: f32,
+}
+
+impl PostNewtonian {
+    pub fn new(speed_of_light: f32) -> Self {
+        PostNewtonian { speed_of_light }
+    }
+
+    /// The 1PN part of every body's acceleration from the massive bodies,
+    /// evaluated unsoftened in f64. It needs every body's Newtonian potential
+    /// and acceleration, so it cannot be split into independent pairs.
+    pub fn accelerations(&self, bodies: &[Body], g: f32) -> Vec<Vector2<f32>> {
+        self.accelerations_of(bodies, g, &(0..bodies.len()).collect::<Vec<_>>())
+    }
+
+    /// The 1PN part of the acceleration of `indices` alone. The Newtonian
+    /// fields are still needed at every massive body, but the corrections
+    /// themselves are only summed for the requested ones.
+    pub fn accelerations_of(&self, bodies: &[Body], g: f32, indices: &[usize]) -> Vec<Vector2<f32>> {
+        let g = g as f64;
+        let c2 = (self.speed_of_light as f64).powi(2);
+        let positions = bodies.iter().map(|body| body.position.cast::<f64>().unwrap()).collect::<Vec<_>>();
+        let speeds = bodies.iter().map(|body| body.speed.cast::<f64>().unwrap()).collect::<Vec<_>>();
+        let massive = (0..bodies.len()).filter(|&i| !bodies[i].test_particle && bodies[i].mass != 0.0).collect::<Vec<_>>();
+
+        // Newtonian potential (as a positive G m / r) and acceleration of the
+        // massive and the requested bodies.
+        let mut needed = vec![false; bodies.len()];
+        for &i in massive.iter().chain(indices) {
+            needed[i] = true;
+        }
+        let mut potentials = vec![0.0; bodies.len()];
+        let mut newtonian = vec![Vector2::new(0.0, 0.0); bodies.len()];
+        for i in (0..bodies.len()).filter(|&i| needed[i]) {
+            for &j in massive.iter().filter(|&&j| j != i) {
+                let separation = positions[j] - positions[i];
+                let distance = separation.magnitude();
+                let gm = g * bodies[j].mass as f64;
+                potentials[i] += gm / distance;
+                newtonian[i] += separation * (gm / (distance * distance * distance));
+            }
+        }
+
+        indices.iter()
+            .map(|&i| {
+                let (x_i, v_i) = (positions[i], speeds[i]);
+                let mut correction = Vector2::new(0.0, 0.0);
+
+                for &j in massive.iter().filter(|&&j| j != i) {
+                    let (x_j, v_j) = (positions[j], speeds[j]);
+                    let separation = x_j - x_i;
+                    let distance = separation.magnitude();
+                    let n_ij = -separation / distance;
+                    let gm = g * bodies[j].mass as f64;
+
+                    let bracket = v_i.magnitude2() + 2.0 * v_j.magnitude2() - 4.0 * v_i.dot(v_j)
+                        - 1.5 * n_ij.dot(v_j).powi(2)
+                        - 4.0 * potentials[i] - potentials[j]
+                        + 0.5 * separation.dot(newtonian[j]);
+                    correction += -n_ij * (gm / (distance * distance) * bracket);
+                    correction += (v_i - v_j) * (gm / (distance * distance) * n_ij.dot(v_i * 4.0 - v_j * 3.0));
+                    correction += newtonian[j] * (3.5 * gm / distance);
+                }
+
+                (correction / c2).cast::<f32>().unwrap()
+            })
+            .collect()
+    }
+
+    /// The 1PN part of the relative acceleration of an isolated pair with
+    /// separation `position`, relative speed `speed`, `mu = G (m1 + m2)` and
+    /// symmetric mass ratio `m1 m2 / (m1 + m2)²`.
+    pub fn relative_acceleration(&self, position: Vector2<f64>, speed: Vector2<f64>, mu: f64, mass_ratio: f64) -> Vector2<f64> {
+        let c2 = (self.speed_of_light as f64).powi(2);
+        let distance = position.magnitude();
+        let n = position / distance;
+        let radial_speed = n.dot(speed);
+
+        let a = (1.0 + 3.0 * mass_ratio) * speed.magnitude2() - 2.0 * (2.0 + mass_ratio) * mu / distance - 1.5 * mass_ratio * radial_speed * radial_speed;
+        let b = -2.0 * (2.0 - mass_ratio) * radial_speed;
+        -(n * a + speed * b) * (mu / (distance * distance * c2))
+    }
+}
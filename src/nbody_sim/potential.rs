@@ -0,0 +1,190 @@
+use cgmath::{InnerSpace, Vector2};
+
+/// A fixed background potential felt by every body on top of (or instead of)
+/// self-gravity. Values are per unit mass at a point in the plane; `g` is the
+/// simulation's gravitational constant, which potentials defined by a mass use.
+pub trait ExternalPotential: Send + Sync {
+    fn potential(&self, position: Vector2<f32>, g: f32) -> f32;
+
+    fn acceleration(&self, position: Vector2<f32>, g: f32) -> Vector2<f32>;
+
+    /// Rate of change of `acceleration` for a body moving with `speed`, needed by
+    /// the Hermite integrator and the Aarseth criterion.
+    fn jerk(&self, position: Vector2<f32>, speed: Vector2<f32>, g: f32) -> Vector2<f32>;
+}
+
+/// Jerk of a spherically symmetric field a = -k(r) * x, given k and dk/dr at r = |x|.
+fn radial_jerk(offset: Vector2<f32>, speed: Vector2<f32>, k: f32, dk_dr: f32) -> Vector2<f32> {
+    let r = offset.magnitude();
+    if r == 0.0 {
+        return speed * -k;
+    }
+    speed * -k - offset * (dk_dr * offset.dot(speed) / r)
+}
+
+/// Φ = -G M / r
+#[derive(Copy, Clone, Debug)]
+pub struct PointMassPotential {
+    pub center: Vector2<f32>,
+    pub mass: f32,
+}
+
+impl ExternalPotential for PointMassPotential {
+    fn potential(&self, position: Vector2<f32>, g: f32) -> f32 {
+        -g * self.mass / (position - self.center).magnitude()
+    }
+
+    fn acceleration(&self, position: Vector2<f32>, g: f32) -> Vector2<f32> {
+        let offset = position - self.center;
+        let r = offset.magnitude();
+        offset * (-g * self.mass / (r * r * r))
+    }
+
+    fn jerk(&self, position: Vector2<f32>, speed: Vector2<f32>, g: f32) -> Vector2<f32> {
+        let offset = position - self.center;
+        let r = offset.magnitude();
+        let k = g * self.mass / (r * r * r);
+        radial_jerk(offset, speed, k, -3.0 * k / r)
+    }
+}
+
+/// Φ = ½ v0² ln(rc² + x² + y²/q²): flat rotation curve at `circular_speed`
+/// outside `core_radius`, with the y-axis flattened by `axis_ratio`.
+#[derive(Copy, Clone, Debug)]
+pub struct LogarithmicHaloPotential {
+    pub center: Vector2<f32>,
+    pub circular_speed: f32,
+    pub core_radius: f32,
+    pub axis_ratio: f32,
+}
+
+impl LogarithmicHaloPotential {
+    fn stretched(&self, offset: Vector2<f32>) -> (Vector2<f32>, f32) {
+        let q2 = self.axis_ratio * self.axis_ratio;
+        let gradient = Vector2::new(offset.x, offset.y / q2);
+        let denominator = self.core_radius * self.core_radius + offset.x * offset.x + offset.y * offset.y / q2;
+        (gradient, denominator)
+    }
+}
+
+impl ExternalPotential for LogarithmicHaloPotential {
+    fn potential(&self, position: Vector2<f32>, _g: f32) -> f32 {
+        let (_, denominator) = self.stretched(position - self.center);
+        0.5 * self.circular_speed * self.circular_speed * denominator.ln()
+    }
+
+    fn acceleration(&self, position: Vector2<f32>, _g: f32) -> Vector2<f32> {
+        let (gradient, denominator) = self.stretched(position - self.center);
+        gradient * (-self.circular_speed * self.circular_speed / denominator)
+    }
+
+    fn jerk(&self, position: Vector2<f32>, speed: Vector2<f32>, _g: f32) -> Vector2<f32> {
+        let (gradient, denominator) = self.stretched(position - self.center);
+        let (speed_gradient, _) = self.stretched(speed);
+        let v0_2 = self.circular_speed * self.circular_speed;
+        speed_gradient * (-v0_2 / denominator) + gradient * (2.0 * v0_2 * gradient.dot(speed) / (denominator * denominator))
+    }
+}
+
+/// Navarro-Frenk-White halo, Φ = -G M_s ln(1 + r/r_s) / r with
+/// M_s = 4π ρ_0 r_s³ the `characteristic_mass`.
+#[derive(Copy, Clone, Debug)]
+pub struct NfwPotential {
+    pub center: Vector2<f32>,
+    pub characteristic_mass: f32,
+    pub scale_radius: f32,
+}
+
+impl ExternalPotential for NfwPotential {
+    fn potential(&self, position: Vector2<f32>, g: f32) -> f32 {
+        let r = (position - self.center).magnitude();
+        if r == 0.0 {
+            return -g * self.characteristic_mass / self.scale_radius;
+        }
+        -g * self.characteristic_mass * (1.0 + r / self.scale_radius).ln() / r
+    }
+
+    fn acceleration(&self, position: Vector2<f32>, g: f32) -> Vector2<f32> {
+        let offset = position - self.center;
+        let r = offset.magnitude();
+        if r == 0.0 {
+            return Vector2::new(0.0, 0.0);
+        }
+        let rs = self.scale_radius;
+        let k = g * self.characteristic_mass * ((1.0 + r / rs).ln() / (r * r * r) - 1.0 / (r * r * (r + rs)));
+        offset * -k
+    }
+
+    fn jerk(&self, position: Vector2<f32>, speed: Vector2<f32>, g: f32) -> Vector2<f32> {
+        let offset = position - self.center;
+        let r = offset.magnitude();
+        if r == 0.0 {
+            return Vector2::new(0.0, 0.0);
+        }
+        let rs = self.scale_radius;
+        let gm = g * self.characteristic_mass;
+        let log = (1.0 + r / rs).ln();
+        let r3 = r * r * r;
+        let k = gm * (log / r3 - 1.0 / (r * r * (r + rs)));
+        let dk_dr = gm * (1.0 / (r3 * (r + rs)) - 3.0 * log / (r3 * r) + (3.0 * r + 2.0 * rs) / (r3 * (r + rs) * (r + rs)));
+        radial_jerk(offset, speed, k, dk_dr)
+    }
+}
+
+/// Miyamoto-Nagai disk evaluated in its mid-plane, where
+/// Φ = -G M / sqrt(R² + (a + b)²).
+#[derive(Copy, Clone, Debug)]
+pub struct MiyamotoNagaiPotential {
+    pub center: Vector2<f32>,
+    pub mass: f32,
+    pub scale_length: f32,
+    pub scale_height: f32,
+}
+
+impl MiyamotoNagaiPotential {
+    fn core2(&self) -> f32 {
+        let core = self.scale_length + self.scale_height;
+        core * core
+    }
+}
+
+impl ExternalPotential for MiyamotoNagaiPotential {
+    fn potential(&self, position: Vector2<f32>, g: f32) -> f32 {
+        let r2 = (position - self.center).magnitude2();
+        -g * self.mass / (r2 + self.core2()).sqrt()
+    }
+
+    fn acceleration(&self, position: Vector2<f32>, g: f32) -> Vector2<f32> {
+        let offset = position - self.center;
+        let d2 = offset.magnitude2() + self.core2();
+        offset * (-g * self.mass / (d2 * d2.sqrt()))
+    }
+
+    fn jerk(&self, position: Vector2<f32>, speed: Vector2<f32>, g: f32) -> Vector2<f32> {
+        let offset = position - self.center;
+        let r = offset.magnitude();
+        let d2 = r * r + self.core2();
+        let k = g * self.mass / (d2 * d2.sqrt());
+        radial_jerk(offset, speed, k, -3.0 * k * r / d2)
+    }
+}
+
+/// Constant acceleration everywhere, Φ = -a · x.
+#[derive(Copy, Clone, Debug)]
+pub struct UniformFieldPotential {
+    pub acceleration: Vector2<f32>,
+}
+
+impl ExternalPotential for UniformFieldPotential {
+    fn potential(&self, position: Vector2<f32>, _g: f32) -> f32 {
+        -self.acceleration.dot(position)
+    }
+
+    fn acceleration(&self, _position: Vector2<f32>, _g: f32) -> Vector2<f32> {
+        self.acceleration
+    }
+
+    fn jerk(&self, _position: Vector2<f32>, _speed: Vector2<f32>, _g: f32) -> Vector2<f32> {
+        Vector2::new(0.0, 0.0)
+    }
+}
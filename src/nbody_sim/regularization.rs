@@ -0,0 +1,142 @@
+use cgmath::Vector2;
+use num_complex::Complex;
+use crate::nbody_sim::{mutual_nearest_neighbours, Body};
+
+const DEFAULT_STEPS_PER_ORBIT: u32 = 64;
+/// Fraction of the step the final fictitious-time iterations may leave over.
+const TIME_TOLERANCE: f64 = 1e-12;
+const MAX_FINAL_ITERATIONS: usize = 8;
+
+/// Levi-Civita regularization of tight pairs, the planar counterpart of
+/// Kustaanheimo-Stiefel. A massive body whose nearest massive neighbour is
+/// within `radius`, and is nearest to it in turn, moves with it as a pair: the
+/// centre of mass follows the global step, and the relative orbit is integrated
+/// in the fictitious time ds = dt / r, where the Kepler problem becomes a
+/// harmonic oscillator with no singularity at pericentre. The pair's mutual pull is
+/// unsoftened Newtonian gravity inside the radius, plus any extra pull that
+/// depends on the pair's own state. Everything else acts on it as a
+/// perturbation held fixed over the step.
+#[derive(Copy, Clone, Debug)]
+pub struct Regularization {
+    pub radius: f32,
+    /// Fictitious-time steps per relative orbit.
+    pub steps_per_orbit: u32,
+}
+
+impl Regularization {
+    pub fn new(radius: f32) -> Self {
+        Regularization {
+            radius,
+            steps_per_orbit: DEFAULT_STEPS_PER_ORBIT,
+        }
+    }
+
+    /// Mutual nearest neighbours closer than `radius`, lower index first.
+    /// `separation` gives the vector between two bodies, so a periodic box can
+    /// use the nearest image.
+    pub fn find_pairs(&self, bodies: &[Body], separation: impl Fn(&Body, &Body) -> Vector2<f32>) -> Vec<(usize, usize)> {
+        mutual_nearest_neighbours(bodies, separation, self.radius)
+    }
+
+    /// Advances the relative position and speed of a pair with gravitational
+    /// parameter `mu = G (m1 + m2)` by `dt`, under an extra relative
+    /// acceleration `perturbation` that stays constant over the step and
+    /// `relative(position, speed)`, which is evaluated along the orbit.
+    pub fn advance(
+        &self,
+        position: Vector2<f64>,
+        speed: Vector2<f64>,
+        mu: f64,
+        perturbation: Vector2<f64>,
+        relative: impl Fn(Vector2<f64>, Vector2<f64>) -> Vector2<f64>,
+        dt: f64,
+    ) -> (Vector2<f64>, Vector2<f64>) {
+        let r = Complex::new(position.x, position.y);
+        let v = Complex::new(speed.x, speed.y);
+        let p = Complex::new(perturbation.x, perturbation.y);
+
+        // r = u², v = 2 u' / conj(u), h the two-body energy per reduced mass.
+        let u = r.sqrt();
+        let mut state = LeviCivita {
+            u,
+            w: v * u.conj() / 2.0,
+            energy: 0.5 * v.norm_sqr() - mu / r.norm(),
+            time: 0.0,
+        };
+
+        loop {
+            let step = std::f64::consts::TAU / (self.steps_per_orbit.max(1) as f64 * state.rate());
+            if dt - state.time <= step * state.u.norm_sqr() {
+                break;
+            }
+            state = state.rk4(step, p, &relative);
+        }
+        // Close the remaining physical time with steps of ds = dt_left / r.
+        for _ in 0..MAX_FINAL_ITERATIONS {
+            let remaining = dt - state.time;
+            if remaining.abs() <= TIME_TOLERANCE * dt.abs().max(f64::MIN_POSITIVE) {
+                break;
+            }
+            state = state.rk4(remaining / state.u.norm_sqr(), p, &relative);
+        }
+
+        let r = state.u * state.u;
+        let v = state.w * 2.0 / state.u.conj();
+        (Vector2::new(r.re, r.im), Vector2::new(v.re, v.im))
+    }
+}
+
+#[derive(Copy, Clone)]
+struct LeviCivita {
+    u: Complex<f64>,
+    /// du/ds
+    w: Complex<f64>,
+    energy: f64,
+    time: f64,
+}
+
+impl LeviCivita {
+    /// Oscillation rate in fictitious time, sqrt(|h| / 2), plus |u'| / |u| so
+    /// near-parabolic orbits still get finite steps.
+    fn rate(&self) -> f64 {
+        (0.5 * self.energy.abs() + self.w.norm_sqr() / self.u.norm_sqr()).sqrt()
+    }
+
+    /// u'' = h/2 u + |u|²/2 conj(u) P,  h' = 2 Re(conj(u') conj(u) P),  t' = |u|²,
+    /// with P the fixed perturbation plus the state-dependent one.
+    fn derivative(&self, perturbation: Complex<f64>, relative: &impl Fn(Vector2<f64>, Vector2<f64>) -> Vector2<f64>) -> LeviCivita {
+        let r = self.u.norm_sqr();
+        let (position, speed) = (self.u * self.u, self.w * 2.0 / self.u.conj());
+        let extra = relative(Vector2::new(position.re, position.im), Vector2::new(speed.re, speed.im));
+        let perturbation = perturbation + Complex::new(extra.x, extra.y);
+        LeviCivita {
+            u: self.w,
+            w: self.u * (0.5 * self.energy) + self.u.conj() * perturbation * (0.5 * r),
+            energy: 2.0 * (self.w.conj() * self.u.conj() * perturbation).re,
+            time: r,
+        }
+    }
+
+    fn add(&self, derivative: &LeviCivita, ds: f64) -> LeviCivita {
+        LeviCivita {
+            u: self.u + derivative.u * ds,
+            w: self.w + derivative.w * ds,
+            energy: self.energy + derivative.energy * ds,
+            time: self.time + derivative.time * ds,
+        }
+    }
+
+    fn rk4(&self, ds: f64, perturbation: Complex<f64>, relative: &impl Fn(Vector2<f64>, Vector2<f64>) -> Vector2<f64>) -> LeviCivita {
+        let k1 = self.derivative(perturbation, relative);
+        let k2 = self.add(&k1, 0.5 * ds).derivative(perturbation, relative);
+        let k3 = self.add(&k2, 0.5 * ds).derivative(perturbation, relative);
+        let k4 = self.add(&k3, ds).derivative(perturbation, relative);
+
+        LeviCivita {
+            u: self.u + (k1.u + k2.u * 2.0 + k3.u * 2.0 + k4.u) * (ds / 6.0),
+            w: self.w + (k1.w + k2.w * 2.0 + k3.w * 2.0 + k4.w) * (ds / 6.0),
+            energy: self.energy + (k1.energy + k2.energy * 2.0 + k3.energy * 2.0 + k4.energy) * (ds / 6.0),
+            time: self.time + (k1.time + k2.time * 2.0 + k3.time * 2.0 + k4.time) * (ds / 6.0),
+        }
+    }
+}
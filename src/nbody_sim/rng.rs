@@ -0,0 +1,11 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// The random number generator behind every initial condition and random
+/// perturbation. ChaCha produces the same stream for a seed on every platform
+/// and every release of `rand_chacha`, so the seed in a setup fixes the run.
+pub type SimulationRng = ChaCha8Rng;
+
+pub fn seeded_rng(seed: u64) -> SimulationRng {
+    SimulationRng::seed_from_u64(seed)
+}
@@ -1,54 +1,1404 @@
-use std::arch::x86_64;
-use std::sync::{Arc, Mutex};
 use cgmath::{InnerSpace, Vector2};
-use crate::nbody_sim::Body;
-use crate::State;
-use std::borrow::BorrowMut;
-use rand::Rng;
-use std::f32::consts::TAU;
-use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator};
-use rayon::iter::ParallelIterator;
-use crate::drawing::Circle;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use crate::nbody_sim::{AdaptiveTimestep, Binary, BinaryEvent, BinaryEventKind, BinaryFinder, Body, BodyExport, BodyForce, centre_of_mass, ClusterAnalysis, ClusterDiagnostics, Configuration, ConfigurationError, CosmologicalOutput, Cosmology, EscapeCriterion, Escaper, ExportCadence, ExternalPotential, ForceParameters, ForceSolver, G, GadgetFormat, GadgetHeader, GadgetSnapshot, Gravity, GroupFinder, Groups, hermite_correct, hermite_predict, Integrator, MassLoss, Multiples, OrbitalElements, PairForce, PeriodicBox, PostNewtonian, redshift_to_scale_factor, Regularization, scale_factor_to_redshift, Timestep, TimestepCriterion, write_gadget};
+use crate::drawing::{Circle, Vertex};
 
 pub struct Simulation {
     bodies: Vec<Body>,
+    integrator: Integrator,
+    timestep: Timestep,
+    gravitational_constant: f32,
+    softening: f32,
+    pair_force: Box<dyn PairForce>,
+    force_solver: ForceSolver,
+    self_gravity: bool,
+    external_potentials: Vec<Box<dyn ExternalPotential>>,
+    body_forces: Vec<Box<dyn BodyForce>>,
+    dt: f32,
+    time: f64,
+    /// Whether every body's `acceleration` matches its current position, which
+    /// the block scheme relies on for its opening half-kick.
+    accelerations_valid: bool,
+    /// Aarseth step picked at the end of the last Hermite step.
+    hermite_dt: Option<f32>,
+    orbit_tracking: Option<OrbitTracking>,
+    binary_tracking: Option<BinaryTracking>,
+    /// Colours to draw the bodies in instead of their own, by index.
+    body_colors: Option<Vec<u32>>,
+    escape_tracking: Option<EscapeTracking>,
+    export: Option<Export>,
+    periodic_box: Option<PeriodicBox>,
+    cosmology: Option<Cosmology>,
+    post_newtonian: Option<PostNewtonian>,
+    regularization: Option<Regularization>,
+    /// Still to be reached, highest redshift first.
+    output_redshifts: Vec<f64>,
+    outputs: Vec<CosmologicalOutput>,
+    output_snapshots: Option<OutputSnapshots>,
+    /// `Body::id` of the next body added.
+    next_id: u64,
 }
+
+/// Bodies whose elements relative to `primary` are refreshed after every update.
+struct OrbitTracking {
+    primary: usize,
+    bodies: Vec<usize>,
+    report: Vec<(usize, OrbitalElements)>,
+}
+/// Binaries found after the last update, and every change to them since
+/// tracking started.
+struct BinaryTracking {
+    finder: BinaryFinder,
+    binaries: Vec<Binary>,
+    events: Vec<BinaryEvent>,
+}
+
+/// Escapers removed since `criterion` was set, for the mass-loss totals.
+struct EscapeTracking {
+    criterion: EscapeCriterion,
+    initial_mass: f32,
+    start_time: f64,
+    escapers: Vec<Escaper>,
+}
+
+/// Gadget snapshots written for every redshift output.
+struct OutputSnapshots {
+    directory: PathBuf,
+    format: GadgetFormat,
+    written: usize,
+    /// First write error during `update`, handed back by `stop_output_snapshots`.
+    error: Option<io::Error>,
+}
+
+/// Body states streamed to `writer` at the cadence of `export`.
+struct Export {
+    export: BodyExport,
+    writer: BufWriter<Box<dyn Write + Send + Sync>>,
+    updates: u32,
+    last_time: f64,
+    /// First write error during `update`, handed back by `stop_export`.
+    error: Option<io::Error>,
+}
+
 const T: f32 = 0.001;
+const SPIRAL_SOFTENING: f32 = 0.05;
+/// Draws the spiral bodies with unit radius.
+const SPIRAL_DENSITY: f32 = 5.0;
+/// Colour mask dimming the periodic images drawn around the box.
+const IMAGE_ALPHA: u32 = 0x40;
+const BOX_OUTLINE_COLOR: [f32; 3] = [1.0, 0.541, 0.396];
 
 impl Simulation {
     pub fn new(num_bodies: usize, spacing: f32) -> Self {
-        let mut bodies = create_spiral_cluster(num_bodies, spacing);
+        let mut simulation = Simulation::from_bodies(create_spiral_cluster(num_bodies, spacing));
+        simulation.softening = SPIRAL_SOFTENING;
+        simulation
+    }
 
+    /// Numbers the bodies from zero in the order given.
+    pub fn from_bodies(mut bodies: Vec<Body>) -> Self {
+        for (id, body) in bodies.iter_mut().enumerate() {
+            body.id = id as u64;
+        }
         Simulation {
+            next_id: bodies.len() as u64,
             bodies,
+            integrator: Integrator::Leapfrog,
+            timestep: Timestep::Fixed(T),
+            gravitational_constant: G,
+            softening: 0.0,
+            pair_force: Box::new(Gravity),
+            force_solver: ForceSolver::Direct,
+            self_gravity: true,
+            external_potentials: Vec::new(),
+            body_forces: Vec::new(),
+            dt: T,
+            time: 0.0,
+            accelerations_valid: false,
+            hermite_dt: None,
+            orbit_tracking: None,
+            binary_tracking: None,
+            body_colors: None,
+            escape_tracking: None,
+            export: None,
+            periodic_box: None,
+            cosmology: None,
+            post_newtonian: None,
+            regularization: None,
+            output_redshifts: Vec::new(),
+            outputs: Vec::new(),
+            output_snapshots: None,
+        }
+    }
+
+    /// A simulation of the snapshot's bodies, keeping their IDs, in its
+    /// periodic box if it has one. With `cosmology` the integration is
+    /// comoving from the snapshot's redshift, and speeds are converted from
+    /// Gadget's a^½ dx/dt to a² dx/dt; without, time starts at the header's.
+    pub fn from_gadget(snapshot: GadgetSnapshot, cosmology: Option<Cosmology>) -> Self {
+        let header = snapshot.header;
+        let ids = snapshot.bodies.iter().map(|body| body.id).collect::<Vec<_>>();
+        let mut simulation = Simulation::from_bodies(snapshot.bodies);
+        for (body, &id) in simulation.bodies.iter_mut().zip(ids.iter()) {
+            body.id = id;
+        }
+        simulation.next_id = ids.iter().max().map_or(0, |&id| id + 1);
+        if header.box_size > 0.0 {
+            simulation.set_periodic_box(Some(PeriodicBox::new(header.box_size as f32)))
+                .expect("a new simulation runs with any box");
+        }
+        match cosmology {
+            Some(cosmology) => {
+                let factor = redshift_to_scale_factor(header.redshift).powf(1.5) as f32;
+                for body in simulation.bodies.iter_mut() {
+                    body.speed *= factor;
+                }
+                simulation.set_cosmology(cosmology, header.redshift)
+                    .expect("a new simulation runs with any cosmology");
+            }
+            None => simulation.time = header.time,
+        }
+        simulation
+    }
+
+    pub fn bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+
+    pub fn bodies_mut(&mut self) -> &mut [Body] {
+        self.accelerations_valid = false;
+        self.hermite_dt = None;
+        &mut self.bodies
+    }
+
+    /// Returns the index of the new body, which gets the next `Body::id`.
+    pub fn add_body(&mut self, body: Body) -> usize {
+        self.bodies.push(Body { id: self.next_id, ..body });
+        self.next_id += 1;
+        self.accelerations_valid = false;
+        self.hermite_dt = None;
+        self.bodies.len() - 1
+    }
+
+    /// Adds a body on the orbit described by `elements` around `bodies[primary]`.
+    pub fn add_body_on_orbit(&mut self, primary: usize, elements: &OrbitalElements, mass: f32, density: f32) -> usize {
+        let primary = self.bodies[primary];
+        let (position, speed) = elements.to_state_vectors(self.gravitational_constant * (primary.mass + mass));
+        self.add_body(Body::new_sp(primary.position + position, mass, primary.speed + speed, density))
+    }
+
+    /// Removes the bodies at `indices` and returns them in the order given.
+    /// Later bodies move down to fill the gaps; tracked orbits, binaries and
+    /// body colours follow them. Binaries that lose a member count as
+    /// disrupted, and orbit tracking stops if its primary is removed.
+    pub fn remove_bodies(&mut self, indices: &[usize]) -> Vec<Body> {
+        let removed = indices.iter().map(|&i| self.bodies[i]).collect::<Vec<_>>();
+
+        // New index of every old one, `None` for the removed bodies.
+        let mut remap = vec![Some(0); self.bodies.len()];
+        for &i in indices.iter() {
+            remap[i] = None;
+        }
+        for (next, index) in remap.iter_mut().flatten().enumerate() {
+            *index = next;
+        }
+
+        let mut n = 0;
+        self.bodies.retain(|_| {
+            n += 1;
+            remap[n - 1].is_some()
+        });
+        if let Some(colors) = self.body_colors.as_mut().filter(|colors| colors.len() == remap.len()) {
+            let mut n = 0;
+            colors.retain(|_| {
+                n += 1;
+                remap[n - 1].is_some()
+            });
         }
+
+        if let Some(mut tracking) = self.orbit_tracking.take() {
+            if let Some(primary) = remap[tracking.primary] {
+                tracking.primary = primary;
+                tracking.bodies = tracking.bodies.iter().filter_map(|&body| remap[body]).collect();
+                tracking.report = self.collect_orbit_report(&tracking);
+                self.orbit_tracking = Some(tracking);
+            }
+        }
+
+        if let Some(tracking) = self.binary_tracking.as_mut() {
+            let mut binaries = Vec::with_capacity(tracking.binaries.len());
+            for binary in tracking.binaries.iter() {
+                match (remap[binary.primary], remap[binary.secondary]) {
+                    (Some(primary), Some(secondary)) => binaries.push(Binary { primary, secondary, ..*binary }),
+                    _ => tracking.events.push(BinaryEvent { time: self.time, kind: BinaryEventKind::Disrupted, binary: *binary }),
+                }
+            }
+            tracking.binaries = binaries;
+        }
+
+        self.accelerations_valid = false;
+        self.hermite_dt = None;
+        removed
+    }
+
+    /// Removes bodies that meet `criterion` after every update, see `escapers`
+    /// and `mass_loss`; `None` stops removing them and clears the log.
+    pub fn set_escape_criterion(&mut self, criterion: Option<EscapeCriterion>) {
+        self.escape_tracking = criterion.map(|criterion| EscapeTracking {
+            criterion,
+            initial_mass: self.bodies.iter().map(|body| body.mass).sum(),
+            start_time: self.time,
+            escapers: Vec::new(),
+        });
+    }
+
+    /// Every body removed as an escaper, in the order they left.
+    pub fn escapers(&self) -> &[Escaper] {
+        match &self.escape_tracking {
+            Some(tracking) => &tracking.escapers,
+            None => &[],
+        }
+    }
+
+    pub fn mass_loss(&self) -> MassLoss {
+        let Some(tracking) = &self.escape_tracking else {
+            return MassLoss::default();
+        };
+
+        let mass = tracking.escapers.iter().map(|escaper| escaper.body.mass).sum::<f32>();
+        let elapsed = (self.time - tracking.start_time) as f32;
+        MassLoss {
+            escapers: tracking.escapers.len(),
+            mass,
+            fraction: if tracking.initial_mass > 0.0 { mass / tracking.initial_mass } else { 0.0 },
+            rate: if elapsed > 0.0 { mass / elapsed } else { 0.0 },
+        }
+    }
+
+    fn remove_escapers(&mut self) {
+        let Some(criterion) = self.escape_tracking.as_ref().map(|tracking| tracking.criterion) else {
+            return;
+        };
+
+        let (speed, distant) = criterion.distant_bodies(&self.bodies, |a, b| self.nearest_image(a, b).position - a.position);
+        let escapers = distant.into_iter()
+            .map(|(i, distance)| (i, distance, self.specific_energy(i, speed)))
+            .filter(|&(_, _, energy)| !criterion.requires_unbound() || energy > 0.0)
+            .collect::<Vec<_>>();
+        if escapers.is_empty() {
+            return;
+        }
+
+        let indices = escapers.iter().map(|&(i, _, _)| i).collect::<Vec<_>>();
+        let removed = self.remove_bodies(&indices);
+        let time = self.time;
+        if let Some(tracking) = self.escape_tracking.as_mut() {
+            for ((index, distance, specific_energy), body) in escapers.into_iter().zip(removed) {
+                tracking.escapers.push(Escaper { time, index, body, distance, specific_energy });
+            }
+        }
+    }
+
+    /// Energy per unit mass of `bodies[i]` moving relative to `speed`, in the
+    /// pull of the other massive bodies and the external potentials.
+    fn specific_energy(&self, i: usize, speed: Vector2<f32>) -> f32 {
+        // A unit-mass stand-in, so test particles get a potential too.
+        let body = Body { mass: 1.0, ..self.bodies[i] };
+        let parameters = self.force_parameters();
+
+        let mut energy = 0.5 * (body.speed - speed).magnitude2();
+        for potential in self.external_potentials.iter() {
+            energy += potential.potential(body.position, self.gravitational_constant);
+        }
+        if self.self_gravity {
+            for (j, other) in self.bodies.iter().enumerate() {
+                if j != i && !other.test_particle {
+                    energy += self.pair_potential_energy(&body, other, &parameters);
+                }
+            }
+        }
+        energy
+    }
+
+    /// Writes the bodies as a Gadget snapshot, see `write_gadget`. Under
+    /// comoving integration the header holds the scale factor and the
+    /// cosmology, and speeds are converted to Gadget's a^½ dx/dt. h is 1, so
+    /// the reading tools take lengths in the simulation's units, not per h.
+    pub fn write_gadget_snapshot(&self, writer: impl Write, format: GadgetFormat) -> io::Result<()> {
+        let mut header = GadgetHeader {
+            time: self.time,
+            box_size: self.periodic_box.as_ref().map_or(0.0, |periodic_box| periodic_box.size as f64),
+            ..GadgetHeader::default()
+        };
+        let Some(cosmology) = self.cosmology else {
+            return write_gadget(writer, &header, &self.bodies, format);
+        };
+
+        let scale_factor = self.scale_factor();
+        header.time = scale_factor;
+        header.redshift = scale_factor_to_redshift(scale_factor);
+        header.omega_matter = cosmology.omega_matter;
+        header.omega_lambda = cosmology.omega_lambda;
+        let factor = scale_factor.powf(-1.5) as f32;
+        let bodies = self.bodies.iter().map(|body| Body { speed: body.speed * factor, ..*body }).collect::<Vec<_>>();
+        write_gadget(writer, &header, &bodies, format)
+    }
+
+    /// Writes the header and the current bodies to `writer`, then a snapshot
+    /// at the cadence of `export` after every update, replacing any export
+    /// already running. Escapers are removed before a snapshot is taken.
+    /// Writes go through a buffer that `stop_export` flushes.
+    pub fn start_export(&mut self, export: BodyExport, writer: Box<dyn Write + Send + Sync>) -> io::Result<()> {
+        if export.fields.acceleration {
+            self.ensure_accelerations();
+        }
+        let mut writer = BufWriter::new(writer);
+        export.write_header(&mut writer)?;
+        export.write_snapshot(&mut writer, &self.bodies, self.time)?;
+        writer.flush()?;
+        self.export = Some(Export {
+            export,
+            writer,
+            updates: 0,
+            last_time: self.time,
+            error: None,
+        });
+        Ok(())
+    }
+
+    /// Flushes and closes the export, returning the first error any update
+    /// met writing to it; snapshots stop at that error.
+    pub fn stop_export(&mut self) -> io::Result<()> {
+        let Some(mut export) = self.export.take() else {
+            return Ok(());
+        };
+        match export.error {
+            Some(error) => Err(error),
+            None => export.writer.flush(),
+        }
+    }
+
+    fn write_export(&mut self) {
+        let due = match &mut self.export {
+            Some(export) if export.error.is_none() => {
+                export.updates += 1;
+                match export.export.cadence {
+                    ExportCadence::EveryUpdates(updates) => export.updates >= updates.max(1),
+                    ExportCadence::EveryTime(interval) => self.time - export.last_time >= interval,
+                }
+            }
+            _ => false,
+        };
+        if !due {
+            return;
+        }
+
+        if self.export.as_ref().is_some_and(|export| export.export.fields.acceleration) {
+            self.ensure_accelerations();
+        }
+        let time = self.time;
+        if let Some(export) = self.export.as_mut() {
+            export.updates = 0;
+            export.last_time = time;
+            if let Err(error) = export.export.write_snapshot(&mut export.writer, &self.bodies, time) {
+                export.error = Some(error);
+            }
+        }
+    }
+
+    /// Removing escapers leaves the accelerations stale, so exporting them
+    /// costs an extra force evaluation for the snapshots that follow.
+    fn ensure_accelerations(&mut self) {
+        if !self.accelerations_valid {
+            self.compute_accelerations();
+            self.accelerations_valid = true;
+        }
+    }
+
+    /// Elements of `bodies[body]` relative to `bodies[primary]`.
+    pub fn orbital_elements(&self, body: usize, primary: usize) -> OrbitalElements {
+        let body = &self.bodies[body];
+        let primary = &self.bodies[primary];
+        OrbitalElements::from_state_vectors(
+            body.position - primary.position,
+            body.speed - primary.speed,
+            self.gravitational_constant * (primary.mass + body.mass),
+        )
+    }
+
+    /// Reports the elements of `bodies` around `primary` after every update,
+    /// see `orbit_report`.
+    pub fn track_orbits(&mut self, primary: usize, bodies: Vec<usize>) {
+        let mut tracking = OrbitTracking {
+            primary,
+            bodies,
+            report: Vec::new(),
+        };
+        tracking.report = self.collect_orbit_report(&tracking);
+        self.orbit_tracking = Some(tracking);
+    }
+
+    pub fn stop_tracking_orbits(&mut self) {
+        self.orbit_tracking = None;
+    }
+
+    /// Body index and elements for every tracked body as of the last update.
+    pub fn orbit_report(&self) -> &[(usize, OrbitalElements)] {
+        match &self.orbit_tracking {
+            Some(tracking) => &tracking.report,
+            None => &[],
+        }
+    }
+
+    fn collect_orbit_report(&self, tracking: &OrbitTracking) -> Vec<(usize, OrbitalElements)> {
+        tracking.bodies.iter()
+            .map(|&body| (body, self.orbital_elements(body, tracking.primary)))
+            .collect()
+    }
+
+    /// Bound pairs and hierarchical triples among the bodies right now, bound
+    /// under the pair force.
+    pub fn find_multiples(&self, finder: &BinaryFinder) -> Multiples {
+        let parameters = self.force_parameters();
+        finder.find(
+            &self.bodies,
+            self.gravitational_constant,
+            |a, b| self.nearest_image(a, b).position - a.position,
+            |a, b| self.pair_force.potential_energy(a, &self.nearest_image(a, b), &parameters),
+        )
+    }
+
+    /// Looks for binaries with `finder` after every update and records when
+    /// they form and break up, see `binary_events`. Binaries present now are
+    /// taken as the starting point rather than as formed.
+    pub fn track_binaries(&mut self, finder: BinaryFinder) {
+        self.binary_tracking = Some(BinaryTracking {
+            finder,
+            binaries: self.find_multiples(&finder).binaries,
+            events: Vec::new(),
+        });
+    }
+
+    pub fn stop_tracking_binaries(&mut self) {
+        self.binary_tracking = None;
+    }
+
+    /// Binaries as of the last update.
+    pub fn tracked_binaries(&self) -> &[Binary] {
+        match &self.binary_tracking {
+            Some(tracking) => &tracking.binaries,
+            None => &[],
+        }
+    }
+
+    /// Binary formations and disruptions in the order they happened.
+    pub fn binary_events(&self) -> &[BinaryEvent] {
+        match &self.binary_tracking {
+            Some(tracking) => &tracking.events,
+            None => &[],
+        }
+    }
+
+    fn record_binary_events(&self, tracking: &mut BinaryTracking) {
+        let binaries = self.find_multiples(&tracking.finder).binaries;
+
+        for old in tracking.binaries.iter().filter(|old| !binaries.iter().any(|binary| binary.same_pair(old))) {
+            tracking.events.push(BinaryEvent { time: self.time, kind: BinaryEventKind::Disrupted, binary: *old });
+        }
+        for new in binaries.iter().filter(|new| !tracking.binaries.iter().any(|binary| binary.same_pair(new))) {
+            tracking.events.push(BinaryEvent { time: self.time, kind: BinaryEventKind::Formed, binary: *new });
+        }
+        tracking.binaries = binaries;
+    }
+
+    /// Clumps among the bodies right now.
+    pub fn find_groups(&self, finder: &GroupFinder) -> Groups {
+        finder.find(&self.bodies, |a, b| self.nearest_image(a, b).position - a.position)
+    }
+
+    /// Draws body `i` in `colors[i]`, for instance `Groups::colors`. Ignored
+    /// once the number of bodies no longer matches.
+    pub fn set_body_colors(&mut self, colors: Option<Vec<u32>>) {
+        self.body_colors = colors;
+    }
+
+    /// Fails for block steps with regularization on, which they do not apply.
+    pub fn set_timestep(&mut self, timestep: Timestep) -> Result<(), ConfigurationError> {
+        let block = matches!(timestep, Timestep::Block(_));
+        Configuration { block, ..self.configuration() }.check()?;
+        // Block steps assign the rungs along with the first forces.
+        if block {
+            self.accelerations_valid = false;
+        }
+        self.timestep = timestep;
+        self.hermite_dt = None;
+        Ok(())
+    }
+
+    pub fn timestep(&self) -> Timestep {
+        self.timestep
+    }
+
+    /// Turns every body around, so integrating on retraces the way they came.
+    /// Leapfrog on fixed steps retraces its steps exactly bar round-off. Fails
+    /// under a cosmology, where the expansion does not run backwards with them.
+    pub fn reverse_velocities(&mut self) -> Result<(), ConfigurationError> {
+        if self.cosmology.is_some() {
+            return Err(ConfigurationError::ReversalWithCosmology);
+        }
+        for body in self.bodies.iter_mut() {
+            body.speed = -body.speed;
+            body.jerk = -body.jerk;
+        }
+        // Velocity-dependent forces change sign with the speeds.
+        self.accelerations_valid = false;
+        self.hermite_dt = None;
+        Ok(())
+    }
+
+    /// Fails if the forces cannot give the Hermite integrator a jerk.
+    pub fn set_integrator(&mut self, integrator: Integrator) -> Result<(), ConfigurationError> {
+        Configuration { integrator, ..self.configuration() }.check()?;
+        self.integrator = integrator;
+        self.accelerations_valid = false;
+        Ok(())
+    }
+
+    pub fn gravitational_constant(&self) -> f32 {
+        self.gravitational_constant
+    }
+
+    pub fn set_gravitational_constant(&mut self, gravitational_constant: f32) {
+        self.gravitational_constant = gravitational_constant;
+        self.accelerations_valid = false;
+    }
+
+    /// Interaction summed over every pair of bodies, `Gravity` by default.
+    /// Fails if the force solver evaluates a different law.
+    pub fn set_pair_force(&mut self, pair_force: Box<dyn PairForce>) -> Result<(), ConfigurationError> {
+        Configuration { pair_force: pair_force.law(), ..self.configuration() }.check()?;
+        self.pair_force = pair_force;
+        self.accelerations_valid = false;
+        Ok(())
+    }
+
+    /// Scheme for the bodies' pull on each other, `ForceSolver::Direct` by default.
+    /// Fails if the solver cannot run with the rest of the settings.
+    pub fn set_force_solver(&mut self, force_solver: ForceSolver) -> Result<(), ConfigurationError> {
+        Configuration { force_solver, ..self.configuration() }.check()?;
+        self.force_solver = force_solver;
+        self.accelerations_valid = false;
+        Ok(())
+    }
+
+    /// Adds the 1PN relativistic terms to the pull of the massive bodies, or
+    /// removes them with `None`. They contribute no jerk, so they cannot run
+    /// under `Integrator::Hermite`.
+    pub fn set_post_newtonian(&mut self, post_newtonian: Option<PostNewtonian>) -> Result<(), ConfigurationError> {
+        Configuration { post_newtonian: post_newtonian.is_some(), ..self.configuration() }.check()?;
+        self.post_newtonian = post_newtonian;
+        self.accelerations_valid = false;
+        Ok(())
+    }
+
+    /// Advances tight pairs with Levi-Civita regularization, so they neither
+    /// need nor force a short global step. Needs leapfrog on fixed or adaptive
+    /// steps, and gravity as the pair force under the direct solver.
+    pub fn set_regularization(&mut self, regularization: Option<Regularization>) -> Result<(), ConfigurationError> {
+        Configuration { regularization: regularization.is_some(), ..self.configuration() }.check()?;
+        self.regularization = regularization;
+        Ok(())
+    }
+
+    fn configuration(&self) -> Configuration {
+        Configuration {
+            integrator: self.integrator,
+            block: matches!(self.timestep, Timestep::Block(_)),
+            force_solver: self.force_solver,
+            periodic_box: self.periodic_box.is_some(),
+            pair_force: self.pair_force.law(),
+            cosmology: self.cosmology.is_some(),
+            post_newtonian: self.post_newtonian.is_some(),
+            regularization: self.regularization.is_some(),
+            body_forces: !self.body_forces.is_empty(),
+        }
+    }
+
+    fn force_parameters(&self) -> ForceParameters {
+        ForceParameters {
+            gravitational_constant: self.gravitational_constant,
+            softening: self.softening,
+        }
+    }
+
+    /// Whether bodies interact with each other through the pair force. With it
+    /// off, only the external potentials act on them.
+    pub fn set_self_gravity(&mut self, self_gravity: bool) {
+        self.self_gravity = self_gravity;
+        self.accelerations_valid = false;
+    }
+
+    /// Adds a background potential; all of them are summed into every body's
+    /// acceleration and into `potential_energy`.
+    pub fn add_external_potential(&mut self, potential: Box<dyn ExternalPotential>) {
+        self.external_potentials.push(potential);
+        self.accelerations_valid = false;
+    }
+
+    pub fn clear_external_potentials(&mut self) {
+        self.external_potentials.clear();
+        self.accelerations_valid = false;
+    }
+
+    /// Adds a per-body force such as drag; all of them are summed into every
+    /// body's acceleration alongside gravity. They contribute no jerk, so they
+    /// cannot run under `Integrator::Hermite`.
+    pub fn add_body_force(&mut self, force: Box<dyn BodyForce>) -> Result<(), ConfigurationError> {
+        Configuration { body_forces: true, ..self.configuration() }.check()?;
+        self.body_forces.push(force);
+        self.accelerations_valid = false;
+        Ok(())
+    }
+
+    pub fn clear_body_forces(&mut self) {
+        self.body_forces.clear();
+        self.accelerations_valid = false;
+    }
+
+    /// Plummer softening length used by gravity and the electrostatic pair forces.
+    pub fn set_softening(&mut self, softening: f32) {
+        self.softening = softening;
+        self.accelerations_valid = false;
+    }
+
+    /// Wraps positions into the box and makes bodies interact with the nearest
+    /// image of each other, plus the Ewald sum over the further images when the
+    /// box has one. `None` restores open boundaries, which fails under the
+    /// particle-mesh solver.
+    pub fn set_periodic_box(&mut self, periodic_box: Option<PeriodicBox>) -> Result<(), ConfigurationError> {
+        Configuration { periodic_box: periodic_box.is_some(), ..self.configuration() }.check()?;
+        if let Some(periodic_box) = &periodic_box {
+            for body in self.bodies.iter_mut() {
+                body.wrap(Some(periodic_box));
+            }
+        }
+        self.periodic_box = periodic_box;
+        self.accelerations_valid = false;
+        self.hermite_dt = None;
+        Ok(())
+    }
+
+    pub fn periodic_box(&self) -> Option<&PeriodicBox> {
+        self.periodic_box.as_ref()
+    }
+
+    /// Integrates in comoving coordinates on `cosmology`'s expanding background,
+    /// starting at `initial_redshift`. Positions become comoving, `speed` the
+    /// canonical momentum per unit mass a² dx/dt, and `time` cosmic time. The
+    /// forces are taken as the comoving peculiar ones, which needs the periodic
+    /// box to subtract the mean density; with open boundaries the bodies should
+    /// form an isolated perturbation. Leapfrog applies the expansion on every
+    /// kind of timestep; `Integrator::Hermite` does not, and is rejected.
+    pub fn set_cosmology(&mut self, cosmology: Cosmology, initial_redshift: f64) -> Result<(), ConfigurationError> {
+        Configuration { cosmology: true, ..self.configuration() }.check()?;
+        self.time = cosmology.time(redshift_to_scale_factor(initial_redshift));
+        self.cosmology = Some(cosmology);
+        self.accelerations_valid = false;
+        self.hermite_dt = None;
+        Ok(())
+    }
+
+    pub fn clear_cosmology(&mut self) {
+        self.cosmology = None;
+        self.accelerations_valid = false;
+    }
+
+    pub fn cosmology(&self) -> Option<&Cosmology> {
+        self.cosmology.as_ref()
+    }
+
+    /// Expansion factor at the current time, 1 without a cosmology.
+    pub fn scale_factor(&self) -> f64 {
+        match &self.cosmology {
+            Some(cosmology) => cosmology.scale_factor(self.time),
+            None => 1.0,
+        }
+    }
+
+    pub fn redshift(&self) -> f64 {
+        scale_factor_to_redshift(self.scale_factor())
+    }
+
+    /// Redshifts to record the bodies at, see `take_outputs` and
+    /// `start_output_snapshots`. Fixed and
+    /// adaptive steps are shortened to land on them; block steps record at the
+    /// end of the update that passes them.
+    pub fn set_output_redshifts(&mut self, mut redshifts: Vec<f64>) {
+        redshifts.sort_by(|a, b| b.total_cmp(a));
+        self.output_redshifts = redshifts;
+    }
+
+    /// Outputs recorded since the last call.
+    pub fn take_outputs(&mut self) -> Vec<CosmologicalOutput> {
+        std::mem::take(&mut self.outputs)
+    }
+
+    /// Also writes every output from now on to `directory` as a Gadget
+    /// snapshot, `snapshot_000`, `snapshot_001` and so on in the order they
+    /// are reached. Creates the directory if needed.
+    pub fn start_output_snapshots(&mut self, directory: impl Into<PathBuf>, format: GadgetFormat) -> io::Result<()> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        self.output_snapshots = Some(OutputSnapshots {
+            directory,
+            format,
+            written: 0,
+            error: None,
+        });
+        Ok(())
+    }
+
+    /// Stops writing output snapshots, returning the first error any update
+    /// met writing them; snapshots stop at that error.
+    pub fn stop_output_snapshots(&mut self) -> io::Result<()> {
+        match self.output_snapshots.take().and_then(|snapshots| snapshots.error) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn write_output_snapshot(&mut self) {
+        let Some(snapshots) = self.output_snapshots.as_ref().filter(|snapshots| snapshots.error.is_none()) else {
+            return;
+        };
+        let path = snapshots.directory.join(format!("snapshot_{:03}", snapshots.written));
+        let format = snapshots.format;
+
+        let result = File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            self.write_gadget_snapshot(&mut writer, format)?;
+            writer.flush()
+        });
+        if let Some(snapshots) = self.output_snapshots.as_mut() {
+            match result {
+                Ok(()) => snapshots.written += 1,
+                Err(error) => snapshots.error = Some(error),
+            }
+        }
+    }
+
+    fn next_output_time(&self) -> Option<f64> {
+        let cosmology = self.cosmology.as_ref()?;
+        let redshift = *self.output_redshifts.first()?;
+        Some(cosmology.time(redshift_to_scale_factor(redshift)))
+    }
+
+    fn record_outputs(&mut self) {
+        while let Some(output_time) = self.next_output_time() {
+            if self.time < output_time {
+                break;
+            }
+            self.output_redshifts.remove(0);
+            let scale_factor = self.scale_factor();
+            self.outputs.push(CosmologicalOutput {
+                redshift: scale_factor_to_redshift(scale_factor),
+                scale_factor,
+                time: self.time,
+                bodies: self.bodies.clone(),
+            });
+            self.write_output_snapshot();
+        }
+    }
+
+    /// What `acceleration` is multiplied by to kick over cosmic times `from` to
+    /// `to`: the interval itself, or ∫ dt / a when comoving.
+    fn kick_factor(&self, from: f64, to: f64) -> f32 {
+        match &self.cosmology {
+            Some(cosmology) => cosmology.kick_factor(cosmology.scale_factor(from), cosmology.scale_factor(to)) as f32,
+            None => (to - from) as f32,
+        }
+    }
+
+    /// What `speed` is multiplied by to drift: the interval, or ∫ dt / a².
+    fn drift_factor(&self, from: f64, to: f64) -> f32 {
+        match &self.cosmology {
+            Some(cosmology) => cosmology.drift_factor(cosmology.scale_factor(from), cosmology.scale_factor(to)) as f32,
+            None => (to - from) as f32,
+        }
+    }
+
+    /// Step size used by the most recent update.
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Total simulated time.
+    pub fn time(&self) -> f64 {
+        self.time
     }
 
     pub fn get_bodies_as_circles(&mut self) -> Vec<Circle> {
-        let circle_bodies = self.bodies.clone().iter().map(Body::to_circle).collect::<Vec<_>>();
-        return circle_bodies;
+        let mut circles = self.bodies.iter().map(Body::to_circle).collect::<Vec<_>>();
+        if let Some(colors) = self.body_colors.as_ref().filter(|colors| colors.len() == circles.len()) {
+            for (circle, &color) in circles.iter_mut().zip(colors) {
+                circle.color = color;
+            }
+        }
+
+        if let Some(periodic_box) = &self.periodic_box {
+            let bodies = circles.len();
+            let size = periodic_box.size;
+
+            for (x, y) in (-1..=1).flat_map(|x| (-1..=1).map(move |y| (x, y))).filter(|&tile| tile != (0, 0)) {
+                for n in 0..bodies {
+                    let mut image = circles[n];
+                    image.world_pos[0] += x as f32 * size;
+                    image.world_pos[1] += y as f32 * size;
+                    image.color = image.color & 0xFFFFFF00 | IMAGE_ALPHA;
+                    circles.push(image);
+                }
+            }
+        }
+
+        circles
+    }
+
+    /// The edges of the periodic box as a line list, empty with open boundaries.
+    pub fn get_box_outline(&self) -> Vec<Vertex> {
+        let Some(periodic_box) = &self.periodic_box else {
+            return Vec::new();
+        };
+
+        let half = 0.5 * periodic_box.size;
+        let corners = [(-half, -half), (half, -half), (half, half), (-half, half)];
+        (0..4)
+            .flat_map(|n| [corners[n], corners[(n + 1) % 4]])
+            .map(|(x, y)| Vertex { position: [x, y, 0.0], color: BOX_OUTLINE_COLOR })
+            .collect()
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        self.bodies.iter()
+            .map(|body| 0.5 * body.mass * body.speed.magnitude2())
+            .sum()
+    }
+
+    pub fn potential_energy(&self) -> f32 {
+        let mut energy = 0.0;
+
+        for body in self.bodies.iter() {
+            for potential in self.external_potentials.iter() {
+                energy += body.mass * potential.potential(body.position, self.gravitational_constant);
+            }
+        }
+
+        energy + self.interaction_energy()
+    }
+
+    /// Potential energy of the bodies' pull on each other alone, from the mesh
+    /// under the particle-mesh solver.
+    pub fn interaction_energy(&self) -> f32 {
+        if !self.self_gravity {
+            return 0.0;
+        }
+        if let (ForceSolver::ParticleMesh(mesh), Some(periodic_box)) = (self.force_solver, &self.periodic_box) {
+            return mesh.potential_energy(&self.bodies, periodic_box, self.gravitational_constant);
+        }
+
+        let parameters = self.force_parameters();
+        let mut energy = 0.0;
+        let massive = self.bodies.iter().filter(|body| !body.test_particle).collect::<Vec<_>>();
+        for (n, body) in massive.iter().enumerate() {
+            for other in massive[n + 1..].iter() {
+                energy += self.pair_potential_energy(body, other, &parameters);
+            }
+        }
+
+        energy
+    }
+
+    /// Lagrangian radii, profiles, relaxation time and virial ratio of the
+    /// massive bodies taken as one cluster.
+    pub fn cluster_diagnostics(&self, analysis: &ClusterAnalysis) -> ClusterDiagnostics {
+        analysis.analyze(&self.bodies, self.gravitational_constant, self.interaction_energy(), self.time, |a, b| {
+            self.nearest_image(a, b).position - a.position
+        })
+    }
+
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic_energy() + self.potential_energy()
     }
 
     pub fn update(&mut self) {
-        let bodies_len = self.bodies.len();
+        match (self.integrator, self.timestep) {
+            (Integrator::Hermite, _) => self.update_hermite(),
+            (Integrator::Leapfrog, Timestep::Block(block)) => self.update_block(block),
+            (Integrator::Leapfrog, _) => self.update_leapfrog(),
+        }
+
+        self.record_outputs();
+        self.remove_escapers();
+        self.write_export();
+
+        if let Some(mut tracking) = self.orbit_tracking.take() {
+            tracking.report = self.collect_orbit_report(&tracking);
+            self.orbit_tracking = Some(tracking);
+        }
+
+        if let Some(mut tracking) = self.binary_tracking.take() {
+            self.record_binary_events(&mut tracking);
+            self.binary_tracking = Some(tracking);
+        }
+    }
+
+    fn update_leapfrog(&mut self) {
+        if !self.accelerations_valid {
+            self.compute_accelerations();
+        }
+
+        let pairs = self.regularized_pairs();
+        self.dt = match self.timestep {
+            Timestep::Fixed(dt) => dt,
+            Timestep::Adaptive(adaptive) | Timestep::Block(adaptive) if pairs.is_empty() => adaptive.compute_dt(&self.bodies, self.gravitational_constant, self.periodic_box.as_ref()),
+            // Each pair counts as one body at its centre of mass, where the
+            // mutual pull cancels out.
+            Timestep::Adaptive(adaptive) | Timestep::Block(adaptive) => {
+                let merged = self.bodies.iter().enumerate()
+                    .filter(|(i, _)| !pairs.iter().any(|&(a, b)| *i == a || *i == b))
+                    .map(|(_, body)| *body)
+                    .chain(pairs.iter().map(|&(a, b)| centre_of_mass(&self.bodies[a], &self.bodies[b])))
+                    .collect::<Vec<_>>();
+                adaptive.compute_dt(&merged, self.gravitational_constant, self.periodic_box.as_ref())
+            }
+        };
+
+        let mut end = self.time + self.dt as f64;
+        if let Some(output_time) = self.next_output_time().filter(|&output_time| output_time > self.time && output_time < end) {
+            end = output_time;
+            self.dt = (end - self.time) as f32;
+        }
+        let middle = self.time + 0.5 * self.dt as f64;
+
+        let relativistic = self.post_newtonian_perturbations(&pairs);
+        let paired = pairs.iter().zip(relativistic)
+            .map(|(&(a, b), [relativistic_a, relativistic_b])| (
+                (self.bodies[a], self.perturbation_of(a, b) + relativistic_a),
+                (self.bodies[b], self.perturbation_of(b, a) + relativistic_b),
+            ))
+            .collect::<Vec<_>>();
+        let free = (0..self.bodies.len())
+            .filter(|i| !pairs.iter().any(|&(a, b)| *i == a || *i == b))
+            .collect::<Vec<_>>();
+
+        let (opening, drift) = (self.kick_factor(self.time, middle), self.drift_factor(self.time, end));
+        for &i in free.iter() {
+            self.bodies[i].kick(opening);
+            self.bodies[i].drift(drift, self.periodic_box.as_ref());
+        }
+
+        for (&(a, b), (body_a, body_b)) in pairs.iter().zip(paired) {
+            let (body_a, body_b) = self.advance_regularized_pair(body_a, body_b, self.dt);
+            self.bodies[a] = body_a;
+            self.bodies[b] = body_b;
+        }
+
+        self.compute_accelerations();
+        let closing = self.kick_factor(middle, end);
+        for &i in free.iter() {
+            self.bodies[i].kick(closing);
+        }
+
+        self.accelerations_valid = true;
+        self.time = end;
+    }
+
+    fn regularized_pairs(&self) -> Vec<(usize, usize)> {
+        match self.regularization {
+            Some(regularization) if self.cosmology.is_none() && self.self_gravity => {
+                regularization.find_pairs(&self.bodies, |a, b| self.nearest_image(a, b).position - a.position)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Acceleration of `bodies[i]` from everything but its pair partner. Summed
+    /// without the partner where possible, since the mutual pull of a tight
+    /// pair dwarfs the rest and subtracting it would leave only rounding noise.
+    fn perturbation_of(&self, i: usize, partner: usize) -> Vector2<f32> {
+        let body = &self.bodies[i];
+        let parameters = self.force_parameters();
+        let (mut acceleration, _) = external_acceleration(&self.external_potentials, &self.body_forces, body, self.gravitational_constant);
+        for (j, other) in self.bodies.iter().enumerate() {
+            if j != i && j != partner && !other.test_particle {
+                acceleration += self.acceleration_from(body, other, &parameters).0;
+            }
+        }
+        acceleration
+    }
+
+    /// The 1PN acceleration of each body of every pair from everything but the
+    /// pair's own two-body terms, which the regularized orbit evaluates itself.
+    fn post_newtonian_perturbations(&self, pairs: &[(usize, usize)]) -> Vec<[Vector2<f32>; 2]> {
+        let Some(post_newtonian) = self.post_newtonian.filter(|_| !pairs.is_empty()) else {
+            return vec![[Vector2::new(0.0, 0.0); 2]; pairs.len()];
+        };
+        let all = post_newtonian.accelerations(&self.bodies, self.gravitational_constant);
+        pairs.iter()
+            .map(|&(a, b)| {
+                let isolated = post_newtonian.accelerations(&[self.bodies[a], self.bodies[b]], self.gravitational_constant);
+                [all[a] - isolated[0], all[b] - isolated[1]]
+            })
+            .collect()
+    }
+
+    /// Moves the centre of mass of a regularized pair by the kick-drift step and
+    /// the relative orbit through the regularized two-body problem. Each body
+    /// comes with its state and its perturbation at the start of the step.
+    fn advance_regularized_pair(&self, (a, perturbation_a): (Body, Vector2<f32>), (b, perturbation_b): (Body, Vector2<f32>), dt: f32) -> (Body, Body) {
+        let regularization = self.regularization.expect("pairs are only found with regularization on");
+        let b_image = self.nearest_image(&a, &b);
+
+        let mut centre = centre_of_mass(&a, &b_image);
+        centre.speed += (perturbation_a * a.mass + perturbation_b * b.mass) / centre.mass * dt;
+        centre.position += centre.speed * dt;
+
+        let mu = (self.gravitational_constant * centre.mass) as f64;
+        let mass_ratio = (a.mass * b.mass / (centre.mass * centre.mass)) as f64;
+        let (separation, speed) = regularization.advance(
+            (b_image.position - a.position).cast::<f64>().unwrap(),
+            (b.speed - a.speed).cast::<f64>().unwrap(),
+            mu,
+            (perturbation_b - perturbation_a).cast::<f64>().unwrap(),
+            |position, speed| match self.post_newtonian {
+                Some(post_newtonian) => post_newtonian.relative_acceleration(position, speed, mu, mass_ratio),
+                None => Vector2::new(0.0, 0.0),
+            },
+            dt as f64,
+        );
+        let (separation, speed) = (separation.cast::<f32>().unwrap(), speed.cast::<f32>().unwrap());
+
+        let moved = |body: Body, share: f32| {
+            let mut body = Body {
+                position: centre.position + separation * share,
+                speed: centre.speed + speed * share,
+                acceleration: Vector2::new(0.0, 0.0),
+                jerk: Vector2::new(0.0, 0.0),
+                ..body
+            };
+            body.wrap(self.periodic_box.as_ref());
+            body
+        };
+        (moved(a, -b.mass / centre.mass), moved(b, a.mass / centre.mass))
+    }
 
+    fn update_hermite(&mut self) {
+        if !self.accelerations_valid {
+            self.compute_accelerations();
+            self.hermite_dt = None;
+        }
+
+        self.dt = match self.timestep {
+            Timestep::Fixed(dt) => dt,
+            Timestep::Adaptive(adaptive) | Timestep::Block(adaptive) => match self.hermite_dt {
+                Some(dt) => dt,
+                None => adaptive.compute_dt(&self.bodies, self.gravitational_constant, self.periodic_box.as_ref()),
+            },
+        };
+
+        let old_bodies = self.bodies.clone();
+        for body in self.bodies.iter_mut() {
+            hermite_predict(body, self.dt);
+        }
 
-        for body_from_i in 0..bodies_len - 1 {
-            for body_other in body_from_i + 1..bodies_len {
-                let body_to_vec = self.bodies[body_from_i].position - self.bodies[body_other].position;
-                // println!("pos0: {:?}, pos1: {:?}", self.bodies[0].position, self.bodies[1].position);
-                let a = self.bodies[body_from_i].compute_acceleration_to_other_body(&self.bodies[body_other]);
-                // println!("a: {a}");
-                self.bodies[body_from_i].acceleration += body_to_vec * -1.0 * a;
-                self.bodies[body_other].acceleration += body_to_vec * a;
+        self.compute_accelerations();
 
+        let mut next_dt = f32::INFINITY;
+        for (body, old) in self.bodies.iter_mut().zip(old_bodies.iter()) {
+            let (snap, crackle) = hermite_correct(body, old, self.dt);
+            body.wrap(self.periodic_box.as_ref());
+            if let Timestep::Adaptive(adaptive) | Timestep::Block(adaptive) = self.timestep {
+                if let TimestepCriterion::Aarseth = adaptive.criterion {
+                    next_dt = next_dt.min(adaptive.aarseth_dt(body.acceleration, body.jerk, snap, crackle));
+                }
             }
         }
+        self.hermite_dt = Some(next_dt).filter(|dt| dt.is_finite());
+
+        // The forces at the predicted state stand in for the corrected one.
+        self.accelerations_valid = true;
+        self.time += self.dt as f64;
+    }
 
+    /// Pairwise acceleration and jerk of every body plus the external
+    /// potentials, replacing the old values. Test particles only get the pull
+    /// of the massive bodies, so the cost is O(N_massive * N).
+    pub(crate) fn compute_accelerations(&mut self) {
         for body in self.bodies.iter_mut() {
-            body.update(T);
+            let (acceleration, jerk) = external_acceleration(&self.external_potentials, &self.body_forces, body, self.gravitational_constant);
+            body.acceleration = acceleration;
+            body.jerk = jerk;
+        }
+
+        if !self.self_gravity {
+            return;
+        }
+
+        self.add_self_gravity();
+
+        if let Some(post_newtonian) = self.post_newtonian {
+            let corrections = post_newtonian.accelerations(&self.bodies, self.gravitational_constant);
+            for (body, correction) in self.bodies.iter_mut().zip(corrections) {
+                body.acceleration += correction;
+            }
+        }
+    }
+
+    fn add_self_gravity(&mut self) {
+        let parameters = self.force_parameters();
+
+        if let Some(accelerations) = self.solver_accelerations() {
+            for (body, acceleration) in self.bodies.iter_mut().zip(accelerations) {
+                body.acceleration += acceleration;
+            }
+            return;
+        }
+
+        let (massive, test_particles): (Vec<usize>, Vec<usize>) = (0..self.bodies.len())
+            .partition(|&i| !self.bodies[i].test_particle);
+
+        for (n, &body_from_i) in massive.iter().enumerate() {
+            for &body_other in massive[n + 1..].iter() {
+                let [(a_from, jerk_from), (a_other, jerk_other)] =
+                    self.pair_acceleration(&self.bodies[body_from_i], &self.bodies[body_other], &parameters);
+
+                self.bodies[body_from_i].acceleration += a_from;
+                self.bodies[body_from_i].jerk += jerk_from;
+                self.bodies[body_other].acceleration += a_other;
+                self.bodies[body_other].jerk += jerk_other;
+            }
+        }
+
+        for &test_particle in test_particles.iter() {
+            for &body_other in massive.iter() {
+                let (a, jerk) = self.acceleration_from(&self.bodies[test_particle], &self.bodies[body_other], &parameters);
+
+                self.bodies[test_particle].acceleration += a;
+                self.bodies[test_particle].jerk += jerk;
+            }
+        }
+    }
+
+    /// Acceleration on `bodies[i]` from every other body, for when only a few
+    /// bodies need their forces refreshed.
+    fn compute_acceleration_of(&self, i: usize) -> (Vector2<f32>, Vector2<f32>) {
+        let body = &self.bodies[i];
+        let parameters = self.force_parameters();
+        let (mut acceleration, mut jerk) = external_acceleration(&self.external_potentials, &self.body_forces, body, self.gravitational_constant);
+
+        for (j, other) in self.bodies.iter().enumerate() {
+            if self.self_gravity && j != i && !other.test_particle {
+                let (a, da) = self.acceleration_from(body, other, &parameters);
+                acceleration += a;
+                jerk += da;
+            }
+        }
+
+        (acceleration, jerk)
+    }
+
+    /// Accelerations and jerks of `indices` alone. The mesh and multipole
+    /// solvers have no per-body shortcut, so they solve for everyone and keep
+    /// the requested ones.
+    fn compute_accelerations_of(&self, indices: &[usize]) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+        let mut accelerations = match self.solver_accelerations().filter(|_| self.self_gravity) {
+            Some(accelerations) => {
+                indices.iter()
+                    .map(|&i| {
+                        let (external, jerk) = external_acceleration(&self.external_potentials, &self.body_forces, &self.bodies[i], self.gravitational_constant);
+                        (external + accelerations[i], jerk)
+                    })
+                    .collect::<Vec<_>>()
+            }
+            None => indices.iter().map(|&i| self.compute_acceleration_of(i)).collect(),
+        };
+
+        if let Some(post_newtonian) = self.post_newtonian.filter(|_| self.self_gravity) {
+            let corrections = post_newtonian.accelerations_of(&self.bodies, self.gravitational_constant, indices);
+            for ((acceleration, _), correction) in accelerations.iter_mut().zip(corrections) {
+                *acceleration += correction;
+            }
+        }
+
+        accelerations
+    }
+
+    /// Self-gravity of every body from the solvers that do not go pair by pair.
+    fn solver_accelerations(&self) -> Option<Vec<Vector2<f32>>> {
+        match self.force_solver {
+            ForceSolver::Direct => None,
+            ForceSolver::ParticleMesh(mesh) => {
+                let periodic_box = self.periodic_box.as_ref()?;
+                Some(mesh.accelerations(&self.bodies, periodic_box, self.gravitational_constant))
+            }
+            ForceSolver::FastMultipole(multipole) => {
+                Some(multipole.accelerations(&self.bodies, self.gravitational_constant, self.softening))
+            }
         }
     }
+
+    /// `other` moved to its image nearest `body` when the box is periodic.
+    fn nearest_image(&self, body: &Body, other: &Body) -> Body {
+        match &self.periodic_box {
+            Some(periodic_box) => Body {
+                position: body.position + periodic_box.minimum_image(other.position - body.position),
+                ..*other
+            },
+            None => *other,
+        }
+    }
+
+    /// Ewald potential and gradient for the pair, if the box has an Ewald table
+    /// and the pair force a 1/r tail: `(c * potential, c * gradient)` with the
+    /// gradient taken with respect to `body.position - other.position`.
+    fn ewald_correction(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> Option<(f32, Vector2<f32>)> {
+        let periodic_box = self.periodic_box.as_ref()?;
+        let coupling = self.pair_force.inverse_distance_coupling(body, other, parameters)?;
+        let (potential, gradient) = periodic_box.ewald_correction(body.position - other.position)?;
+        Some((coupling * potential, gradient * coupling))
+    }
+
+    /// Ewald acceleration and jerk of `body` from the further images of
+    /// `other`. A massless body is treated as a unit mass, so test particles
+    /// feel them too.
+    fn ewald_acceleration(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> (Vector2<f32>, Vector2<f32>) {
+        let body = if body.mass == 0.0 { Body { mass: 1.0, ..*body } } else { *body };
+        self.ewald_pull(&body, other, parameters).unwrap_or((Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0)))
+    }
+
+    fn ewald_pull(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> Option<(Vector2<f32>, Vector2<f32>)> {
+        let periodic_box = self.periodic_box.as_ref()?;
+        let coupling = self.pair_force.inverse_distance_coupling(body, other, parameters)?;
+        let separation = body.position - other.position;
+        let (_, gradient) = periodic_box.ewald_correction(separation)?;
+        let [xx, xy, yy] = periodic_box.ewald_hessian(separation)?;
+
+        let dv = body.speed - other.speed;
+        let rate = Vector2::new(xx * dv.x + xy * dv.y, xy * dv.x + yy * dv.y);
+        let scale = -coupling / body.mass;
+        Some((gradient * scale, rate * scale))
+    }
+
+    fn pair_acceleration(&self, a: &Body, b: &Body, parameters: &ForceParameters) -> [(Vector2<f32>, Vector2<f32>); 2] {
+        let b = self.nearest_image(a, b);
+        let mut accelerations = self.pair_force.pair_acceleration(a, &b, parameters);
+        for (pull, (body, other)) in accelerations.iter_mut().zip([(a, &b), (&b, a)]) {
+            let (acceleration, jerk) = self.ewald_acceleration(body, other, parameters);
+            pull.0 += acceleration;
+            pull.1 += jerk;
+        }
+        accelerations
+    }
+
+    /// Pull of `other` on `body`, including its periodic images.
+    fn acceleration_from(&self, body: &Body, other: &Body, parameters: &ForceParameters) -> (Vector2<f32>, Vector2<f32>) {
+        let other = self.nearest_image(body, other);
+        let (acceleration, jerk) = self.pair_force.acceleration(body, &other, parameters);
+        let (ewald_acceleration, ewald_jerk) = self.ewald_acceleration(body, &other, parameters);
+        (acceleration + ewald_acceleration, jerk + ewald_jerk)
+    }
+
+    fn pair_potential_energy(&self, a: &Body, b: &Body, parameters: &ForceParameters) -> f32 {
+        let b = self.nearest_image(a, b);
+        let correction = self.ewald_correction(a, &b, parameters).map_or(0.0, |(potential, _)| potential);
+        self.pair_force.potential_energy(a, &b, parameters) + correction
+    }
+
+    /// Advances every body by `block.max_dt` using kick-drift-kick leapfrog on
+    /// its own power-of-two rung. All bodies drift every sub-step, but only the
+    /// bodies whose step ends get new forces and their closing half-kick.
+    fn update_block(&mut self, block: AdaptiveTimestep) {
+        let max_rung = block.max_rung();
+
+        if !self.accelerations_valid {
+            self.compute_accelerations();
+            for i in 0..self.bodies.len() {
+                let rung = block.rung_for_dt(block.compute_body_dt(&self.bodies, i, self.gravitational_constant, self.periodic_box.as_ref()));
+                self.bodies[i].rung = rung;
+            }
+        }
+
+        let substep_dt = block.rung_dt(max_rung) as f64;
+        let stride = |rung: u32| 1u32 << (max_rung - rung);
+        let start = self.time;
+
+        for step in 0..1u32 << max_rung {
+            let (from, to) = (start + step as f64 * substep_dt, start + (step + 1) as f64 * substep_dt);
+            let opening_kicks = (0..=max_rung)
+                .map(|rung| self.kick_factor(from, from + 0.5 * block.rung_dt(rung) as f64))
+                .collect::<Vec<_>>();
+            let closing_kicks = (0..=max_rung)
+                .map(|rung| self.kick_factor(to - 0.5 * block.rung_dt(rung) as f64, to))
+                .collect::<Vec<_>>();
+            let drift = self.drift_factor(from, to);
+
+            for body in self.bodies.iter_mut() {
+                if step % stride(body.rung) == 0 {
+                    body.kick(opening_kicks[body.rung as usize]);
+                }
+            }
+
+            for body in self.bodies.iter_mut() {
+                body.drift(drift, self.periodic_box.as_ref());
+            }
+
+            let active = (0..self.bodies.len())
+                .filter(|&i| (step + 1) % stride(self.bodies[i].rung) == 0)
+                .collect::<Vec<_>>();
+
+            let accelerations = self.compute_accelerations_of(&active);
+            for (&i, (acceleration, jerk)) in active.iter().zip(accelerations) {
+                self.bodies[i].acceleration = acceleration;
+                self.bodies[i].jerk = jerk;
+            }
+
+            for &i in active.iter() {
+                let body = &mut self.bodies[i];
+                body.kick(closing_kicks[body.rung as usize]);
+            }
+
+            // A body may only move to a longer step where that step's boundaries
+            // line up with the current time, which keeps the kicks paired.
+            for &i in active.iter() {
+                let mut rung = block.rung_for_dt(block.compute_body_dt(&self.bodies, i, self.gravitational_constant, self.periodic_box.as_ref()));
+                while rung < self.bodies[i].rung && (step + 1) % stride(rung) != 0 {
+                    rung += 1;
+                }
+                self.bodies[i].rung = rung;
+            }
+        }
+
+        self.accelerations_valid = true;
+        self.dt = block.max_dt;
+        self.time = start + block.max_dt as f64;
+    }
+}
+
+fn external_acceleration(potentials: &[Box<dyn ExternalPotential>], forces: &[Box<dyn BodyForce>], body: &Body, g: f32) -> (Vector2<f32>, Vector2<f32>) {
+    let mut acceleration = Vector2::new(0.0, 0.0);
+    let mut jerk = Vector2::new(0.0, 0.0);
+
+    for potential in potentials.iter() {
+        acceleration += potential.acceleration(body.position, g);
+        jerk += potential.jerk(body.position, body.speed, g);
+    }
+
+    for force in forces.iter() {
+        acceleration += force.acceleration(body, g);
+    }
+
+    (acceleration, jerk)
 }
 
 fn create_spiral_cluster(num_bodies: usize, average_spacing: f32) -> Vec<Body> {
@@ -61,7 +1411,7 @@ fn create_spiral_cluster(num_bodies: usize, average_spacing: f32) -> Vec<Body> {
         let x = radius * angle.cos();
         let y = radius * angle.sin();
 
-        let body = Body::new_sp(Vector2::new(x, y), 5.0, Vector2::new(0.0, 0.0), 100.0);
+        let body = Body::new_sp(Vector2::new(x, y), 5.0, Vector2::new(0.0, 0.0), SPIRAL_DENSITY);
         bodies.push(body);
 
         angle += 20.0; // You can adjust this value for tighter or looser spirals
@@ -69,4 +1419,4 @@ fn create_spiral_cluster(num_bodies: usize, average_spacing: f32) -> Vec<Body> {
     }
 
     bodies
-}
\ No newline at end of file
+}
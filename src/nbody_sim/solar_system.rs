@@ -0,0 +1,131 @@
+use cgmath::Vector2;
+use crate::nbody_sim::{Body, OrbitalElements, Simulation, Timestep};
+
+/// Gaussian gravitational constant squared, in AU^3 / (solar mass * day^2).
+pub const SOLAR_SYSTEM_G: f32 = 2.959_122e-4;
+
+/// Speed of light in AU / day, for `PostNewtonian` in the solar-system units.
+pub const SOLAR_SYSTEM_SPEED_OF_LIGHT: f32 = 173.144_63;
+
+/// One row of the embedded J2000 table. Elements are Standish's mean elements
+/// at J2000 relative to the ecliptic, angles in degrees, `semi_major_axis` in
+/// AU and `mass` in solar masses. `radius` only sizes the rendered circle.
+#[derive(Copy, Clone, Debug)]
+pub struct EphemerisEntry {
+    pub name: &'static str,
+    pub mass: f32,
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub mean_longitude: f32,
+    pub longitude_of_periapsis: f32,
+    pub radius: f32,
+}
+
+pub const SUN_MASS: f32 = 1.0;
+const SUN_RADIUS: f32 = 0.2;
+
+pub const SOLAR_SYSTEM_PLANETS: [EphemerisEntry; 8] = [
+    EphemerisEntry { name: "Mercury", mass: 1.660_1e-7, semi_major_axis: 0.387_099_27, eccentricity: 0.205_635_93, mean_longitude: 252.250_32, longitude_of_periapsis: 77.457_8, radius: 0.03 },
+    EphemerisEntry { name: "Venus", mass: 2.447_8e-6, semi_major_axis: 0.723_335_7, eccentricity: 0.006_776_72, mean_longitude: 181.979_1, longitude_of_periapsis: 131.602_47, radius: 0.05 },
+    EphemerisEntry { name: "Earth", mass: 3.040_4e-6, semi_major_axis: 1.000_002_6, eccentricity: 0.016_711_23, mean_longitude: 100.464_57, longitude_of_periapsis: 102.937_68, radius: 0.05 },
+    EphemerisEntry { name: "Mars", mass: 3.227_2e-7, semi_major_axis: 1.523_710_3, eccentricity: 0.093_394_1, mean_longitude: -4.553_432, longitude_of_periapsis: -23.943_63, radius: 0.04 },
+    EphemerisEntry { name: "Jupiter", mass: 9.547_9e-4, semi_major_axis: 5.202_887, eccentricity: 0.048_386_24, mean_longitude: 34.396_44, longitude_of_periapsis: 14.728_48, radius: 0.12 },
+    EphemerisEntry { name: "Saturn", mass: 2.858_9e-4, semi_major_axis: 9.536_676, eccentricity: 0.053_861_79, mean_longitude: 49.954_24, longitude_of_periapsis: 92.598_88, radius: 0.1 },
+    EphemerisEntry { name: "Uranus", mass: 4.366_2e-5, semi_major_axis: 19.189_165, eccentricity: 0.047_257_44, mean_longitude: 313.238_1, longitude_of_periapsis: 170.954_28, radius: 0.08 },
+    EphemerisEntry { name: "Neptune", mass: 5.151_4e-5, semi_major_axis: 30.069_923, eccentricity: 0.008_590_48, mean_longitude: -55.120_03, longitude_of_periapsis: 44.964_76, radius: 0.08 },
+];
+
+/// The Moon, relative to Earth (mean elements from Meeus at J2000). When the
+/// Moon is included, Earth's row is split into Earth and Moon around their
+/// common barycentre.
+pub const MOON: EphemerisEntry = EphemerisEntry { name: "Moon", mass: 3.694_3e-8, semi_major_axis: 0.002_569_55, eccentricity: 0.054_9, mean_longitude: 218.316_45, longitude_of_periapsis: 83.353_25, radius: 0.02 };
+
+/// Step that resolves Mercury's orbit, or the Moon's when it is included.
+pub const SOLAR_SYSTEM_DT: f32 = 0.1;
+pub const SOLAR_SYSTEM_DT_WITH_MOON: f32 = 0.02;
+
+/// The Sun and the eight planets at J2000 in the barycentric frame, in AU, days
+/// and solar masses. The simulation is planar, so inclinations are dropped and
+/// every orbit keeps its semi-major axis, eccentricity and phase in the
+/// ecliptic. Bodies come in table order after the Sun, with the Moon last when
+/// `include_moon` is set; see `solar_system_index`.
+pub fn create_solar_system(include_moon: bool) -> Vec<Body> {
+    let mut bodies = vec![Body::new(Vector2::new(0.0, 0.0), SUN_MASS, SUN_MASS / SUN_RADIUS)];
+
+    for planet in SOLAR_SYSTEM_PLANETS.iter() {
+        let (position, speed) = entry_state_vectors(planet, SUN_MASS);
+        bodies.push(Body::new_sp(position, planet.mass, speed, planet.mass / planet.radius));
+    }
+
+    if include_moon {
+        let earth = solar_system_index("Earth", include_moon).unwrap();
+        let barycentre = bodies[earth];
+        let earth_mass = barycentre.mass - MOON.mass;
+        let (position, speed) = entry_state_vectors(&MOON, earth_mass);
+
+        let moon_share = MOON.mass / barycentre.mass;
+        bodies[earth] = Body::new_sp(
+            barycentre.position - position * moon_share,
+            earth_mass,
+            barycentre.speed - speed * moon_share,
+            barycentre.density,
+        );
+        bodies.push(Body::new_sp(
+            barycentre.position + position * (1.0 - moon_share),
+            MOON.mass,
+            barycentre.speed + speed * (1.0 - moon_share),
+            MOON.mass / MOON.radius,
+        ));
+    }
+
+    move_to_barycentre(&mut bodies);
+    bodies
+}
+
+/// Index of a body by name in `create_solar_system(include_moon)`'s output,
+/// `None` for the Moon when it was left out.
+pub fn solar_system_index(name: &str, include_moon: bool) -> Option<usize> {
+    if name == "Sun" {
+        return Some(0);
+    }
+    if name == MOON.name {
+        return include_moon.then_some(SOLAR_SYSTEM_PLANETS.len() + 1);
+    }
+    SOLAR_SYSTEM_PLANETS.iter()
+        .position(|planet| planet.name == name)
+        .map(|i| i + 1)
+}
+
+fn entry_state_vectors(entry: &EphemerisEntry, primary_mass: f32) -> (Vector2<f32>, Vector2<f32>) {
+    let elements = OrbitalElements {
+        semi_major_axis: entry.semi_major_axis,
+        eccentricity: entry.eccentricity,
+        inclination: 0.0,
+        longitude_of_ascending_node: 0.0,
+        argument_of_periapsis: entry.longitude_of_periapsis.to_radians(),
+        mean_anomaly: (entry.mean_longitude - entry.longitude_of_periapsis).to_radians(),
+    };
+    elements.to_state_vectors(SOLAR_SYSTEM_G * (primary_mass + entry.mass))
+}
+
+fn move_to_barycentre(bodies: &mut [Body]) {
+    let total_mass = bodies.iter().map(|body| body.mass).sum::<f32>();
+    let position = bodies.iter().map(|body| body.position * body.mass).sum::<Vector2<f32>>() / total_mass;
+    let speed = bodies.iter().map(|body| body.speed * body.mass).sum::<Vector2<f32>>() / total_mass;
+
+    for body in bodies.iter_mut() {
+        body.position -= position;
+        body.speed -= speed;
+    }
+}
+
+impl Simulation {
+    /// Loads `create_solar_system` with matching units and step size.
+    pub fn new_solar_system(include_moon: bool) -> Self {
+        let mut simulation = Simulation::from_bodies(create_solar_system(include_moon));
+        simulation.set_gravitational_constant(SOLAR_SYSTEM_G);
+        simulation.set_timestep(Timestep::Fixed(if include_moon { SOLAR_SYSTEM_DT_WITH_MOON } else { SOLAR_SYSTEM_DT }))
+            .expect("a new simulation runs with any fixed step");
+        simulation
+    }
+}
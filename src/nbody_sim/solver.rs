@@ -0,0 +1,19 @@
+use crate::nbody_sim::{FastMultipole, ParticleMesh};
+
+/// How `Simulation::update` sums the bodies' pull on each other.
+#[derive(Copy, Clone, Debug)]
+pub enum ForceSolver {
+    /// Every pair through the simulation's `PairForce`, O(N²).
+    Direct,
+    /// Newtonian gravity on a mesh covering the periodic box, O(N + M² log M).
+    /// Needs `Simulation::set_periodic_box` and has no jerk, so it cannot run
+    /// under `Integrator::Hermite`. Ignores the pair force and softening; the
+    /// potential energy comes from the mesh as well.
+    ParticleMesh(ParticleMesh),
+    /// Two-dimensional `LogarithmicGravity` through a fast multipole expansion,
+    /// O(N p²). Needs `LogarithmicGravity` as the pair force, which sets the
+    /// potential energy, and has no jerk, so it cannot run under
+    /// `Integrator::Hermite`. Uses the softening for near pairs; ignores the
+    /// periodic box.
+    FastMultipole(FastMultipole),
+}
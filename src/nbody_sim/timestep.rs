@@ -0,0 +1,130 @@
+use cgmath::{InnerSpace, Vector2};
+use crate::nbody_sim::{Body, PeriodicBox};
+
+/// How `Simulation` picks the step size for each update.
+#[derive(Copy, Clone, Debug)]
+pub enum Timestep {
+    Fixed(f32),
+    Adaptive(AdaptiveTimestep),
+    /// Hierarchical power-of-two steps: every body runs on its own rung between
+    /// `max_dt` and `max_dt / 2^k >= min_dt`, and one update advances by `max_dt`.
+    Block(AdaptiveTimestep),
+}
+
+/// The quantity an adaptive step is derived from. Every criterion is scaled by
+/// `AdaptiveTimestep::eta`, so smaller values mean smaller steps.
+#[derive(Copy, Clone, Debug)]
+pub enum TimestepCriterion {
+    /// dt = eta * sqrt(length_scale / |a|)
+    MaxAcceleration { length_scale: f32 },
+    /// dt = eta * min over partners of sqrt(r^3 / (G * (m_i + m_j)))
+    FreeFall,
+    /// dt = eta * |a| / |da/dt|. After its first step `Integrator::Hermite` has
+    /// the higher derivatives and uses the fourth-order form
+    /// eta * sqrt((|a||a2| + |a1|^2) / (|a1||a3| + |a2|^2)), which is the same
+    /// step when the derivatives grow like powers of |a1| / |a|.
+    Aarseth,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct AdaptiveTimestep {
+    pub criterion: TimestepCriterion,
+    pub eta: f32,
+    pub min_dt: f32,
+    pub max_dt: f32,
+}
+
+impl AdaptiveTimestep {
+    pub fn new(criterion: TimestepCriterion, min_dt: f32, max_dt: f32) -> Self {
+        AdaptiveTimestep {
+            criterion,
+            eta: 0.02,
+            min_dt,
+            max_dt,
+        }
+    }
+
+    /// Smallest step wanted by any body. Expects the accelerations of `bodies`
+    /// to already be computed for the current positions; `g` is the
+    /// gravitational constant they were computed with. Separations are to
+    /// the nearest image in `periodic_box`.
+    pub fn compute_dt(&self, bodies: &[Body], g: f32, periodic_box: Option<&PeriodicBox>) -> f32 {
+        (0..bodies.len())
+            .map(|i| self.compute_body_dt(bodies, i, g, periodic_box))
+            .fold(self.max_dt, f32::min)
+    }
+
+    /// Step wanted by `bodies[i]` alone, clamped to the bounds.
+    pub fn compute_body_dt(&self, bodies: &[Body], i: usize, g: f32, periodic_box: Option<&PeriodicBox>) -> f32 {
+        let body = &bodies[i];
+        let dt = match self.criterion {
+            TimestepCriterion::MaxAcceleration { length_scale } => {
+                (length_scale / body.acceleration.magnitude()).sqrt()
+            }
+            TimestepCriterion::FreeFall => free_fall_time(bodies, i, g, periodic_box),
+            TimestepCriterion::Aarseth => {
+                body.acceleration.magnitude() / body.jerk.magnitude()
+            }
+        };
+
+        // Infinite or NaN (lone body, no acceleration) falls back to the upper bound.
+        let dt = self.eta * dt;
+        if dt.is_nan() {
+            return self.max_dt;
+        }
+        dt.clamp(self.min_dt, self.max_dt)
+    }
+
+    /// Aarseth's fourth-order step from the acceleration `a` and its derivatives,
+    /// clamped to the bounds.
+    pub fn aarseth_dt(&self, a: Vector2<f32>, jerk: Vector2<f32>, snap: Vector2<f32>, crackle: Vector2<f32>) -> f32 {
+        let numerator = a.magnitude() * snap.magnitude() + jerk.magnitude2();
+        let denominator = jerk.magnitude() * crackle.magnitude() + snap.magnitude2();
+
+        let dt = self.eta * (numerator / denominator).sqrt();
+        if dt.is_nan() {
+            return self.max_dt;
+        }
+        dt.clamp(self.min_dt, self.max_dt)
+    }
+
+    /// Deepest rung allowed by `min_dt`.
+    pub fn max_rung(&self) -> u32 {
+        let levels = (self.max_dt / self.min_dt).log2().floor();
+        if levels.is_finite() && levels > 0.0 {
+            (levels as u32).min(30)
+        } else {
+            0
+        }
+    }
+
+    pub fn rung_dt(&self, rung: u32) -> f32 {
+        self.max_dt / (1u32 << rung) as f32
+    }
+
+    /// Shallowest rung whose step does not exceed `dt`.
+    pub fn rung_for_dt(&self, dt: f32) -> u32 {
+        let rung = (self.max_dt / dt).log2().ceil();
+        if rung.is_finite() && rung > 0.0 {
+            (rung as u32).min(self.max_rung())
+        } else {
+            0
+        }
+    }
+}
+
+fn free_fall_time(bodies: &[Body], i: usize, g: f32, periodic_box: Option<&PeriodicBox>) -> f32 {
+    let mut min_t2 = f32::INFINITY;
+
+    for (j, other) in bodies.iter().enumerate() {
+        if j == i {
+            continue;
+        }
+        let separation = other.position - bodies[i].position;
+        let r2 = periodic_box.map_or(separation, |periodic_box| periodic_box.minimum_image(separation)).magnitude2();
+        let t2 = r2 * r2.sqrt() / (g * (bodies[i].mass + other.mass));
+        min_t2 = min_t2.min(t2);
+    }
+
+    min_t2.sqrt()
+}
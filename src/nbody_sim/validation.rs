@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use cgmath::{InnerSpace, Vector2};
+use crate::nbody_sim::{Body, ConfigurationError, Simulation, Timestep};
+
+/// How far the bodies of a validation run ended up from where they should be.
+#[derive(Clone, Debug)]
+pub struct ValidationReport {
+    /// Position error of every body still in the simulation, in the order of
+    /// the expected bodies.
+    pub errors: Vec<f32>,
+    /// Ids of the expected bodies no longer in the simulation, removed by
+    /// mergers, escapes or collisions during the run.
+    pub removed: Vec<u64>,
+    pub max_error: f32,
+    pub rms_error: f32,
+    /// Relative difference between the total energies of the two states.
+    pub energy_error: f32,
+}
+
+/// Integrates `simulation` forward `steps` updates, reverses the velocities,
+/// integrates back the same number of updates and compares the positions with
+/// the starting ones, then reverses again so the bodies head the way they did.
+/// Leapfrog on fixed steps is time-symmetric and should return to round-off;
+/// adaptive steps and dissipative forces are not, and the report measures how
+/// much. Fails, before any step, under a cosmology.
+pub fn time_reversal_error(simulation: &mut Simulation, steps: usize) -> Result<ValidationReport, ConfigurationError> {
+    if simulation.cosmology().is_some() {
+        return Err(ConfigurationError::ReversalWithCosmology);
+    }
+    let start = simulation.bodies().to_vec();
+    let start_energy = simulation.total_energy();
+
+    for _ in 0..steps {
+        simulation.update();
+    }
+    simulation.reverse_velocities()?;
+    for _ in 0..steps {
+        simulation.update();
+    }
+
+    let report = report(simulation, &start, start_energy);
+    simulation.reverse_velocities()?;
+    Ok(report)
+}
+
+/// Integrates `simulation` and `reference`, which should start from the same
+/// bodies with the reference on a finer step or a higher-order integrator,
+/// both to `time`, and measures `simulation` against `reference`. Fixed steps
+/// are shortened to land on `time`; adaptive ones may overshoot it.
+pub fn reference_error(simulation: &mut Simulation, reference: &mut Simulation, time: f64) -> ValidationReport {
+    advance_to(simulation, time);
+    advance_to(reference, time);
+    report(simulation, reference.bodies(), reference.total_energy())
+}
+
+fn advance_to(simulation: &mut Simulation, time: f64) {
+    let timestep = simulation.timestep();
+    while time - simulation.time() > 1e-9 * time.abs().max(1.0) {
+        if let Timestep::Fixed(dt) = timestep {
+            let remaining = (time - simulation.time()) as f32;
+            simulation.set_timestep(Timestep::Fixed(dt.min(remaining))).expect("fixed steps run with anything");
+        }
+        simulation.update();
+    }
+    simulation.set_timestep(timestep).expect("the simulation already ran with its own step");
+}
+
+/// Matches the bodies by id, so removals and reordering during the run do not
+/// pair up the wrong ones.
+fn report(simulation: &Simulation, expected: &[Body], expected_energy: f32) -> ValidationReport {
+    let bodies = simulation.bodies().iter().map(|body| (body.id, body)).collect::<HashMap<_, _>>();
+    let separation = |offset: Vector2<f32>| match simulation.periodic_box() {
+        Some(periodic_box) => periodic_box.minimum_image(offset),
+        None => offset,
+    };
+    let mut errors = Vec::with_capacity(expected.len());
+    let mut removed = Vec::new();
+    for expected in expected {
+        match bodies.get(&expected.id) {
+            Some(body) => errors.push(separation(body.position - expected.position).magnitude()),
+            None => removed.push(expected.id),
+        }
+    }
+
+    let max_error = errors.iter().copied().fold(0.0, f32::max);
+    let rms_error = (errors.iter().map(|error| error * error).sum::<f32>() / errors.len().max(1) as f32).sqrt();
+    let energy_error = if expected_energy != 0.0 {
+        ((simulation.total_energy() - expected_energy) / expected_energy).abs()
+    } else {
+        simulation.total_energy().abs()
+    };
+
+    ValidationReport { errors, removed, max_error, rms_error, energy_error }
+}
@@ -55,7 +55,7 @@ fn vs_main(
 
   let local_position = VERTICES[vertex_index];
 
-  out.clip_position = vec4<f32>((local_position  + instance.world_pos) * instance.radius, 1.0) * camera.view_proj;
+  out.clip_position = vec4<f32>(local_position * instance.radius + instance.world_pos, 1.0) * camera.view_proj;
   out.local_position = vec2<f32>(local_position.x, local_position.y);
   out.color = unpack_u32_to_vec4(instance.color);
 
@@ -69,4 +69,29 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         discard;
     }
     return in.color;
-}
\ No newline at end of file
+}
+
+struct OutlineVertex {
+  @location(0) position: vec3<f32>,
+  @location(1) color: vec3<f32>,
+}
+
+struct OutlineOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_outline(vertex: OutlineVertex) -> OutlineOutput {
+  var out: OutlineOutput;
+
+  out.clip_position = vec4<f32>(vertex.position, 1.0) * camera.view_proj;
+  out.color = vec4<f32>(vertex.color, 1.0);
+
+  return out;
+}
+
+@fragment
+fn fs_outline(in: OutlineOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,123 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{Body, BinaryEventKind, BinaryFinder, GasDrag, GasVelocity, OrbitalElements, Simulation, Timestep};
+
+/// Masses 2 and 1 on a binary with `elements`, centred on `centre` and at rest.
+fn binary(elements: &OrbitalElements, centre: Vector2<f32>) -> Vec<Body> {
+    let (position, speed) = elements.to_state_vectors(3.0);
+    vec![
+        Body::new_sp(centre - position / 3.0, 2.0, -speed / 3.0, 100.0),
+        Body::new_sp(centre + position * (2.0 / 3.0), 1.0, speed * (2.0 / 3.0), 100.0),
+    ]
+}
+
+fn elements(semi_major_axis: f32, eccentricity: f32) -> OrbitalElements {
+    OrbitalElements {
+        semi_major_axis,
+        eccentricity,
+        ..OrbitalElements::circular(semi_major_axis, 1.0)
+    }
+}
+
+#[test]
+fn finds_bound_pairs_with_their_orbits() {
+    let mut bodies = binary(&elements(0.1, 0.5), Vector2::new(0.0, 0.0));
+    // A single far away and a fast pair that is close but unbound.
+    bodies.push(Body::new(Vector2::new(50.0, 0.0), 1.0, 100.0));
+    bodies.push(Body::new_sp(Vector2::new(-50.0, 0.0), 1.0, Vector2::new(0.0, 10.0), 100.0));
+    bodies.push(Body::new_sp(Vector2::new(-50.1, 0.0), 1.0, Vector2::new(0.0, -10.0), 100.0));
+
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(1.0);
+    let multiples = simulation.find_multiples(&BinaryFinder::new());
+
+    assert_eq!(multiples.binaries.len(), 1);
+    let binary = multiples.binaries[0];
+    assert_eq!((binary.primary, binary.secondary), (0, 1));
+    assert!((binary.elements.semi_major_axis - 0.1).abs() < 1e-5);
+    assert!((binary.elements.eccentricity - 0.5).abs() < 1e-5);
+    // G m1 m2 / 2a
+    assert!((binary.binding_energy - 10.0).abs() < 1e-3, "binding energy {}", binary.binding_energy);
+    assert!(multiples.triples.is_empty());
+
+    let close = BinaryFinder { max_separation: 0.05, ..BinaryFinder::new() };
+    assert!(simulation.find_multiples(&close).binaries.is_empty());
+}
+
+#[test]
+fn binding_energy_follows_the_softened_potential() {
+    // Bound as point masses, but softening at ten times the separation leaves
+    // the pair about a tenth of the pull.
+    let bodies = binary(&elements(0.1, 0.5), Vector2::new(0.0, 0.0));
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(1.0);
+    assert_eq!(simulation.find_multiples(&BinaryFinder::new()).binaries.len(), 1);
+
+    simulation.set_softening(1.0);
+    assert!(simulation.find_multiples(&BinaryFinder::new()).binaries.is_empty());
+}
+
+#[test]
+fn finds_hierarchical_triples_only() {
+    let inner = elements(0.01, 0.3);
+    let triple = |outer_radius: f32| {
+        let mut bodies = binary(&inner, Vector2::new(0.0, 0.0));
+        let (position, speed) = OrbitalElements::circular(outer_radius, 0.0).to_state_vectors(3.1);
+        bodies.push(Body::new_sp(position, 0.1, speed, 100.0));
+        let mut simulation = Simulation::from_bodies(bodies);
+        simulation.set_gravitational_constant(1.0);
+        simulation.find_multiples(&BinaryFinder::new())
+    };
+
+    let multiples = triple(1.0);
+    assert_eq!(multiples.triples.len(), 1);
+    assert_eq!(multiples.triples[0].outer, 2);
+    assert!((multiples.triples[0].elements.semi_major_axis - 1.0).abs() < 1e-3);
+    assert!(multiples.triples[0].elements.eccentricity < 1e-3);
+
+    // Outer pericentre inside three inner apocentres.
+    assert!(triple(0.035).triples.is_empty());
+}
+
+#[test]
+fn tracks_formation_and_disruption() {
+    // Two bodies on a hyperbolic encounter inside a gas that brakes them into a binary.
+    let mut bodies = vec![
+        Body::new_sp(Vector2::new(-1.0, 0.25), 1.0, Vector2::new(1.0, 0.0), 1.0),
+        Body::new_sp(Vector2::new(1.0, -0.25), 1.0, Vector2::new(-1.0, 0.0), 1.0),
+    ];
+    for body in bodies.iter_mut() {
+        body.size = 1.0;
+    }
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_timestep(Timestep::Fixed(1e-3)).unwrap();
+    simulation.add_body_force(Box::new(GasDrag {
+        gas_density: 0.2,
+        thermal_speed: 1.0,
+        mean_free_path: 1.0,
+        velocity: GasVelocity::Uniform(Vector2::new(0.0, 0.0)),
+    }))
+    .unwrap();
+    simulation.track_binaries(BinaryFinder::new());
+    assert!(simulation.tracked_binaries().is_empty());
+
+    while simulation.time() < 2.0 {
+        simulation.update();
+    }
+    let events = simulation.binary_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, BinaryEventKind::Formed);
+    assert!(events[0].time > 0.0 && events[0].time < 2.0);
+    assert_eq!(simulation.tracked_binaries().len(), 1);
+
+    simulation.clear_body_forces();
+    simulation.bodies_mut()[1].speed += Vector2::new(0.0, 100.0);
+    simulation.update();
+
+    let events = simulation.binary_events();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].kind, BinaryEventKind::Disrupted);
+    assert!(events[1].binary.binding_energy > 0.0);
+    assert!(simulation.tracked_binaries().is_empty());
+    assert!((simulation.bodies()[1].speed - simulation.bodies()[0].speed).magnitude() > 50.0);
+}
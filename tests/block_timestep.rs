@@ -0,0 +1,100 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{AdaptiveTimestep, Body, G, Simulation, Timestep, TimestepCriterion};
+
+const MAX_DT: f32 = 0.004;
+const MIN_DT: f32 = MAX_DT / 64.0;
+
+/// A loose ring of bodies with a tight equal-mass binary in the middle.
+fn cluster_with_hard_binary() -> Vec<Body> {
+    let mut bodies = Vec::new();
+
+    let separation = 0.1;
+    let mut primary = Body::new(Vector2::new(-separation / 2.0, 0.0), 5.0, 100.0);
+    let mut secondary = Body::new(Vector2::new(separation / 2.0, 0.0), 5.0, 100.0);
+    let (acceleration, _) = primary.compute_acceleration_to_other_body(&secondary, G, 0.0);
+    let pull = acceleration.magnitude() * secondary.mass;
+    let speed = (pull * separation / 2.0).sqrt();
+    primary.speed = Vector2::new(0.0, -speed);
+    secondary.speed = Vector2::new(0.0, speed);
+    bodies.push(primary);
+    bodies.push(secondary);
+
+    for i in 0..24 {
+        let angle = i as f32 * std::f32::consts::TAU / 24.0;
+        let radius = 6.0 + (i % 3) as f32 * 2.5;
+        bodies.push(Body::new(Vector2::new(radius * angle.cos(), radius * angle.sin()), 5.0, 100.0));
+    }
+
+    bodies
+}
+
+fn block_timestep(min_dt: f32, max_dt: f32) -> Timestep {
+    Timestep::Block(AdaptiveTimestep {
+        eta: 0.04,
+        ..AdaptiveTimestep::new(TimestepCriterion::Aarseth, min_dt, max_dt)
+    })
+}
+
+fn max_position_error(a: &[Body], b: &[Body]) -> f32 {
+    a.iter().zip(b)
+        .map(|(a, b)| (a.position - b.position).magnitude())
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn binary_runs_on_deeper_rung_than_cluster() {
+    let mut simulation = Simulation::from_bodies(cluster_with_hard_binary());
+    simulation.set_timestep(block_timestep(MIN_DT, MAX_DT)).unwrap();
+    simulation.update();
+
+    let bodies = simulation.bodies();
+    let outer_deepest = bodies[2..].iter().map(|b| b.rung).max().unwrap();
+    assert!(bodies[0].rung > outer_deepest);
+    assert!(bodies[1].rung > outer_deepest);
+    assert!((simulation.time() - MAX_DT as f64).abs() < 1e-9);
+}
+
+#[test]
+fn block_steps_match_finest_global_step() {
+    let mut block = Simulation::from_bodies(cluster_with_hard_binary());
+    block.set_timestep(block_timestep(MIN_DT, MAX_DT)).unwrap();
+
+    let mut reference = Simulation::from_bodies(cluster_with_hard_binary());
+    reference.set_timestep(block_timestep(MIN_DT, MIN_DT)).unwrap();
+
+    for _ in 0..10 {
+        block.update();
+    }
+    for _ in 0..10 * 64 {
+        reference.update();
+    }
+
+    assert!((block.time() - reference.time()).abs() < 1e-6);
+    assert!(max_position_error(block.bodies(), reference.bodies()) < 1e-3);
+
+    // The binary sits on the finest rung, so it has turned through the same
+    // phase in both runs.
+    assert_eq!(block.bodies()[0].rung, 6);
+    let separation = |bodies: &[Body]| bodies[1].position - bodies[0].position;
+    let turned = separation(block.bodies()).angle(separation(reference.bodies()));
+    assert!(turned.0.abs() < 1e-3, "{:?}", turned);
+}
+
+#[test]
+fn block_steps_are_time_reversible() {
+    let initial = cluster_with_hard_binary();
+    let mut simulation = Simulation::from_bodies(initial.clone());
+    simulation.set_timestep(block_timestep(MIN_DT, MAX_DT)).unwrap();
+
+    for _ in 0..10 {
+        simulation.update();
+    }
+    for body in simulation.bodies_mut() {
+        body.speed = -body.speed;
+    }
+    for _ in 0..10 {
+        simulation.update();
+    }
+
+    assert!(max_position_error(simulation.bodies(), &initial) < 1e-5);
+}
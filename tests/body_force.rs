@@ -0,0 +1,118 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{
+    Body, ConfigurationError, DynamicalFriction, GasDrag, GasVelocity, Integrator, LogarithmicHaloPotential, PointMassPotential, Simulation, Timestep,
+};
+
+fn dust_grain(size: f32) -> Body {
+    Body {
+        size,
+        material_density: 3.0,
+        ..Body::new_sp(Vector2::new(0.0, 0.0), 1e-6, Vector2::new(2.0, 0.0), 1.0)
+    }
+}
+
+#[test]
+fn drag_relaxes_onto_the_gas_with_the_stopping_time() {
+    let drag = GasDrag {
+        gas_density: 1e-3,
+        thermal_speed: 10.0,
+        mean_free_path: 1.0,
+        velocity: GasVelocity::Uniform(Vector2::new(0.0, 1.0)),
+    };
+
+    // Epstein for small grains, Stokes for large ones, matching at 9λ/4.
+    let small = drag.stopping_time(&dust_grain(0.1)).unwrap();
+    assert!((small - 3.0 * 0.1 / (1e-3 * 10.0)).abs() < 1e-3 * small);
+    assert_eq!(drag.stopping_time(&Body { density: 0.5, ..dust_grain(0.1) }), Some(small));
+    let (below, above) = (drag.stopping_time(&dust_grain(2.2499)).unwrap(), drag.stopping_time(&dust_grain(2.2501)).unwrap());
+    assert!((below - above).abs() < 1e-3 * below);
+    assert!(drag.stopping_time(&dust_grain(0.0)).is_none());
+
+    let mut simulation = Simulation::from_bodies(vec![dust_grain(0.1)]);
+    simulation.set_self_gravity(false);
+    simulation.add_body_force(Box::new(drag)).unwrap();
+    simulation.set_timestep(Timestep::Fixed(0.01)).unwrap();
+    for _ in 0..3000 {
+        simulation.update();
+    }
+
+    // v = v_gas + (v0 - v_gas) e^(-t / t_s), with t = t_s.
+    let expected = Vector2::new(0.0, 1.0) + Vector2::new(2.0, -1.0) * (-1.0_f32).exp();
+    assert!((simulation.bodies()[0].speed - expected).magnitude() < 1e-2 * expected.magnitude());
+}
+
+#[test]
+fn headwind_makes_solids_drift_inwards() {
+    let drag = GasDrag {
+        gas_density: 1e-3,
+        thermal_speed: 10.0,
+        mean_free_path: 1.0,
+        velocity: GasVelocity::Keplerian { center: Vector2::new(0.0, 0.0), central_mass: 1.0, headwind: 0.01 },
+    };
+    let star = PointMassPotential { center: Vector2::new(0.0, 0.0), mass: 1.0 };
+
+    let circular = |radius: f32, size: f32| Body {
+        size,
+        material_density: 3.0,
+        ..Body::new_sp(Vector2::new(radius, 0.0), 1e-9, Vector2::new(0.0, (1.0 / radius).sqrt()), 1.0)
+    };
+    let mut simulation = Simulation::from_bodies(vec![circular(1.0, 0.5), circular(1.0, 0.0)]);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_self_gravity(false);
+    simulation.add_external_potential(Box::new(star));
+    simulation.add_body_force(Box::new(drag)).unwrap();
+    simulation.set_timestep(Timestep::Fixed(0.005)).unwrap();
+    for _ in 0..20_000 {
+        simulation.update();
+    }
+
+    let radii = simulation.bodies().iter().map(|body| body.position.magnitude()).collect::<Vec<_>>();
+    assert!(radii[0] < 0.99, "{:?}", radii);
+    assert!((radii[1] - 1.0).abs() < 5e-3, "{:?}", radii);
+}
+
+#[test]
+fn satellite_sinks_at_the_chandrasekhar_rate() {
+    let (circular_speed, core_radius, coulomb_logarithm) = (1.0, 0.01, 3.0);
+    let halo = LogarithmicHaloPotential { center: Vector2::new(0.0, 0.0), circular_speed, core_radius, axis_ratio: 1.0 };
+    let friction = DynamicalFriction { center: Vector2::new(0.0, 0.0), circular_speed, core_radius, coulomb_logarithm };
+
+    let (radius, satellite_mass) = (2.0_f32, 0.05);
+    let mut simulation = Simulation::from_bodies(vec![
+        Body::new_sp(Vector2::new(radius, 0.0), satellite_mass, Vector2::new(0.0, circular_speed), 100.0),
+        Body::new_test_particle(Vector2::new(-radius, 0.0), Vector2::new(0.0, -circular_speed)),
+    ]);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_self_gravity(false);
+    simulation.add_external_potential(Box::new(halo));
+    simulation.add_body_force(Box::new(friction)).unwrap();
+    simulation.set_timestep(Timestep::Fixed(0.002)).unwrap();
+
+    let duration = 20.0;
+    while simulation.time() < duration {
+        simulation.update();
+    }
+
+    // Singular isothermal sphere: r dr/dt = -0.428 ln Λ G M / v_c.
+    let expected = (radius * radius - 2.0 * 0.428 * coulomb_logarithm * satellite_mass * duration as f32 / circular_speed).sqrt();
+    let sunk = simulation.bodies()[0].position.magnitude();
+    assert!((sunk - expected).abs() < 0.05 * (radius - expected), "{} vs {}", sunk, expected);
+    assert!((simulation.bodies()[1].position.magnitude() - radius).abs() < 1e-2);
+}
+
+#[test]
+fn hermite_is_rejected_with_body_forces() {
+    let drag = GasDrag {
+        gas_density: 1e-3,
+        thermal_speed: 10.0,
+        mean_free_path: 1.0,
+        velocity: GasVelocity::Uniform(Vector2::new(0.0, 0.0)),
+    };
+    let mut simulation = Simulation::from_bodies(vec![dust_grain(0.1)]);
+    simulation.add_body_force(Box::new(drag)).unwrap();
+    assert_eq!(simulation.set_integrator(Integrator::Hermite).unwrap_err(), ConfigurationError::HermiteWithoutJerk);
+
+    simulation.clear_body_forces();
+    simulation.set_integrator(Integrator::Hermite).unwrap();
+    assert_eq!(simulation.add_body_force(Box::new(drag)).unwrap_err(), ConfigurationError::HermiteWithoutJerk);
+}
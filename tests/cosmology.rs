@@ -0,0 +1,139 @@
+use cgmath::{InnerSpace, Vector2};
+use std::fs::File;
+use wgpu_test::{
+    read_gadget, time_reversal_error, AdaptiveTimestep, Body, ConfigurationError, Cosmology, GadgetFormat, Integrator, Simulation, Timestep, TimestepCriterion,
+};
+
+#[test]
+fn background_matches_the_analytic_lambda_cdm_age() {
+    let cosmology = Cosmology::flat(1.0, 0.3);
+    for a in [0.01_f64, 0.1, 0.5, 1.0, 2.0] {
+        // t(a) = 2 / (3 H0 sqrt(Ω_Λ)) asinh(sqrt(Ω_Λ / Ω_m) a^(3/2))
+        let expected = 2.0 / (3.0 * 0.7_f64.sqrt()) * ((0.7_f64 / 0.3).sqrt() * a.powf(1.5)).asinh();
+        let time = cosmology.time(a);
+        assert!((time - expected).abs() < 1e-6 * expected, "a = {}: {} vs {}", a, time, expected);
+        assert!((cosmology.scale_factor(time) - a).abs() < 1e-9 * a);
+    }
+}
+
+#[test]
+fn step_factors_match_einstein_de_sitter() {
+    let cosmology = Cosmology::flat(2.0, 1.0);
+    let (from, to) = (0.2_f64, 0.5_f64);
+
+    let kick = 2.0 / 2.0 * (to.sqrt() - from.sqrt());
+    let drift = 2.0 / 2.0 * (1.0 / from.sqrt() - 1.0 / to.sqrt());
+    assert!((cosmology.kick_factor(from, to) - kick).abs() < 1e-8);
+    assert!((cosmology.drift_factor(from, to) - drift).abs() < 1e-8, "{} {}", cosmology.drift_factor(from, to), drift);
+}
+
+#[test]
+fn free_bodies_drift_by_the_drift_factor_and_hit_the_output_redshifts() {
+    let cosmology = Cosmology::flat(1.0, 0.3);
+    let momentum = Vector2::new(0.5, -0.25);
+    let mut simulation = Simulation::from_bodies(vec![
+        Body::new_sp(Vector2::new(0.0, 0.0), 1.0, momentum, 100.0),
+        Body::new(Vector2::new(1.0, 1.0), 1.0, 100.0),
+    ]);
+    simulation.set_self_gravity(false);
+    simulation.set_cosmology(cosmology, 9.0).unwrap();
+    simulation.set_timestep(Timestep::Fixed(0.01)).unwrap();
+    simulation.set_output_redshifts(vec![0.0, 3.0, 1.0]);
+
+    let mut outputs = Vec::new();
+    while simulation.redshift() > 1e-6 {
+        simulation.update();
+        outputs.extend(simulation.take_outputs());
+    }
+
+    let redshifts = outputs.iter().map(|output| output.redshift).collect::<Vec<_>>();
+    assert_eq!(redshifts.len(), 3);
+    for (redshift, expected) in redshifts.iter().zip([3.0, 1.0, 0.0]) {
+        assert!((redshift - expected).abs() < 1e-5, "{:?}", redshifts);
+    }
+
+    let last = &outputs[2].bodies;
+    let expected = momentum.cast::<f64>().unwrap() * cosmology.drift_factor(0.1, 1.0);
+    assert!((last[0].position.cast::<f64>().unwrap() - expected).magnitude() < 1e-4 * expected.magnitude());
+    assert_eq!(last[0].speed, momentum);
+    assert_eq!(last[1].position, Vector2::new(1.0, 1.0));
+}
+
+#[test]
+fn block_steps_expand_with_the_direct_sum() {
+    let mut bodies = Vec::new();
+    for i in 0..16 {
+        let angle = i as f32 * std::f32::consts::TAU / 16.0;
+        bodies.push(Body::new(Vector2::new(angle.cos(), angle.sin()) * (1.0 + 0.1 * (i % 3) as f32), 1.0, 100.0));
+    }
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(0.01);
+    simulation.set_softening(0.05);
+    simulation.set_cosmology(Cosmology::flat(1.0, 0.3), 4.0).unwrap();
+    simulation.set_timestep(Timestep::Block(AdaptiveTimestep::new(TimestepCriterion::FreeFall, 1e-4, 0.005))).unwrap();
+    simulation.set_output_redshifts(vec![2.0]);
+
+    while simulation.redshift() > 2.0 {
+        simulation.update();
+    }
+
+    let outputs = simulation.take_outputs();
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs[0].redshift <= 2.0 && outputs[0].redshift > 1.9, "{}", outputs[0].redshift);
+
+    // The ring's pull is symmetric enough that it keeps no net momentum.
+    let net = simulation.bodies().iter().map(|body| body.speed * body.mass).sum::<Vector2<f32>>();
+    let total = simulation.bodies().iter().map(|body| body.speed.magnitude() * body.mass).sum::<f32>();
+    assert!(total > 0.0 && net.magnitude() < 1e-3 * total);
+}
+
+#[test]
+fn outputs_are_written_as_gadget_snapshots() {
+    let directory = std::env::temp_dir().join(format!("nbody-outputs-{}", std::process::id()));
+    let mut simulation = Simulation::from_bodies(vec![
+        Body::new_sp(Vector2::new(0.0, 0.0), 1.0, Vector2::new(0.5, 0.0), 100.0),
+        Body::new(Vector2::new(1.0, 1.0), 1.0, 100.0),
+    ]);
+    simulation.set_self_gravity(false);
+    simulation.set_cosmology(Cosmology::flat(1.0, 0.3), 9.0).unwrap();
+    simulation.set_timestep(Timestep::Fixed(0.01)).unwrap();
+    simulation.set_output_redshifts(vec![3.0, 1.0]);
+    simulation.start_output_snapshots(&directory, GadgetFormat::Format2).unwrap();
+
+    while simulation.redshift() > 0.5 {
+        simulation.update();
+    }
+    simulation.stop_output_snapshots().unwrap();
+
+    let outputs = simulation.take_outputs();
+    for (i, output) in outputs.iter().enumerate() {
+        let snapshot = read_gadget(File::open(directory.join(format!("snapshot_{:03}", i))).unwrap(), 100.0).unwrap();
+        assert!((snapshot.header.redshift - output.redshift).abs() < 1e-9);
+        assert_eq!(snapshot.bodies[0].position, output.bodies[0].position);
+    }
+    assert_eq!(outputs.len(), 2);
+    assert!(!directory.join("snapshot_002").exists());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn hermite_is_rejected_under_a_cosmology() {
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    simulation.set_integrator(Integrator::Hermite).unwrap();
+    assert_eq!(simulation.set_cosmology(Cosmology::flat(1.0, 0.3), 9.0).unwrap_err(), ConfigurationError::HermiteWithCosmology);
+    assert_eq!(simulation.redshift(), 0.0);
+
+    simulation.set_integrator(Integrator::Leapfrog).unwrap();
+    simulation.set_cosmology(Cosmology::flat(1.0, 0.3), 9.0).unwrap();
+    assert_eq!(simulation.set_integrator(Integrator::Hermite).unwrap_err(), ConfigurationError::HermiteWithCosmology);
+}
+
+#[test]
+fn velocities_are_not_reversed_under_a_cosmology() {
+    let mut simulation = Simulation::from_bodies(vec![Body::new_sp(Vector2::new(0.0, 0.0), 1.0, Vector2::new(1.0, 0.0), 100.0)]);
+    simulation.set_cosmology(Cosmology::flat(1.0, 0.3), 9.0).unwrap();
+    assert_eq!(simulation.reverse_velocities().unwrap_err(), ConfigurationError::ReversalWithCosmology);
+    assert_eq!(time_reversal_error(&mut simulation, 10).unwrap_err(), ConfigurationError::ReversalWithCosmology);
+    assert_eq!(simulation.bodies()[0].speed, Vector2::new(1.0, 0.0));
+    assert!((simulation.redshift() - 9.0).abs() < 1e-9);
+}
@@ -0,0 +1,94 @@
+use cgmath::{InnerSpace, Vector2};
+use std::f32::consts::{PI, TAU};
+use wgpu_test::{Body, ClusterAnalysis, ClusterDiagnostics, OrbitalElements, ProfileCenter, Simulation};
+
+/// Ten rings of ten unit masses at radii 0.5, 1.5, ... 9.5 around `center`,
+/// each turning at a speed of its ring number.
+fn rings(center: Vector2<f32>) -> Vec<Body> {
+    (0..100)
+        .map(|n| {
+            let (ring, angle) = ((n / 10) as f32, (n % 10) as f32 * TAU / 10.0 + 0.1);
+            let direction = Vector2::new(angle.cos(), angle.sin());
+            let speed = Vector2::new(-direction.y, direction.x) * ring;
+            Body::new_sp(center + direction * (ring + 0.5), 1.0, speed, 100.0)
+        })
+        .collect()
+}
+
+#[test]
+fn lagrangian_radii_and_profiles_of_rings() {
+    let simulation = Simulation::from_bodies(rings(Vector2::new(3.0, -2.0)));
+    let analysis = ClusterAnalysis { bins: 10, outer_radius: Some(10.0), ..ClusterAnalysis::new(ProfileCenter::CenterOfMass) };
+    let diagnostics = simulation.cluster_diagnostics(&analysis);
+
+    assert!((diagnostics.center - Vector2::new(3.0, -2.0)).magnitude() < 1e-4);
+    for (radius, expected) in diagnostics.lagrangian_radii.iter().zip([0.5, 4.5, 8.5]) {
+        assert!((radius - expected).abs() < 1e-4, "{:?}", diagnostics.lagrangian_radii);
+    }
+
+    assert_eq!(diagnostics.profile.len(), 10);
+    for (n, bin) in diagnostics.profile.iter().enumerate() {
+        let area = PI * ((n + 1) * (n + 1) - n * n) as f32;
+        assert!((bin.surface_density - 10.0 / area).abs() < 1e-4, "bin {}: {:?}", n, bin);
+        assert!((bin.velocity_dispersion - n as f32).abs() < 1e-4, "bin {}: {:?}", n, bin);
+    }
+
+    let n = 100.0_f32;
+    let expected = 0.138 * n.sqrt() * 4.5_f32.powf(1.5) / ((simulation.gravitational_constant()).sqrt() * (0.11 * n).ln());
+    assert!((diagnostics.relaxation_time / expected - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn density_center_follows_the_core() {
+    // A tight clump of 40 bodies off to the side of a sparse ring of 60.
+    let mut bodies = (0..40)
+        .map(|n| Body::new(Vector2::new(5.0 + 0.01 * (n % 7) as f32, 0.01 * (n / 7) as f32), 1.0, 100.0))
+        .collect::<Vec<_>>();
+    bodies.extend((0..60).map(|n| {
+        let angle = n as f32 * TAU / 60.0;
+        Body::new(Vector2::new(angle.cos(), angle.sin()) * 20.0, 1.0, 100.0)
+    }));
+    let simulation = Simulation::from_bodies(bodies);
+
+    let mass_center = simulation.cluster_diagnostics(&ClusterAnalysis::new(ProfileCenter::CenterOfMass)).center;
+    let density_center = simulation.cluster_diagnostics(&ClusterAnalysis::new(ProfileCenter::DensityCenter)).center;
+    assert!((mass_center - Vector2::new(2.0, 0.0)).magnitude() < 0.1);
+    assert!((density_center - Vector2::new(5.03, 0.025)).magnitude() < 0.05, "{:?}", density_center);
+}
+
+#[test]
+fn circular_binary_is_virialized() {
+    let mut simulation = Simulation::from_bodies(vec![Body::new_sp(Vector2::new(1.0, 1.0), 1.0, Vector2::new(0.5, 0.0), 100.0)]);
+    simulation.set_gravitational_constant(1.0);
+    simulation.add_body_on_orbit(0, &OrbitalElements::circular(1.0, 0.0), 1.0, 100.0);
+
+    let diagnostics = simulation.cluster_diagnostics(&ClusterAnalysis::new(ProfileCenter::CenterOfMass));
+    // Relative speed √2, so K = 1/2 in the centre-of-mass frame; W = -1.
+    assert!((diagnostics.kinetic_energy - 0.5).abs() < 1e-5);
+    assert!((diagnostics.potential_energy + 1.0).abs() < 1e-5);
+    assert!((diagnostics.virial_ratio - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn diagnostics_stream_as_csv() {
+    let mut simulation = Simulation::from_bodies(rings(Vector2::new(0.0, 0.0)));
+    let analysis = ClusterAnalysis { bins: 4, ..ClusterAnalysis::new(ProfileCenter::DensityCenter) };
+
+    let (mut series, mut profiles) = (Vec::new(), Vec::new());
+    ClusterDiagnostics::write_csv_header(&mut series).unwrap();
+    ClusterDiagnostics::write_profile_csv_header(&mut profiles).unwrap();
+    for _ in 0..3 {
+        simulation.update();
+        let diagnostics = simulation.cluster_diagnostics(&analysis);
+        diagnostics.write_csv(&mut series).unwrap();
+        diagnostics.write_profile_csv(&mut profiles).unwrap();
+    }
+
+    let series = String::from_utf8(series).unwrap();
+    let profiles = String::from_utf8(profiles).unwrap();
+    assert_eq!(series.lines().count(), 4);
+    assert_eq!(profiles.lines().count(), 1 + 3 * 4);
+    let columns = series.lines().next().unwrap().split(',').count();
+    assert!(series.lines().all(|line| line.split(',').count() == columns));
+    assert!(series.lines().nth(3).unwrap().starts_with(&simulation.time().to_string()));
+}
@@ -0,0 +1,86 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{BinaryEventKind, BinaryFinder, Body, EscapeCriterion, OrbitalElements, Simulation};
+
+/// A heavy centre with light bodies on circular orbits of radius 1 to 4, a
+/// bound body on a wide circular orbit at 30 and a fast one leaving from 30.
+fn cluster() -> Simulation {
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 100.0, 100.0)]);
+    simulation.set_gravitational_constant(1.0);
+    for n in 1..=4 {
+        simulation.add_body_on_orbit(0, &OrbitalElements::circular(n as f32, n as f32), 0.1, 100.0);
+    }
+    simulation.add_body_on_orbit(0, &OrbitalElements::circular(30.0, 0.0), 0.1, 100.0);
+    simulation.add_body(Body::new_sp(Vector2::new(0.0, -30.0), 0.2, Vector2::new(0.0, -5.0), 100.0));
+    simulation
+}
+
+#[test]
+fn unbound_bodies_far_out_are_removed() {
+    let mut simulation = cluster();
+    simulation.set_escape_criterion(Some(EscapeCriterion::Unbound { half_mass_radii: 5.0 }));
+    simulation.update();
+
+    assert_eq!(simulation.bodies().len(), 6);
+    let escapers = simulation.escapers();
+    assert_eq!(escapers.len(), 1);
+    assert_eq!(escapers[0].index, 6);
+    assert_eq!(escapers[0].body.mass, 0.2);
+    assert!(escapers[0].distance > 29.0);
+    // 25/2 - 100/30, give or take the rest of the cluster and the first step.
+    assert!((escapers[0].specific_energy - 9.17).abs() < 0.15, "{}", escapers[0].specific_energy);
+    assert_eq!(escapers[0].time, simulation.time());
+
+    // The wide orbit is bound, so it stays even though it is far out.
+    assert!(simulation.bodies().iter().any(|body| body.position.magnitude() > 29.0));
+}
+
+#[test]
+fn hard_radius_removes_bound_bodies_too() {
+    let mut simulation = cluster();
+    simulation.set_escape_criterion(Some(EscapeCriterion::Radius(10.0)));
+    for _ in 0..10 {
+        simulation.update();
+    }
+
+    assert_eq!(simulation.bodies().len(), 5);
+    let indices = simulation.escapers().iter().map(|escaper| escaper.index).collect::<Vec<_>>();
+    assert_eq!(indices, vec![5, 6]);
+
+    let loss = simulation.mass_loss();
+    assert_eq!(loss.escapers, 2);
+    assert!((loss.mass - 0.3).abs() < 1e-6);
+    assert!((loss.fraction - 0.3 / 100.7).abs() < 1e-6);
+    assert!((loss.rate - 0.3 / simulation.time() as f32).abs() < 1e-3);
+
+    simulation.set_escape_criterion(None);
+    assert!(simulation.escapers().is_empty());
+    assert_eq!(simulation.mass_loss().escapers, 0);
+}
+
+#[test]
+fn removal_shifts_tracked_indices() {
+    let mut simulation = cluster();
+    simulation.add_body_on_orbit(3, &OrbitalElements::circular(0.05, 0.0), 0.01, 100.0);
+    simulation.track_orbits(0, vec![1, 2, 4]);
+    simulation.track_binaries(BinaryFinder { max_separation: 0.1, ..BinaryFinder::new() });
+    simulation.set_body_colors(Some((0..8).collect()));
+    assert_eq!(simulation.tracked_binaries().len(), 1);
+
+    let removed = simulation.remove_bodies(&[2, 3]);
+    assert_eq!(removed.iter().map(|body| body.mass).collect::<Vec<_>>(), vec![0.1, 0.1]);
+    assert_eq!(simulation.bodies().len(), 6);
+
+    let report = simulation.orbit_report().iter().map(|&(body, _)| body).collect::<Vec<_>>();
+    assert_eq!(report, vec![1, 2]);
+    assert!((simulation.orbit_report()[1].1.semi_major_axis - 4.0).abs() < 1e-3);
+
+    assert!(simulation.tracked_binaries().is_empty());
+    assert_eq!(simulation.binary_events().len(), 1);
+    assert_eq!(simulation.binary_events()[0].kind, BinaryEventKind::Disrupted);
+
+    let colors = simulation.get_bodies_as_circles().iter().map(|circle| circle.color).collect::<Vec<_>>();
+    assert_eq!(colors, vec![0, 1, 4, 5, 6, 7]);
+
+    simulation.remove_bodies(&[0]);
+    assert!(simulation.orbit_report().is_empty());
+}
@@ -0,0 +1,160 @@
+use cgmath::{InnerSpace, Vector2};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use wgpu_test::{Body, BodyExport, ExportCadence, ExportFields, ExportFormat, OrbitalElements, Simulation, Timestep};
+
+/// A writer the test can read back while the simulation owns it.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap().lines().map(str::to_string).collect()
+    }
+}
+
+/// Fails every write once `full` is set.
+#[derive(Clone, Default)]
+struct FillingWriter {
+    full: Arc<AtomicBool>,
+}
+
+impl Write for FillingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.full.load(Ordering::Relaxed) {
+            true => Err(io::Error::other("disk full")),
+            false => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A heavy body, a light one on a circular orbit and a test particle.
+fn system() -> Simulation {
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 100.0, 100.0)]);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_timestep(Timestep::Fixed(0.01)).unwrap();
+    simulation.add_body_on_orbit(0, &OrbitalElements::circular(1.0, 0.0), 0.1, 50.0);
+    simulation.add_body(Body::new_test_particle(Vector2::new(0.0, 2.0), Vector2::new(-7.0, 0.0)));
+    simulation
+}
+
+#[test]
+fn csv_has_a_header_and_a_line_per_body_per_snapshot() {
+    let mut simulation = system();
+    let buffer = SharedBuffer::default();
+    let export = BodyExport::new(ExportFormat::Csv, ExportCadence::EveryUpdates(2));
+    simulation.start_export(export, Box::new(buffer.clone())).unwrap();
+    for _ in 0..5 {
+        simulation.update();
+    }
+    simulation.stop_export().unwrap();
+
+    let lines = buffer.lines();
+    assert_eq!(lines[0], "id,t,x,y,vx,vy,ax,ay,mass,density,test_particle");
+    // The initial snapshot and those after updates 2 and 4.
+    assert_eq!(lines.len(), 1 + 3 * 3);
+
+    let last = lines[7..].iter().map(|line| line.split(',').collect::<Vec<_>>()).collect::<Vec<_>>();
+    assert_eq!(last[1][0], "1");
+    assert!((last[1][1].parse::<f64>().unwrap() - 0.04).abs() < 1e-6);
+    assert_eq!(last[1][8], "0.1");
+    assert_eq!(last[1][9].parse::<f32>().unwrap(), 50.0);
+    assert_eq!(last[2][10], "1");
+
+    // Accelerations are fresh, not left behind by the leapfrog drift: the
+    // orbiting body is pulled by G M / r² toward where the centre is now.
+    let column = |body: usize, n: usize| last[body][n].parse::<f32>().unwrap();
+    let offset = Vector2::new(column(1, 2) - column(0, 2), column(1, 3) - column(0, 3));
+    let expected = -offset * 100.0 / offset.magnitude().powi(3);
+    let acceleration = Vector2::new(column(1, 6), column(1, 7));
+    assert!((acceleration - expected).magnitude() < 0.5, "{:?} {:?}", acceleration, expected);
+}
+
+#[test]
+fn json_lines_hold_only_the_selected_fields() {
+    let mut simulation = system();
+    let buffer = SharedBuffer::default();
+    let export = BodyExport {
+        format: ExportFormat::JsonLines,
+        fields: ExportFields { id: true, position: true, test_particle: true, ..ExportFields::none() },
+        cadence: ExportCadence::EveryTime(0.025),
+    };
+    simulation.start_export(export, Box::new(buffer.clone())).unwrap();
+    for _ in 0..6 {
+        simulation.update();
+    }
+    simulation.stop_export().unwrap();
+
+    let lines = buffer.lines();
+    // The initial snapshot and those at t = 0.03 and 0.06.
+    assert_eq!(lines.len(), 3 * 3);
+    assert!(lines[0].starts_with("{\"id\":0,\"x\":0,\"y\":0,\"test_particle\":false}"), "{}", lines[0]);
+    assert!(lines[8].starts_with("{\"id\":2,\"x\":"), "{}", lines[8]);
+    assert!(lines[8].ends_with(",\"test_particle\":true}"), "{}", lines[8]);
+}
+
+#[test]
+fn non_finite_values_are_null_in_json() {
+    let mut body = Body::new(Vector2::new(f32::NAN, 1.0), 1.0, f32::INFINITY);
+    body.speed = Vector2::new(0.0, 0.0);
+    let export = BodyExport {
+        format: ExportFormat::JsonLines,
+        fields: ExportFields { position: true, density: true, ..ExportFields::none() },
+        cadence: ExportCadence::EveryUpdates(1),
+    };
+
+    let mut buffer = Vec::new();
+    export.write_snapshot(&mut buffer, &[body], 0.0).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), "{\"x\":null,\"y\":1,\"density\":null}\n");
+}
+
+#[test]
+fn write_errors_surface_when_the_export_stops() {
+    let mut simulation = system();
+    let writer = FillingWriter::default();
+    let export = BodyExport::new(ExportFormat::Csv, ExportCadence::EveryUpdates(1));
+    simulation.start_export(export, Box::new(writer.clone())).unwrap();
+    simulation.update();
+    writer.full.store(true, Ordering::Relaxed);
+    simulation.update();
+    simulation.update();
+    assert_eq!(simulation.stop_export().unwrap_err().to_string(), "disk full");
+    assert!(simulation.start_export(export, Box::new(writer)).is_err());
+
+    // Stopped, so later updates write nothing.
+    let buffer = SharedBuffer::default();
+    simulation.start_export(export, Box::new(buffer.clone())).unwrap();
+    simulation.stop_export().unwrap();
+    simulation.update();
+    assert_eq!(buffer.lines().len(), 1 + 3);
+}
+
+#[test]
+fn ids_stay_with_their_bodies_after_removals() {
+    let mut simulation = system();
+    simulation.remove_bodies(&[0]);
+    let buffer = SharedBuffer::default();
+    let export = BodyExport {
+        format: ExportFormat::Csv,
+        fields: ExportFields { id: true, test_particle: true, ..ExportFields::none() },
+        cadence: ExportCadence::EveryUpdates(1),
+    };
+    simulation.start_export(export, Box::new(buffer.clone())).unwrap();
+    simulation.stop_export().unwrap();
+    assert_eq!(buffer.lines(), vec!["id,test_particle", "1,0", "2,1"]);
+}
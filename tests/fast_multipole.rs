@@ -0,0 +1,66 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{create_galaxy, Body, ConfigurationError, FastMultipole, ForceSolver, Gravity, GalaxyModel, Integrator, LogarithmicGravity, Simulation, Timestep};
+
+/// Accelerations from an update of zero length, which leaves the bodies
+/// where they are.
+fn accelerations(bodies: &[Body], configure: impl FnOnce(&mut Simulation)) -> Vec<Vector2<f32>> {
+    let mut simulation = Simulation::from_bodies(bodies.to_vec());
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_timestep(Timestep::Fixed(0.0)).unwrap();
+    configure(&mut simulation);
+    simulation.update();
+    simulation.bodies().iter().map(|body| body.acceleration).collect()
+}
+
+fn relative_error(reference: &[Vector2<f32>], approximation: &[Vector2<f32>]) -> f32 {
+    let error = reference.iter().zip(approximation).map(|(a, b)| (a - b).magnitude2()).sum::<f32>();
+    let norm = reference.iter().map(|a| a.magnitude2()).sum::<f32>();
+    (error / norm).sqrt()
+}
+
+#[test]
+fn multipole_error_falls_with_the_expansion_order() {
+    let bodies = create_galaxy(&GalaxyModel::default(), 1.0, 0.0);
+    let direct = accelerations(&bodies, |simulation| simulation.set_pair_force(Box::new(LogarithmicGravity)).unwrap());
+
+    let errors = [2, 4, 8].map(|order| {
+        let multipole = FastMultipole::new(order);
+        relative_error(&direct, &accelerations(&bodies, |simulation| {
+            simulation.set_pair_force(Box::new(LogarithmicGravity)).unwrap();
+            simulation.set_force_solver(ForceSolver::FastMultipole(multipole)).unwrap();
+        }))
+    });
+
+    assert!(errors[0] > errors[1] && errors[1] > errors[2], "{:?}", errors);
+    assert!(errors[2] < 1e-5, "{:?}", errors);
+}
+
+#[test]
+fn multipole_handles_few_and_coincident_bodies() {
+    let bodies = vec![
+        Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0),
+        Body::new(Vector2::new(10.0, 0.0), 1.0, 100.0),
+        Body::new(Vector2::new(10.0, 0.0), 1.0, 100.0),
+    ];
+    let multipole = FastMultipole::new(8).accelerations(&bodies, 1.0, 0.1);
+    let direct = accelerations(&bodies, |simulation| {
+        simulation.set_pair_force(Box::new(LogarithmicGravity)).unwrap();
+        simulation.set_softening(0.1);
+    });
+
+    // Only near pairs are softened, which the distant body barely notices.
+    assert!(relative_error(&direct, &multipole) < 1e-3);
+    assert!(multipole.iter().all(|a| a.x.is_finite() && a.y.is_finite()));
+}
+
+#[test]
+fn multipole_needs_logarithmic_gravity_and_no_jerk() {
+    let multipole = ForceSolver::FastMultipole(FastMultipole::new(4));
+    let mut simulation = Simulation::from_bodies(create_galaxy(&GalaxyModel::default(), 1.0, 0.0));
+    assert_eq!(simulation.set_force_solver(multipole).unwrap_err(), ConfigurationError::FastMultipoleWithoutLogarithmicGravity);
+
+    simulation.set_pair_force(Box::new(LogarithmicGravity)).unwrap();
+    simulation.set_force_solver(multipole).unwrap();
+    assert_eq!(simulation.set_pair_force(Box::new(Gravity)).unwrap_err(), ConfigurationError::FastMultipoleWithoutLogarithmicGravity);
+    assert_eq!(simulation.set_integrator(Integrator::Hermite).unwrap_err(), ConfigurationError::HermiteWithoutJerk);
+}
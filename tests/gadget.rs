@@ -0,0 +1,181 @@
+use cgmath::Vector2;
+use std::io::ErrorKind;
+use wgpu_test::{read_gadget, write_gadget, Body, Cosmology, GadgetFormat, GadgetHeader, PeriodicBox, Simulation};
+
+/// Numbered from zero, as a simulation would.
+fn bodies() -> Vec<Body> {
+    Simulation::from_bodies(vec![
+        Body::new_sp(Vector2::new(1.0, -2.0), 3.0, Vector2::new(0.5, 0.25), 10.0),
+        Body::new_test_particle(Vector2::new(-4.0, 5.0), Vector2::new(-1.0, 2.0)),
+        Body::new_sp(Vector2::new(0.0, 7.5), 1.5, Vector2::new(-0.125, 0.0), 10.0),
+    ])
+    .bodies()
+    .to_vec()
+}
+
+#[test]
+fn snapshots_read_back_in_both_formats() {
+    let header = GadgetHeader { time: 2.5, box_size: 20.0, ..GadgetHeader::default() };
+    for format in [GadgetFormat::Format1, GadgetFormat::Format2] {
+        let mut file = Vec::new();
+        write_gadget(&mut file, &header, &bodies(), format).unwrap();
+        let snapshot = read_gadget(file.as_slice(), 10.0).unwrap();
+
+        assert_eq!(snapshot.header, header);
+        assert_eq!(snapshot.bodies.len(), 3);
+        // The test particle was written after the massive bodies, as type 5,
+        // and is back in its place by ID.
+        for (read, written) in snapshot.bodies.iter().zip(bodies()) {
+            assert_eq!(read.position, written.position);
+            assert_eq!(read.speed, written.speed);
+            assert_eq!(read.mass, written.mass);
+            assert_eq!(read.test_particle, written.test_particle);
+        }
+        assert_eq!(snapshot.bodies[0].density, 10.0);
+    }
+}
+
+#[test]
+fn ids_survive_removals_and_snapshots() {
+    let mut simulation = Simulation::from_bodies(bodies());
+    simulation.remove_bodies(&[0]);
+    let mut file = Vec::new();
+    simulation.write_gadget_snapshot(&mut file, GadgetFormat::Format2).unwrap();
+
+    let mut simulation = Simulation::from_gadget(read_gadget(file.as_slice(), 10.0).unwrap(), None);
+    assert_eq!(simulation.bodies().iter().map(|body| body.id).collect::<Vec<_>>(), vec![1, 2]);
+    let added = simulation.add_body(Body::new(Vector2::new(0.0, 0.0), 1.0, 10.0));
+    assert_eq!(simulation.bodies()[added].id, 3);
+}
+
+#[test]
+fn equal_masses_go_in_the_mass_table() {
+    let bodies = vec![Body::new(Vector2::new(1.0, 2.0), 4.0, 1.0); 5];
+    let mut file = Vec::new();
+    write_gadget(&mut file, &GadgetHeader::default(), &bodies, GadgetFormat::Format1).unwrap();
+
+    // HEAD, POS, VEL and ID records, no MASS.
+    assert_eq!(file.len(), (256 + 8) + 2 * (5 * 12 + 8) + (5 * 4 + 8));
+    assert_eq!(&file[..4], &256u32.to_le_bytes());
+    assert_eq!(u32::from_le_bytes(file[8..12].try_into().unwrap()), 5);
+    assert_eq!(f64::from_le_bytes(file[36..44].try_into().unwrap()), 4.0);
+
+    let snapshot = read_gadget(file.as_slice(), 1.0).unwrap();
+    assert!(snapshot.bodies.iter().all(|body| body.mass == 4.0 && !body.test_particle));
+}
+
+/// A format-2 record with its label record, big-endian.
+fn big_endian_block(file: &mut Vec<u8>, label: &[u8; 4], data: &[u8]) {
+    file.extend(8u32.to_be_bytes());
+    file.extend(label);
+    file.extend((data.len() as u32 + 8).to_be_bytes());
+    file.extend(8u32.to_be_bytes());
+    file.extend((data.len() as u32).to_be_bytes());
+    file.extend(data);
+    file.extend((data.len() as u32).to_be_bytes());
+}
+
+#[test]
+fn reads_big_endian_double_precision_files_from_other_codes() {
+    // Two gas particles with their own masses and two halo particles sharing
+    // one, 64-bit IDs out of order, and a block this reader has no use for.
+    let mut head = Vec::new();
+    for count in [2u32, 2, 0, 0, 0, 0] {
+        head.extend(count.to_be_bytes());
+    }
+    for mass in [0.0f64, 0.5, 0.0, 0.0, 0.0, 0.0] {
+        head.extend(mass.to_be_bytes());
+    }
+    for value in [0.25f64, 3.0] {
+        head.extend(value.to_be_bytes());
+    }
+    head.resize(128, 0);
+    for value in [100.0f64, 0.3, 0.7, 0.7] {
+        head.extend(value.to_be_bytes());
+    }
+    head.resize(256, 0);
+
+    let doubles = |values: &[f64]| values.iter().flat_map(|value| value.to_be_bytes()).collect::<Vec<_>>();
+    let mut file = Vec::new();
+    big_endian_block(&mut file, b"HEAD", &head);
+    big_endian_block(&mut file, b"POS ", &doubles(&[1.0, 2.0, 9.0, 3.0, 4.0, 9.0, 5.0, 6.0, 9.0, 7.0, 8.0, 9.0]));
+    big_endian_block(&mut file, b"VEL ", &doubles(&[0.1, 0.2, 0.0, 0.3, 0.4, 0.0, 0.5, 0.6, 0.0, 0.7, 0.8, 0.0]));
+    big_endian_block(&mut file, b"ID  ", &[40u64, 10, 30, 20].iter().flat_map(|id| id.to_be_bytes()).collect::<Vec<_>>());
+    big_endian_block(&mut file, b"MASS", &doubles(&[2.0, 3.0]));
+    big_endian_block(&mut file, b"U   ", &doubles(&[1.0, 1.0]));
+
+    let snapshot = read_gadget(file.as_slice(), 5.0).unwrap();
+    assert_eq!(snapshot.header.time, 0.25);
+    assert_eq!(snapshot.header.redshift, 3.0);
+    assert_eq!(snapshot.header.box_size, 100.0);
+    assert_eq!(snapshot.header.omega_matter, 0.3);
+
+    let positions = snapshot.bodies.iter().map(|body| (body.position.x, body.position.y)).collect::<Vec<_>>();
+    // Shifted from [0, 100) to the box about the origin.
+    assert_eq!(positions, [(-47.0, -46.0), (-43.0, -42.0), (-45.0, -44.0), (-49.0, -48.0)]);
+    let masses = snapshot.bodies.iter().map(|body| body.mass).collect::<Vec<_>>();
+    assert_eq!(masses, [3.0, 0.5, 0.5, 2.0]);
+    assert_eq!(snapshot.bodies[1].speed, Vector2::new(0.7, 0.8));
+}
+
+#[test]
+fn comoving_simulations_round_trip_through_snapshots() {
+    let cosmology = Cosmology::flat(1.0, 0.3);
+    let mut simulation = Simulation::from_bodies(bodies());
+    simulation.set_periodic_box(Some(PeriodicBox::new(10.0))).unwrap();
+    simulation.set_cosmology(cosmology, 1.0).unwrap();
+
+    let mut file = Vec::new();
+    simulation.write_gadget_snapshot(&mut file, GadgetFormat::Format2).unwrap();
+    let snapshot = read_gadget(file.as_slice(), 10.0).unwrap();
+    assert!((snapshot.header.time - 0.5).abs() < 1e-9);
+    assert_eq!(snapshot.header.omega_lambda, 0.7);
+    // Gadget's a^½ dx/dt is a² dx/dt / a^3/2.
+    let expected = bodies()[0].speed * 0.5f32.powf(-1.5);
+    assert!((snapshot.bodies[0].speed.x - expected.x).abs() < 1e-5);
+
+    let loaded = Simulation::from_gadget(snapshot, Some(cosmology));
+    assert!((loaded.redshift() - 1.0).abs() < 1e-6);
+    assert_eq!(loaded.periodic_box().map(|periodic_box| periodic_box.size), Some(10.0));
+    for (read, written) in loaded.bodies().iter().zip(simulation.bodies()) {
+        assert_eq!(read.position, written.position);
+        assert!((read.speed.x - written.speed.x).abs() < 1e-6);
+        assert!((read.speed.y - written.speed.y).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn box_positions_are_written_from_zero_to_the_box_size() {
+    let mut simulation = Simulation::from_bodies(vec![
+        Body::new(Vector2::new(-5.0, 4.999_999), 1.0, 10.0),
+        Body::new(Vector2::new(-1e-9, 0.0), 1.0, 10.0),
+        Body::new(Vector2::new(3.0, -2.0), 1.0, 10.0),
+    ]);
+    simulation.set_periodic_box(Some(PeriodicBox::new(10.0))).unwrap();
+    let mut file = Vec::new();
+    simulation.write_gadget_snapshot(&mut file, GadgetFormat::Format1).unwrap();
+
+    // Format-1 POS data follows the 256-byte header and three record markers,
+    // x y z per body.
+    let positions = file[268..268 + 36].chunks_exact(4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap())).collect::<Vec<_>>();
+    for (n, &value) in positions.iter().enumerate().filter(|&(n, _)| n % 3 != 2) {
+        assert!((0.0..10.0).contains(&value), "coordinate {} is {}", n, value);
+    }
+    assert_eq!(positions[6..8], [8.0, 3.0]);
+
+    let loaded = Simulation::from_gadget(read_gadget(file.as_slice(), 10.0).unwrap(), None);
+    assert_eq!(loaded.bodies()[2].position, Vector2::new(3.0, -2.0));
+}
+
+#[test]
+fn other_files_are_rejected() {
+    let Err(error) = read_gadget(b"not a snapshot".as_slice(), 1.0) else {
+        panic!("read text as a snapshot");
+    };
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    let mut file = Vec::new();
+    write_gadget(&mut file, &GadgetHeader::default(), &bodies(), GadgetFormat::Format1).unwrap();
+    file.truncate(file.len() - 2);
+    assert!(read_gadget(file.as_slice(), 1.0).is_err());
+}
@@ -0,0 +1,107 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{Body, ConfigurationError, EncounterOrbit, GalaxyCollision, GalaxyModel, Simulation};
+
+fn small_galaxy() -> GalaxyModel {
+    GalaxyModel {
+        disk_bodies: 200,
+        bulge_bodies: 50,
+        halo_bodies: 100,
+        ..GalaxyModel::default()
+    }
+}
+
+fn centre_of_mass(bodies: &[Body]) -> (Vector2<f32>, Vector2<f32>) {
+    let mass = bodies.iter().map(|body| body.mass).sum::<f32>();
+    let position = bodies.iter().map(|body| body.position * body.mass).sum::<Vector2<f32>>() / mass;
+    let speed = bodies.iter().map(|body| body.speed * body.mass).sum::<Vector2<f32>>() / mass;
+    (position, speed)
+}
+
+#[test]
+fn galaxies_sit_on_the_requested_encounter_orbit() {
+    let setup = GalaxyCollision {
+        galaxy: small_galaxy(),
+        mass_ratio: 0.5,
+        ..GalaxyCollision::default()
+    };
+    let bodies = setup.create_bodies().unwrap();
+
+    let primary_count = 350;
+    assert_eq!(bodies.len(), primary_count + 175);
+
+    let (primary, primary_speed) = centre_of_mass(&bodies[..primary_count]);
+    let (secondary, secondary_speed) = centre_of_mass(&bodies[primary_count..]);
+    let separation = (secondary - primary).magnitude();
+    assert!((separation - setup.initial_separation).abs() < 1e-3 * setup.initial_separation);
+
+    // A parabolic orbit has zero orbital energy.
+    let mu = setup.gravitational_constant * setup.galaxy.total_mass() * 1.5;
+    let relative_speed = (secondary_speed - primary_speed).magnitude();
+    let energy = 0.5 * relative_speed * relative_speed - mu / separation;
+    assert!(energy.abs() < 1e-2 * mu / separation);
+}
+
+#[test]
+fn elliptic_encounter_is_bound() {
+    let setup = GalaxyCollision {
+        galaxy: small_galaxy(),
+        orbit: EncounterOrbit::Elliptic { eccentricity: 0.6 },
+        ..GalaxyCollision::default()
+    };
+    let simulation = Simulation::new_galaxy_collision(&setup).unwrap();
+
+    let bodies = simulation.bodies();
+    let half = bodies.len() / 2;
+    let (primary, primary_speed) = centre_of_mass(&bodies[..half]);
+    let (secondary, secondary_speed) = centre_of_mass(&bodies[half..]);
+
+    let mu = setup.gravitational_constant * setup.galaxy.total_mass() * 2.0;
+    let relative_speed = (secondary_speed - primary_speed).magnitude();
+    assert!(0.5 * relative_speed * relative_speed - mu / (secondary - primary).magnitude() < 0.0);
+}
+
+#[test]
+fn isolated_disk_stays_in_rotation() {
+    let mut simulation = Simulation::new_galaxy_collision(&GalaxyCollision {
+        galaxy: small_galaxy(),
+        initial_separation: 1000.0,
+        ..GalaxyCollision::default()
+    })
+    .unwrap();
+
+    let half_mass_radius = |simulation: &Simulation| {
+        let bodies = &simulation.bodies()[..200];
+        let (centre, _) = centre_of_mass(bodies);
+        let mut radii = bodies.iter().map(|body| (body.position - centre).magnitude()).collect::<Vec<_>>();
+        radii.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        radii[radii.len() / 2]
+    };
+
+    let initial = half_mass_radius(&simulation);
+    for _ in 0..100 {
+        simulation.update();
+    }
+    assert!((half_mass_radius(&simulation) - initial).abs() < 0.2 * initial);
+}
+
+#[test]
+fn retrograde_disks_are_mirrored_and_tilted_ones_rejected() {
+    let setup = |inclinations: [f32; 2]| GalaxyCollision { galaxy: small_galaxy(), inclinations, ..GalaxyCollision::default() };
+    let prograde = setup([0.0, 0.0]).create_bodies().unwrap();
+    let retrograde = setup([std::f32::consts::PI, 0.0]).create_bodies().unwrap();
+
+    // The primary's disk turns the other way about its own centre; the secondary is untouched.
+    let (centre, drift) = centre_of_mass(&prograde[..350]);
+    let (mirrored_centre, mirrored_drift) = centre_of_mass(&retrograde[..350]);
+    let spin = |body: &Body, centre: Vector2<f32>, drift: Vector2<f32>| {
+        let (offset, speed) = (body.position - centre, body.speed - drift);
+        offset.x * speed.y - offset.y * speed.x
+    };
+    for (a, b) in prograde[..200].iter().zip(&retrograde[..200]) {
+        assert!((spin(a, centre, drift) + spin(b, mirrored_centre, mirrored_drift)).abs() < 1e-3 * spin(a, centre, drift).abs().max(1.0));
+    }
+    assert_eq!(prograde[350..].iter().map(|body| body.position).collect::<Vec<_>>(), retrograde[350..].iter().map(|body| body.position).collect::<Vec<_>>());
+
+    assert_eq!(setup([0.5, 0.0]).create_bodies().err(), Some(ConfigurationError::IntermediateInclination));
+    assert!(Simulation::new_galaxy_collision(&setup([0.0, 1.0])).is_err());
+}
@@ -0,0 +1,114 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{Body, DensityGroups, FriendsOfFriends, GroupFinder, PeriodicBox, Simulation};
+
+/// A `side` × `side` square lattice of unit masses spaced `spacing` apart,
+/// centred on `center` and moving with `speed`.
+fn clump(center: Vector2<f32>, side: usize, spacing: f32, speed: Vector2<f32>) -> Vec<Body> {
+    let half = (side - 1) as f32 * spacing / 2.0;
+    (0..side * side)
+        .map(|n| {
+            let offset = Vector2::new((n % side) as f32 * spacing - half, (n / side) as f32 * spacing - half);
+            Body::new_sp(center + offset, 1.0, speed, 100.0)
+        })
+        .collect()
+}
+
+/// Two dense clumps joined by a sparse line of bodies, plus scattered loners.
+fn two_clumps_and_a_bridge() -> Vec<Body> {
+    let mut bodies = clump(Vector2::new(0.0, 0.0), 6, 0.1, Vector2::new(1.0, 0.0));
+    bodies.extend(clump(Vector2::new(2.0, 0.0), 4, 0.1, Vector2::new(-1.0, 0.0)));
+    bodies.extend((1..=10).map(|n| Body::new(Vector2::new(0.25 + 0.15 * n as f32, 0.0), 1.0, 100.0)));
+    bodies.extend((0..5).map(|n| Body::new(Vector2::new(-5.0 + 2.0 * n as f32, 5.0), 1.0, 100.0)));
+    bodies
+}
+
+#[test]
+fn friends_of_friends_links_through_bridges() {
+    let simulation = Simulation::from_bodies(two_clumps_and_a_bridge());
+    let groups = simulation.find_groups(&GroupFinder::FriendsOfFriends(FriendsOfFriends::new(0.16)));
+
+    assert_eq!(groups.groups.len(), 1);
+    assert_eq!(groups.groups[0].members.len(), 36 + 16 + 10);
+    assert!(groups.membership[62..].iter().all(Option::is_none));
+}
+
+#[test]
+fn density_groups_split_at_sparse_bridges() {
+    let simulation = Simulation::from_bodies(two_clumps_and_a_bridge());
+    let groups = simulation.find_groups(&GroupFinder::Density(DensityGroups::new(0.16, 5)));
+
+    // Each clump takes in the one bridge body next to it.
+    assert_eq!(groups.groups.len(), 2);
+    assert_eq!(groups.groups[0].members.len(), 37);
+    assert_eq!(groups.groups[1].members.len(), 17);
+    assert_eq!(groups.membership[52], Some(0));
+    assert_eq!(groups.membership[61], Some(1));
+    assert!(groups.membership[53..61].iter().all(Option::is_none));
+    assert!(groups.membership[62..].iter().all(Option::is_none));
+}
+
+#[test]
+fn groups_report_mass_center_dispersion_and_radius() {
+    let mut bodies = clump(Vector2::new(3.0, -1.0), 6, 0.1, Vector2::new(1.0, 0.5));
+    bodies.extend(clump(Vector2::new(0.0, 0.0), 2, 0.1, Vector2::new(0.0, 0.0)));
+    let simulation = Simulation::from_bodies(bodies);
+    let groups = simulation.find_groups(&GroupFinder::FriendsOfFriends(FriendsOfFriends::new(0.15)));
+
+    // The four-body clump is below the default minimum size.
+    assert_eq!(groups.groups.len(), 1);
+    let group = &groups.groups[0];
+    assert_eq!(group.members, (0..36).collect::<Vec<_>>());
+    assert_eq!(group.mass, 36.0);
+    assert!((group.center - Vector2::new(3.0, -1.0)).magnitude() < 1e-5);
+    assert!((group.speed - Vector2::new(1.0, 0.5)).magnitude() < 1e-5);
+    assert!(group.velocity_dispersion < 1e-5);
+    // Corner of a 0.5 × 0.5 square.
+    assert!((group.radius - 0.25 * 2.0_f32.sqrt()).abs() < 1e-5);
+}
+
+#[test]
+fn groups_stay_whole_across_the_periodic_boundary() {
+    let mut bodies = clump(Vector2::new(5.0, 0.0), 4, 0.1, Vector2::new(0.0, 0.0));
+    for (n, body) in bodies.iter_mut().enumerate() {
+        body.speed = Vector2::new(if n % 2 == 0 { 1.0 } else { -1.0 }, 0.0);
+    }
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_periodic_box(Some(PeriodicBox::new(10.0))).unwrap();
+
+    let groups = simulation.find_groups(&GroupFinder::FriendsOfFriends(FriendsOfFriends::new(0.15)));
+    assert_eq!(groups.groups.len(), 1);
+    let group = &groups.groups[0];
+    assert_eq!(group.members.len(), 16);
+    assert!((group.center.x.abs() - 5.0).abs() < 1e-5 && group.center.y.abs() < 1e-5);
+    assert!((group.radius - 0.15 * 2.0_f32.sqrt()).abs() < 1e-5);
+    assert!((group.velocity_dispersion - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn groups_export_and_color_bodies() {
+    let mut simulation = Simulation::from_bodies(two_clumps_and_a_bridge());
+    let groups = simulation.find_groups(&GroupFinder::Density(DensityGroups::new(0.16, 5)));
+
+    let mut csv = Vec::new();
+    groups.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("0,37,37,"));
+
+    let mut membership = Vec::new();
+    groups.write_membership_csv(&mut membership).unwrap();
+    let membership = String::from_utf8(membership).unwrap();
+    assert_eq!(membership.lines().nth(1), Some("0,0"));
+    assert_eq!(membership.lines().last(), Some("66,"));
+
+    simulation.set_body_colors(Some(groups.colors()));
+    let circles = simulation.get_bodies_as_circles();
+    assert_eq!(circles[0].color, circles[52].color);
+    assert_ne!(circles[0].color, circles[36].color);
+    assert_ne!(circles[0].color, circles[56].color);
+
+    // Stale colours are dropped rather than shifted onto other bodies.
+    simulation.add_body(Body::new(Vector2::new(9.0, 9.0), 1.0, 100.0));
+    assert!(simulation.get_bodies_as_circles().iter().all(|circle| circle.color == 0xFFFFFFFF));
+}
@@ -0,0 +1,96 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{AdaptiveTimestep, Body, G, Integrator, Simulation, Timestep, TimestepCriterion};
+
+const STAR_MASS: f32 = 1.0;
+const PLANET_MASS: f32 = 1e-3;
+const SEMI_MAJOR_AXIS: f32 = 1.0;
+
+/// Two bodies in the centre-of-mass frame, starting at periapsis.
+fn kepler_pair(eccentricity: f32) -> Vec<Body> {
+    let mu = G * (STAR_MASS + PLANET_MASS);
+    let periapsis = SEMI_MAJOR_AXIS * (1.0 - eccentricity);
+    let speed = (mu * (1.0 + eccentricity) / periapsis).sqrt();
+
+    let star_share = PLANET_MASS / (STAR_MASS + PLANET_MASS);
+    let planet_share = STAR_MASS / (STAR_MASS + PLANET_MASS);
+    vec![
+        Body::new_sp(Vector2::new(-periapsis * star_share, 0.0), STAR_MASS, Vector2::new(0.0, -speed * star_share), 100.0),
+        Body::new_sp(Vector2::new(periapsis * planet_share, 0.0), PLANET_MASS, Vector2::new(0.0, speed * planet_share), 100.0),
+    ]
+}
+
+fn analytic_energy() -> f32 {
+    -G * STAR_MASS * PLANET_MASS / (2.0 * SEMI_MAJOR_AXIS)
+}
+
+fn period() -> f32 {
+    std::f32::consts::TAU * (SEMI_MAJOR_AXIS.powi(3) / (G * (STAR_MASS + PLANET_MASS))).sqrt()
+}
+
+fn relative_energy_error(simulation: &Simulation) -> f32 {
+    ((simulation.total_energy() - analytic_energy()) / analytic_energy()).abs()
+}
+
+fn run_orbits(integrator: Integrator, timestep: Timestep, eccentricity: f32, orbits: f32) -> Simulation {
+    let mut simulation = Simulation::from_bodies(kepler_pair(eccentricity));
+    simulation.set_integrator(integrator).unwrap();
+    simulation.set_timestep(timestep).unwrap();
+    while simulation.time() < (orbits * period()) as f64 {
+        simulation.update();
+    }
+    simulation
+}
+
+#[test]
+fn initial_conditions_match_analytic_energy() {
+    let simulation = Simulation::from_bodies(kepler_pair(0.5));
+    assert!(relative_energy_error(&simulation) < 1e-5);
+}
+
+#[test]
+fn hermite_with_aarseth_steps_conserves_energy() {
+    let timestep = Timestep::Adaptive(AdaptiveTimestep {
+        eta: 0.1,
+        ..AdaptiveTimestep::new(TimestepCriterion::Aarseth, 1e-6, 0.01)
+    });
+    let simulation = run_orbits(Integrator::Hermite, timestep, 0.7, 10.0);
+
+    assert!(relative_energy_error(&simulation) < 1e-4);
+}
+
+#[test]
+fn hermite_returns_to_periapsis_after_one_period() {
+    let steps = 400;
+    let dt = period() / steps as f32;
+    let mut simulation = Simulation::from_bodies(kepler_pair(0.5));
+    simulation.set_integrator(Integrator::Hermite).unwrap();
+    simulation.set_timestep(Timestep::Fixed(dt)).unwrap();
+    for _ in 0..steps {
+        simulation.update();
+    }
+
+    let start = kepler_pair(0.5);
+    let error = (simulation.bodies()[1].position - start[1].position).magnitude();
+    assert!(error < 1e-3 * SEMI_MAJOR_AXIS);
+}
+
+#[test]
+fn hermite_beats_leapfrog_at_equal_step() {
+    // Leapfrog's energy error swings with the orbit and nearly vanishes again at
+    // whole periods, so the runs are compared by their worst error.
+    let dt = period() / 200.0;
+    let worst_energy_error = |integrator: Integrator| {
+        let mut simulation = Simulation::from_bodies(kepler_pair(0.5));
+        simulation.set_integrator(integrator).unwrap();
+        simulation.set_timestep(Timestep::Fixed(dt)).unwrap();
+        let mut worst = 0.0f32;
+        while simulation.time() < (3.0 * period()) as f64 {
+            simulation.update();
+            worst = worst.max(relative_energy_error(&simulation));
+        }
+        worst
+    };
+
+    let (hermite, leapfrog) = (worst_energy_error(Integrator::Hermite), worst_energy_error(Integrator::Leapfrog));
+    assert!(hermite * 10.0 < leapfrog, "hermite {} leapfrog {}", hermite, leapfrog);
+}
@@ -0,0 +1,57 @@
+use cgmath::{InnerSpace, Vector2};
+use std::f32::consts::PI;
+use wgpu_test::{Body, G, OrbitalElements, Simulation};
+
+fn assert_close(a: f32, b: f32, tolerance: f32) {
+    assert!((a - b).abs() < tolerance, "{a} != {b}");
+}
+
+#[test]
+fn elements_round_trip_through_state_vectors() {
+    let mu = 3.0;
+    let cases = [
+        OrbitalElements { semi_major_axis: 2.0, eccentricity: 0.3, inclination: 0.0, longitude_of_ascending_node: 0.0, argument_of_periapsis: 1.1, mean_anomaly: 0.4 },
+        OrbitalElements { semi_major_axis: 0.5, eccentricity: 0.9, inclination: 0.0, longitude_of_ascending_node: 0.0, argument_of_periapsis: 4.0, mean_anomaly: 3.0 },
+        OrbitalElements { semi_major_axis: 1.0, eccentricity: 0.5, inclination: PI, longitude_of_ascending_node: 0.0, argument_of_periapsis: 2.0, mean_anomaly: 5.5 },
+        OrbitalElements { semi_major_axis: -1.5, eccentricity: 1.8, inclination: 0.0, longitude_of_ascending_node: 0.0, argument_of_periapsis: 0.7, mean_anomaly: -1.2 },
+    ];
+
+    for elements in cases {
+        let (position, speed) = elements.to_state_vectors(mu);
+        let back = OrbitalElements::from_state_vectors(position, speed, mu);
+
+        assert_close(back.semi_major_axis, elements.semi_major_axis, 1e-3);
+        assert_close(back.eccentricity, elements.eccentricity, 1e-4);
+        assert_close(back.inclination, elements.inclination, 1e-6);
+        assert_close(back.argument_of_periapsis, elements.argument_of_periapsis, 1e-3);
+        assert_close(back.mean_anomaly, elements.mean_anomaly, 1e-3);
+    }
+}
+
+#[test]
+fn circular_orbit_has_expected_speed() {
+    let mu = 4.0;
+    let (position, speed) = OrbitalElements::circular(2.0, 0.0).to_state_vectors(mu);
+
+    assert_close(position.magnitude(), 2.0, 1e-6);
+    assert_close(speed.magnitude(), (mu / 2.0).sqrt(), 1e-6);
+    assert_close(position.dot(speed), 0.0, 1e-6);
+}
+
+#[test]
+fn tracked_orbit_keeps_its_elements() {
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    let elements = OrbitalElements { eccentricity: 0.2, ..OrbitalElements::circular(1.0, 0.0) };
+    let planet = simulation.add_body_on_orbit(0, &elements, 1e-4, 100.0);
+    simulation.track_orbits(0, vec![planet]);
+
+    let period = elements.period(G * (1.0 + 1e-4));
+    while simulation.time() < period as f64 {
+        simulation.update();
+    }
+
+    let (index, tracked) = simulation.orbit_report()[0];
+    assert_eq!(index, planet);
+    assert_close(tracked.semi_major_axis, 1.0, 1e-2);
+    assert_close(tracked.eccentricity, 0.2, 1e-2);
+}
@@ -0,0 +1,105 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{Body, Coulomb, ForceParameters, Gravity, Integrator, LennardJones, PairForce, Simulation, Timestep, Yukawa};
+
+const PARAMETERS: ForceParameters = ForceParameters { gravitational_constant: 2.0, softening: 0.01 };
+
+fn forces() -> Vec<Box<dyn PairForce>> {
+    vec![
+        Box::new(Gravity),
+        Box::new(Coulomb { coulomb_constant: 3.0 }),
+        Box::new(LennardJones { epsilon: 1.0, sigma: 1.0, cutoff: 2.5 }),
+        Box::new(Yukawa { coupling: 3.0, screening_length: 0.8 }),
+    ]
+}
+
+fn charged(position: Vector2<f32>, speed: Vector2<f32>, mass: f32, charge: f32) -> Body {
+    let mut body = Body::new_sp(position, mass, speed, 100.0);
+    body.charge = charge;
+    body
+}
+
+fn assert_close(a: Vector2<f32>, b: Vector2<f32>) {
+    assert!((a - b).magnitude() <= 5e-3 * (1.0 + b.magnitude()), "{a:?} != {b:?}");
+}
+
+#[test]
+fn acceleration_is_minus_the_energy_gradient_over_mass() {
+    let h = 1e-3;
+    let other = charged(Vector2::new(0.2, -0.1), Vector2::new(0.1, 0.3), 1.5, -0.7);
+    for force in forces() {
+        let body = charged(Vector2::new(1.1, 0.4), Vector2::new(-0.2, 0.5), 2.0, 1.3);
+        let energy_at = |offset: Vector2<f32>| {
+            let moved = Body { position: body.position + offset, ..body };
+            force.potential_energy(&moved, &other, &PARAMETERS)
+        };
+        let gradient = Vector2::new(
+            energy_at(Vector2::new(h, 0.0)) - energy_at(Vector2::new(-h, 0.0)),
+            energy_at(Vector2::new(0.0, h)) - energy_at(Vector2::new(0.0, -h)),
+        ) / (2.0 * h);
+
+        let (acceleration, _) = force.acceleration(&body, &other, &PARAMETERS);
+        assert_close(acceleration, -gradient / body.mass);
+    }
+}
+
+#[test]
+fn jerk_is_the_acceleration_derivative() {
+    let h = 1e-3;
+    for force in forces() {
+        let body = charged(Vector2::new(1.1, 0.4), Vector2::new(-0.2, 0.5), 2.0, 1.3);
+        let other = charged(Vector2::new(0.2, -0.1), Vector2::new(0.1, 0.3), 1.5, -0.7);
+        let at = |t: f32| {
+            let body = Body { position: body.position + body.speed * t, ..body };
+            let other = Body { position: other.position + other.speed * t, ..other };
+            force.acceleration(&body, &other, &PARAMETERS).0
+        };
+
+        let (_, jerk) = force.acceleration(&body, &other, &PARAMETERS);
+        assert_close(jerk, (at(h) - at(-h)) / (2.0 * h));
+    }
+}
+
+#[test]
+fn pair_acceleration_matches_both_halves() {
+    let a = charged(Vector2::new(1.1, 0.4), Vector2::new(-0.2, 0.5), 2.0, 1.3);
+    let b = charged(Vector2::new(0.2, -0.1), Vector2::new(0.1, 0.3), 1.5, -0.7);
+    for force in forces() {
+        let [from_a, from_b] = force.pair_acceleration(&a, &b, &PARAMETERS);
+        assert_close(from_a.0, force.acceleration(&a, &b, &PARAMETERS).0);
+        assert_close(from_b.0, force.acceleration(&b, &a, &PARAMETERS).0);
+    }
+}
+
+#[test]
+fn charged_bodies_conserve_energy() {
+    let setups: Vec<(Box<dyn PairForce>, Vec<Body>)> = vec![
+        // Two like charges scattering off each other.
+        (Box::new(Coulomb { coulomb_constant: 1.0 }), vec![
+            charged(Vector2::new(-2.0, 0.1), Vector2::new(1.0, 0.0), 1.0, 1.0),
+            charged(Vector2::new(2.0, -0.1), Vector2::new(-1.0, 0.0), 1.0, 1.0),
+        ]),
+        // A vibrating Lennard-Jones dimer.
+        (Box::new(LennardJones { epsilon: 1.0, sigma: 1.0, cutoff: 2.5 }), vec![
+            Body::new_sp(Vector2::new(-0.6, 0.0), 1.0, Vector2::new(0.0, 0.2), 100.0),
+            Body::new_sp(Vector2::new(0.6, 0.0), 1.0, Vector2::new(0.0, -0.2), 100.0),
+        ]),
+        // Opposite charges bound by a screened attraction.
+        (Box::new(Yukawa { coupling: 1.0, screening_length: 2.0 }), vec![
+            charged(Vector2::new(-0.5, 0.0), Vector2::new(0.0, 0.4), 1.0, 1.0),
+            charged(Vector2::new(0.5, 0.0), Vector2::new(0.0, -0.4), 1.0, -1.0),
+        ]),
+    ];
+
+    for (force, bodies) in setups {
+        let mut simulation = Simulation::from_bodies(bodies);
+        simulation.set_pair_force(force).unwrap();
+        simulation.set_integrator(Integrator::Hermite).unwrap();
+        simulation.set_timestep(Timestep::Fixed(1e-3)).unwrap();
+
+        let initial = simulation.total_energy();
+        for _ in 0..4000 {
+            simulation.update();
+        }
+        assert!((simulation.total_energy() - initial).abs() < 1e-4 * initial.abs().max(1.0));
+    }
+}
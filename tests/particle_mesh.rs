@@ -0,0 +1,106 @@
+use cgmath::{InnerSpace, Vector2};
+use std::f32::consts::PI;
+use wgpu_test::{Body, Coulomb, ConfigurationError, ForceSolver, Integrator, ParticleMesh, PeriodicBox, Simulation, Timestep};
+
+const SIZE: f32 = 1.0;
+
+/// A lattice displaced by box-scale waves. The direct sum still feels the
+/// lattice's graininess, which fades with the number of bodies per mesh cell.
+fn perturbed_lattice(per_side: usize) -> Vec<Body> {
+    let spacing = SIZE / per_side as f32;
+    let mut bodies = Vec::with_capacity(per_side * per_side);
+    for iy in 0..per_side {
+        for ix in 0..per_side {
+            let x = -0.5 * SIZE + (ix as f32 + 0.5) * spacing;
+            let y = -0.5 * SIZE + (iy as f32 + 0.5) * spacing;
+            let displacement = Vector2::new(
+                0.04 * (2.0 * PI * x / SIZE).sin(),
+                0.03 * (2.0 * PI * y / SIZE).sin() + 0.02 * (2.0 * PI * (x + y) / SIZE).cos(),
+            );
+            bodies.push(Body::new(Vector2::new(x, y) + displacement, 1.0 / (per_side * per_side) as f32, 100.0));
+        }
+    }
+    bodies
+}
+
+/// Accelerations from an update of zero length, which leaves the bodies
+/// where they are.
+fn accelerations(solver: ForceSolver, periodic_box: PeriodicBox) -> Vec<Vector2<f32>> {
+    let mut simulation = Simulation::from_bodies(perturbed_lattice(64));
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_periodic_box(Some(periodic_box)).unwrap();
+    simulation.set_force_solver(solver).unwrap();
+    simulation.set_timestep(Timestep::Fixed(0.0)).unwrap();
+    simulation.update();
+    simulation.bodies().iter().map(|body| body.acceleration).collect()
+}
+
+#[test]
+fn particle_mesh_matches_the_ewald_direct_sum() {
+    let direct = accelerations(ForceSolver::Direct, PeriodicBox::with_ewald(SIZE));
+    let mesh = accelerations(ForceSolver::ParticleMesh(ParticleMesh::new(16)), PeriodicBox::new(SIZE));
+
+    let error = direct.iter().zip(mesh.iter()).map(|(a, b)| (a - b).magnitude2()).sum::<f32>();
+    let norm = direct.iter().map(|a| a.magnitude2()).sum::<f32>();
+    let relative = (error / norm).sqrt();
+    assert!(relative < 0.06, "relative rms error {}", relative);
+}
+
+#[test]
+fn particle_mesh_conserves_momentum() {
+    let mesh = accelerations(ForceSolver::ParticleMesh(ParticleMesh::new(32)), PeriodicBox::new(SIZE));
+    let net = mesh.iter().fold(Vector2::new(0.0, 0.0), |sum, a| sum + a);
+    let scale = mesh.iter().map(|a| a.magnitude()).sum::<f32>();
+    assert!(net.magnitude() < 1e-3 * scale);
+}
+
+#[test]
+fn uniform_lattice_feels_no_mesh_force() {
+    let mut simulation = Simulation::from_bodies(
+        (0..256).map(|i| {
+            let position = Vector2::new((i % 16) as f32 + 0.5, (i / 16) as f32 + 0.5) / 16.0 - Vector2::new(0.5, 0.5);
+            Body::new(position, 1.0, 100.0)
+        }).collect(),
+    );
+    simulation.set_periodic_box(Some(PeriodicBox::new(SIZE))).unwrap();
+    simulation.set_force_solver(ForceSolver::ParticleMesh(ParticleMesh::new(16))).unwrap();
+    simulation.update();
+
+    assert!(simulation.bodies().iter().all(|body| body.speed.magnitude() < 1e-3));
+}
+
+#[test]
+fn particle_mesh_needs_a_periodic_box_and_no_jerk() {
+    let mesh = ForceSolver::ParticleMesh(ParticleMesh::new(16));
+    let mut simulation = Simulation::from_bodies(perturbed_lattice(4));
+    assert_eq!(simulation.set_force_solver(mesh).unwrap_err(), ConfigurationError::ParticleMeshWithoutPeriodicBox);
+
+    simulation.set_periodic_box(Some(PeriodicBox::new(SIZE))).unwrap();
+    simulation.set_force_solver(mesh).unwrap();
+    assert_eq!(simulation.set_periodic_box(None).unwrap_err(), ConfigurationError::ParticleMeshWithoutPeriodicBox);
+    assert!(simulation.periodic_box().is_some());
+    assert_eq!(simulation.set_integrator(Integrator::Hermite).unwrap_err(), ConfigurationError::HermiteWithoutJerk);
+    assert_eq!(simulation.set_pair_force(Box::new(Coulomb { coulomb_constant: 1.0 })).unwrap_err(), ConfigurationError::ParticleMeshWithoutNewtonianGravity);
+    simulation.update();
+}
+
+#[test]
+fn mesh_energy_falls_by_the_work_of_the_mesh_forces() {
+    let mut simulation = Simulation::from_bodies(perturbed_lattice(64));
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_periodic_box(Some(PeriodicBox::new(SIZE))).unwrap();
+    simulation.set_force_solver(ForceSolver::ParticleMesh(ParticleMesh::new(16))).unwrap();
+    simulation.set_timestep(Timestep::Fixed(0.0)).unwrap();
+    simulation.update();
+
+    // Nudging every body along its acceleration releases Σ m a·δ.
+    let step = 1e-3 / simulation.bodies().iter().map(|body| body.acceleration.magnitude()).fold(0.0, f32::max);
+    let before = simulation.interaction_energy();
+    let mut work = 0.0;
+    for body in simulation.bodies_mut() {
+        work += body.mass * body.acceleration.magnitude2() * step;
+        body.position += body.acceleration * step;
+    }
+    let released = before - simulation.interaction_energy();
+    assert!((released - work).abs() < 0.03 * work, "{} vs {}", released, work);
+}
@@ -0,0 +1,137 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{AdaptiveTimestep, Body, PeriodicBox, Simulation, TimestepCriterion};
+
+#[test]
+fn bodies_wrap_around_the_box() {
+    let mut simulation = Simulation::from_bodies(vec![
+        Body::new_sp(Vector2::new(0.49, 0.0), 1.0, Vector2::new(1.0, -2.0), 100.0),
+    ]);
+    simulation.set_self_gravity(false);
+    simulation.set_periodic_box(Some(PeriodicBox::new(1.0))).unwrap();
+
+    for _ in 0..1000 {
+        simulation.update();
+        let position = simulation.bodies()[0].position;
+        assert!((-0.5..0.5).contains(&position.x) && (-0.5..0.5).contains(&position.y));
+    }
+
+    // One unit of time moves the body by whole box lengths in both axes.
+    let position = simulation.bodies()[0].position;
+    assert!((position - Vector2::new(0.49, 0.0)).magnitude() < 1e-3);
+}
+
+#[test]
+fn minimum_image_pairs_pull_across_the_edge() {
+    let bodies = vec![
+        Body::new(Vector2::new(-0.45, 0.0), 1.0, 100.0),
+        Body::new(Vector2::new(0.45, 0.0), 1.0, 100.0),
+    ];
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_periodic_box(Some(PeriodicBox::new(1.0))).unwrap();
+    simulation.update();
+
+    let [a, b] = [simulation.bodies()[0].speed, simulation.bodies()[1].speed];
+    assert!(a.x < 0.0 && b.x > 0.0);
+    assert!((a + b).magnitude() < 1e-6 * a.magnitude());
+}
+
+#[test]
+fn free_fall_step_sees_pairs_across_the_edge() {
+    let bodies = [Body::new(Vector2::new(-4.9, 0.0), 1.0, 1.0), Body::new(Vector2::new(4.9, 0.0), 1.0, 1.0)];
+    let adaptive = AdaptiveTimestep { eta: 1.0, ..AdaptiveTimestep::new(TimestepCriterion::FreeFall, 1e-6, 100.0) };
+
+    // 0.2 apart through the edge, not 9.8 across the box.
+    let expected = (0.2f32.powi(3) / 2.0).sqrt();
+    let dt = adaptive.compute_dt(&bodies, 1.0, Some(&PeriodicBox::new(10.0)));
+    assert!((dt - expected).abs() < 1e-3 * expected, "{} vs {}", dt, expected);
+    assert!(adaptive.compute_dt(&bodies, 1.0, None) > 100.0 * expected);
+}
+
+#[test]
+fn ewald_force_matches_a_direct_lattice_sum() {
+    let size = 1.0;
+    let separation = Vector2::new(0.3_f64, 0.1);
+
+    // Symmetric square shells of images; the neutralising background's pull
+    // on an off-centre body fades as the shells grow.
+    let images = 400;
+    let mut expected = Vector2::new(0.0_f64, 0.0);
+    for nx in -images..=images {
+        for ny in -images..=images {
+            let dx = separation + Vector2::new(nx as f64, ny as f64) * size;
+            expected += dx / dx.magnitude().powi(3);
+        }
+    }
+
+    let bodies = vec![
+        Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0),
+        Body::new(Vector2::new(separation.x as f32, separation.y as f32), 1.0, 100.0),
+    ];
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_periodic_box(Some(PeriodicBox::with_ewald(size as f32))).unwrap();
+    simulation.set_timestep(wgpu_test::Timestep::Fixed(0.0)).unwrap();
+    simulation.update();
+
+    // An update of zero length leaves the forces at the starting positions.
+    let acceleration = simulation.bodies()[0].acceleration;
+    let expected = Vector2::new(expected.x as f32, expected.y as f32);
+    assert!((acceleration - expected).magnitude() < 1e-2 * expected.magnitude(),
+        "{:?} vs {:?}", acceleration, expected);
+}
+
+#[test]
+fn ewald_force_vanishes_at_symmetric_points() {
+    let size = 2.0;
+    let periodic_box = PeriodicBox::with_ewald(size);
+
+    // Half a box away the images pull equally from both sides.
+    for separation in [Vector2::new(0.5 * size, 0.0), Vector2::new(0.5 * size, 0.5 * size)] {
+        let (_, gradient) = periodic_box.ewald_correction(separation).unwrap();
+        let nearest = -separation / separation.magnitude().powi(3);
+        assert!((gradient + nearest).magnitude() < 1e-3 * nearest.magnitude());
+    }
+}
+
+#[test]
+fn box_outline_traces_the_edges() {
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    assert!(simulation.get_box_outline().is_empty());
+
+    simulation.set_periodic_box(Some(PeriodicBox::new(4.0))).unwrap();
+    let outline = simulation.get_box_outline();
+    assert_eq!(outline.len(), 8);
+    // Every vertex is a corner, and each edge runs along one side.
+    for edge in outline.chunks(2) {
+        let [a, b] = [edge[0].position, edge[1].position];
+        assert!(a[..2].iter().chain(&b[..2]).all(|coordinate| coordinate.abs() == 2.0));
+        assert!((a[0] == b[0]) != (a[1] == b[1]));
+    }
+    // The images are drawn with the bodies, the outline separately.
+    assert_eq!(simulation.get_bodies_as_circles().len(), 9);
+}
+
+#[test]
+fn ewald_jerk_is_the_rate_of_change_of_the_force() {
+    let bodies = |offset: f32| {
+        let speeds = [Vector2::new(0.3, -0.2), Vector2::new(-0.1, 0.4)];
+        let positions = [Vector2::new(0.0, 0.0), Vector2::new(0.3, 0.1)];
+        positions.iter().zip(speeds)
+            .map(|(&position, speed)| Body::new_sp(position + speed * offset, 1.0, speed, 100.0))
+            .collect::<Vec<_>>()
+    };
+    let forces = |offset: f32| {
+        let mut simulation = Simulation::from_bodies(bodies(offset));
+        simulation.set_gravitational_constant(1.0);
+        simulation.set_periodic_box(Some(PeriodicBox::with_ewald(1.0))).unwrap();
+        simulation.set_timestep(wgpu_test::Timestep::Fixed(0.0)).unwrap();
+        simulation.update();
+        (simulation.bodies()[0].acceleration, simulation.bodies()[0].jerk)
+    };
+
+    let h = 1e-3;
+    let (_, jerk) = forces(0.0);
+    let expected = (forces(h).0 - forces(-h).0) / (2.0 * h);
+    assert!((jerk - expected).magnitude() < 1e-2 * expected.magnitude(), "{:?} vs {:?}", jerk, expected);
+}
@@ -0,0 +1,91 @@
+use cgmath::Vector2;
+use wgpu_test::{solar_system_index, Body, ConfigurationError, Integrator, OrbitalElements, PostNewtonian, Regularization, Simulation, Timestep, SOLAR_SYSTEM_SPEED_OF_LIGHT};
+
+const DAYS_PER_CENTURY: f64 = 36_525.0;
+const ARCSECONDS_PER_RADIAN: f64 = 206_264.806;
+
+/// Mercury's argument of periapsis around the Sun after `days`, unwrapped.
+fn mercury_periapsis_shift(post_newtonian: Option<PostNewtonian>, days: f64) -> f64 {
+    let (sun, mercury) = (solar_system_index("Sun", false).unwrap(), solar_system_index("Mercury", false).unwrap());
+    let mut simulation = Simulation::new_solar_system(false);
+    simulation.set_post_newtonian(post_newtonian).unwrap();
+
+    let start = simulation.orbital_elements(mercury, sun).argument_of_periapsis as f64;
+    while simulation.time() < days {
+        simulation.update();
+    }
+    let end = simulation.orbital_elements(mercury, sun).argument_of_periapsis as f64;
+
+    (end - start + std::f64::consts::PI).rem_euclid(std::f64::consts::TAU) - std::f64::consts::PI
+}
+
+#[test]
+fn mercury_perihelion_advances_43_arcseconds_per_century() {
+    // The real shift is below what f32 positions resolve over a short run, so
+    // light is slowed tenfold and the 1/c² shift scaled back. Differencing with
+    // a Newtonian run removes the planets' share of the precession.
+    let slowdown = 10.0;
+    let days = 10.0 * 365.25;
+    let relativistic = mercury_periapsis_shift(Some(PostNewtonian::new(SOLAR_SYSTEM_SPEED_OF_LIGHT / slowdown as f32)), days);
+    let newtonian = mercury_periapsis_shift(None, days);
+
+    let per_century = (relativistic - newtonian) / (slowdown * slowdown) * DAYS_PER_CENTURY / days * ARCSECONDS_PER_RADIAN;
+    assert!((per_century - 43.0).abs() < 3.0, "precession was {per_century}″ per century");
+}
+
+/// Argument of periapsis of a tight relativistic binary after ten orbits,
+/// taken in `steps` fixed steps.
+fn binary_periapsis_shift(regularization: Option<Regularization>, steps: usize) -> f32 {
+    let mu = 1.5;
+    let elements = OrbitalElements {
+        semi_major_axis: 1.0,
+        eccentricity: 0.5,
+        inclination: 0.0,
+        longitude_of_ascending_node: 0.0,
+        argument_of_periapsis: 0.0,
+        mean_anomaly: 0.0,
+    };
+    let duration = 10.0 * elements.period(mu);
+
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_softening(0.0);
+    simulation.add_body_on_orbit(0, &elements, 0.5, 100.0);
+    simulation.set_post_newtonian(Some(PostNewtonian::new(40.0))).unwrap();
+    simulation.set_regularization(regularization).unwrap();
+    simulation.set_timestep(Timestep::Fixed(duration / steps as f32)).unwrap();
+    for _ in 0..steps {
+        simulation.update();
+    }
+    simulation.orbital_elements(1, 0).argument_of_periapsis
+}
+
+#[test]
+fn regularized_binary_precesses_like_the_direct_one() {
+    // About 0.024 rad an orbit; without the 1PN terms in the pair it would not move.
+    let regularized = binary_periapsis_shift(Some(Regularization::new(10.0)), 30);
+    let direct = binary_periapsis_shift(None, 20_000);
+    assert!(direct > 0.2, "direct shift was {direct}");
+    assert!((regularized - direct).abs() < 0.02 * direct, "{regularized} vs {direct}");
+}
+
+#[test]
+fn hermite_is_rejected_with_post_newtonian_terms() {
+    let mut simulation = Simulation::new_solar_system(false);
+    simulation.set_post_newtonian(Some(PostNewtonian::new(SOLAR_SYSTEM_SPEED_OF_LIGHT))).unwrap();
+    assert_eq!(simulation.set_integrator(Integrator::Hermite).unwrap_err(), ConfigurationError::HermiteWithoutJerk);
+
+    simulation.set_post_newtonian(None).unwrap();
+    simulation.set_integrator(Integrator::Hermite).unwrap();
+    assert_eq!(simulation.set_post_newtonian(Some(PostNewtonian::new(SOLAR_SYSTEM_SPEED_OF_LIGHT))).unwrap_err(), ConfigurationError::HermiteWithoutJerk);
+}
+
+#[test]
+fn corrections_of_a_few_bodies_match_the_full_set() {
+    let simulation = Simulation::new_solar_system(true);
+    let post_newtonian = PostNewtonian::new(SOLAR_SYSTEM_SPEED_OF_LIGHT);
+    let all = post_newtonian.accelerations(simulation.bodies(), simulation.gravitational_constant());
+    let indices = [solar_system_index("Mercury", true).unwrap(), solar_system_index("Moon", true).unwrap()];
+    let some = post_newtonian.accelerations_of(simulation.bodies(), simulation.gravitational_constant(), &indices);
+    assert_eq!(some, indices.map(|i| all[i]));
+}
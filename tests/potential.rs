@@ -0,0 +1,71 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{
+    Body, ExternalPotential, Integrator, LogarithmicHaloPotential, MiyamotoNagaiPotential, NfwPotential,
+    PointMassPotential, Simulation, Timestep, UniformFieldPotential,
+};
+
+const G: f32 = 1.0;
+
+fn potentials() -> Vec<Box<dyn ExternalPotential>> {
+    vec![
+        Box::new(PointMassPotential { center: Vector2::new(0.5, -0.2), mass: 2.0 }),
+        Box::new(LogarithmicHaloPotential { center: Vector2::new(0.0, 0.0), circular_speed: 1.5, core_radius: 0.3, axis_ratio: 0.8 }),
+        Box::new(NfwPotential { center: Vector2::new(0.0, 0.0), characteristic_mass: 5.0, scale_radius: 2.0 }),
+        Box::new(MiyamotoNagaiPotential { center: Vector2::new(0.0, 0.0), mass: 3.0, scale_length: 1.0, scale_height: 0.2 }),
+        Box::new(UniformFieldPotential { acceleration: Vector2::new(0.1, -0.05) }),
+    ]
+}
+
+fn assert_close(a: Vector2<f32>, b: Vector2<f32>) {
+    assert!((a - b).magnitude() <= 2e-3 * (1.0 + b.magnitude()), "{a:?} != {b:?}");
+}
+
+#[test]
+fn acceleration_is_minus_the_potential_gradient() {
+    let h = 1e-2;
+    for potential in potentials() {
+        for position in [Vector2::new(1.3, 0.7), Vector2::new(-2.0, 1.1), Vector2::new(0.4, -3.0)] {
+            let gradient = Vector2::new(
+                potential.potential(position + Vector2::new(h, 0.0), G) - potential.potential(position - Vector2::new(h, 0.0), G),
+                potential.potential(position + Vector2::new(0.0, h), G) - potential.potential(position - Vector2::new(0.0, h), G),
+            ) / (2.0 * h);
+            assert_close(potential.acceleration(position, G), -gradient);
+        }
+    }
+}
+
+#[test]
+fn jerk_is_the_acceleration_derivative_along_the_path() {
+    let h = 1e-2;
+    let speed = Vector2::new(0.3, -0.8);
+    for potential in potentials() {
+        for position in [Vector2::new(1.3, 0.7), Vector2::new(-2.0, 1.1)] {
+            let difference = (potential.acceleration(position + speed * h, G) - potential.acceleration(position - speed * h, G)) / (2.0 * h);
+            assert_close(potential.jerk(position, speed, G), difference);
+        }
+    }
+}
+
+#[test]
+fn test_stars_conserve_energy_in_a_fixed_potential() {
+    let bodies = (0..8)
+        .map(|i| {
+            let angle = i as f32;
+            Body::new_sp(Vector2::new(angle.cos(), angle.sin()) * (1.0 + 0.3 * i as f32), 1e-3, Vector2::new(-angle.sin(), angle.cos()) * 1.2, 100.0)
+        })
+        .collect();
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(G);
+    simulation.set_self_gravity(false);
+    for potential in potentials().into_iter().skip(1) {
+        simulation.add_external_potential(potential);
+    }
+    simulation.set_integrator(Integrator::Hermite).unwrap();
+    simulation.set_timestep(Timestep::Fixed(1e-3)).unwrap();
+
+    let initial = simulation.total_energy();
+    for _ in 0..2000 {
+        simulation.update();
+    }
+    assert!(((simulation.total_energy() - initial) / initial).abs() < 1e-4);
+}
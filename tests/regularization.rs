@@ -0,0 +1,126 @@
+use cgmath::{InnerSpace, Vector2};
+use std::f32::consts::TAU;
+use wgpu_test::{
+    AdaptiveTimestep, Body, ConfigurationError, FastMultipole, ForceSolver, Integrator, LogarithmicGravity, OrbitalElements, Regularization, Simulation, Timestep,
+    TimestepCriterion,
+};
+
+fn eccentric() -> OrbitalElements {
+    OrbitalElements {
+        semi_major_axis: 1.0,
+        eccentricity: 0.95,
+        inclination: 0.0,
+        longitude_of_ascending_node: 0.0,
+        argument_of_periapsis: 0.5,
+        mean_anomaly: 2.0,
+    }
+}
+
+#[test]
+fn hard_binary_keeps_its_orbit_with_a_long_global_step() {
+    let mu = 1.5;
+    let elements = eccentric();
+    let period = elements.period(mu);
+
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    simulation.set_gravitational_constant(1.0);
+    simulation.add_body_on_orbit(0, &elements, 0.5, 100.0);
+    simulation.set_regularization(Some(Regularization::new(10.0))).unwrap();
+    simulation.set_timestep(Timestep::Fixed(period / 3.0)).unwrap();
+
+    let centre = |simulation: &Simulation| {
+        let bodies = simulation.bodies();
+        ((bodies[0].position + bodies[1].position * 0.5) / 1.5, (bodies[0].speed + bodies[1].speed * 0.5) / 1.5)
+    };
+    let (start, drift) = centre(&simulation);
+
+    for _ in 0..30 {
+        simulation.update();
+    }
+
+    // Ten full orbits: the pair is back where it started, pericentre passages
+    // at 1 - e = 0.05 and all.
+    let after = simulation.orbital_elements(1, 0);
+    assert!((after.semi_major_axis - 1.0).abs() < 1e-3, "a = {}", after.semi_major_axis);
+    assert!((after.eccentricity - 0.95).abs() < 1e-4, "e = {}", after.eccentricity);
+    let phase = (after.mean_anomaly - elements.mean_anomaly + TAU / 2.0).rem_euclid(TAU) - TAU / 2.0;
+    assert!(phase.abs() < 1e-3, "mean anomaly drifted by {}", phase);
+
+    let (end, _) = centre(&simulation);
+    assert!((end - start - drift * simulation.time() as f32).magnitude() < 1e-4);
+}
+
+#[test]
+fn binary_in_a_triple_does_not_set_the_global_step() {
+    // Equal masses about the origin: away from it, f32 positions would lose
+    // the pericentre separation of 5e-4 to rounding.
+    let inner = OrbitalElements { semi_major_axis: 0.01, ..eccentric() };
+    let (position, speed) = inner.to_state_vectors(2.0);
+    let mut bodies = vec![
+        Body::new_sp(-position / 2.0, 1.0, -speed / 2.0, 100.0),
+        Body::new_sp(position / 2.0, 1.0, speed / 2.0, 100.0),
+    ];
+
+    // A light third body on a wide circular orbit around the pair.
+    let (position, speed) = OrbitalElements::circular(2.0, 0.0).to_state_vectors(2.0);
+    bodies.push(Body::new_sp(position, 1e-3, speed, 100.0));
+
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_regularization(Some(Regularization::new(0.1))).unwrap();
+    simulation.set_timestep(Timestep::Adaptive(AdaptiveTimestep::new(TimestepCriterion::FreeFall, 1e-6, 0.05))).unwrap();
+
+    let inner_period = inner.period(2.0);
+    let mut steps = 0;
+    while simulation.time() < 10.0 {
+        simulation.update();
+        steps += 1;
+    }
+
+    assert!(simulation.dt() > 5.0 * inner_period, "dt = {} for an inner period of {}", simulation.dt(), inner_period);
+    assert!(steps < 1000);
+
+    let after = simulation.orbital_elements(1, 0);
+    assert!((after.semi_major_axis - 0.01).abs() < 1e-5, "a = {}", after.semi_major_axis);
+    assert!((after.eccentricity - 0.95).abs() < 1e-3, "e = {}", after.eccentricity);
+}
+
+#[test]
+fn only_mutual_nearest_neighbours_pair_up() {
+    let bodies = vec![
+        Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0),
+        Body::new(Vector2::new(0.1, 0.0), 1.0, 100.0),
+        Body::new(Vector2::new(0.25, 0.0), 1.0, 100.0),
+        Body::new(Vector2::new(5.0, 0.0), 1.0, 100.0),
+        Body::new_test_particle(Vector2::new(5.01, 0.0), Vector2::new(0.0, 0.0)),
+    ];
+    let pairs = Regularization::new(1.0).find_pairs(&bodies, |a, b| b.position - a.position);
+    assert_eq!(pairs, vec![(0, 1)]);
+}
+
+#[test]
+fn regularization_needs_gravity_under_the_direct_solver() {
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    simulation.set_pair_force(Box::new(LogarithmicGravity)).unwrap();
+    assert_eq!(simulation.set_regularization(Some(Regularization::new(1.0))).unwrap_err(), ConfigurationError::RegularizationWithoutDirectGravity);
+
+    simulation.set_force_solver(ForceSolver::FastMultipole(FastMultipole::new(8))).unwrap();
+    assert_eq!(simulation.set_regularization(Some(Regularization::new(1.0))).unwrap_err(), ConfigurationError::RegularizationWithoutDirectGravity);
+
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    simulation.set_regularization(Some(Regularization::new(1.0))).unwrap();
+    assert_eq!(simulation.set_pair_force(Box::new(LogarithmicGravity)).unwrap_err(), ConfigurationError::RegularizationWithoutDirectGravity);
+}
+
+#[test]
+fn regularization_needs_leapfrog_without_block_steps() {
+    let block = Timestep::Block(AdaptiveTimestep::new(TimestepCriterion::FreeFall, 1e-4, 0.1));
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    simulation.set_regularization(Some(Regularization::new(1.0))).unwrap();
+    assert_eq!(simulation.set_integrator(Integrator::Hermite).unwrap_err(), ConfigurationError::RegularizationWithoutLeapfrog);
+    assert_eq!(simulation.set_timestep(block).unwrap_err(), ConfigurationError::RegularizationWithoutLeapfrog);
+
+    simulation.set_regularization(None).unwrap();
+    simulation.set_timestep(block).unwrap();
+    assert_eq!(simulation.set_regularization(Some(Regularization::new(1.0))).unwrap_err(), ConfigurationError::RegularizationWithoutLeapfrog);
+}
@@ -0,0 +1,75 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+use wgpu_test::{create_galaxy, Body, BodyExport, ExportCadence, ExportFormat, GadgetFormat, GalaxyCollision, GalaxyModel, Simulation};
+
+fn small_galaxy(seed: u64) -> GalaxyModel {
+    GalaxyModel {
+        disk_bodies: 100,
+        bulge_bodies: 20,
+        halo_bodies: 50,
+        seed,
+        ..GalaxyModel::default()
+    }
+}
+
+/// A file of its own in the temporary directory.
+fn temporary_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("nbody-seed-{}-{}", std::process::id(), name))
+}
+
+/// The bytes of a CSV export of every update of `simulation` over `updates`
+/// and of a Gadget snapshot at the end, each written to a file.
+fn snapshot_files(mut simulation: Simulation, updates: usize, name: &str) -> (Vec<u8>, Vec<u8>) {
+    let (export_path, gadget_path) = (temporary_file(&format!("{}.csv", name)), temporary_file(&format!("{}.gadget", name)));
+    let export = BodyExport::new(ExportFormat::Csv, ExportCadence::EveryUpdates(1));
+    simulation.start_export(export, Box::new(File::create(&export_path).unwrap())).unwrap();
+    for _ in 0..updates {
+        simulation.update();
+    }
+    simulation.stop_export().unwrap();
+    simulation.write_gadget_snapshot(File::create(&gadget_path).unwrap(), GadgetFormat::Format2).unwrap();
+
+    let files = (fs::read(&export_path).unwrap(), fs::read(&gadget_path).unwrap());
+    fs::remove_file(export_path).unwrap();
+    fs::remove_file(gadget_path).unwrap();
+    files
+}
+
+#[test]
+fn same_seed_gives_identical_galaxies() {
+    let galaxy = |seed: u64, name: &str| snapshot_files(Simulation::from_bodies(create_galaxy(&small_galaxy(seed), 1.0, 0.05)), 0, name);
+    let first = galaxy(7, "first-galaxy");
+    let second = galaxy(7, "second-galaxy");
+    let other = galaxy(8, "other-galaxy");
+
+    assert_eq!(first, second);
+    assert_ne!(first.0, other.0);
+    assert_ne!(first.1, other.1);
+}
+
+#[test]
+fn collision_runs_are_reproducible() {
+    let setup = GalaxyCollision {
+        galaxy: small_galaxy(3),
+        ..GalaxyCollision::default()
+    };
+    let run = |name: &str| snapshot_files(Simulation::new_galaxy_collision(&setup).unwrap(), 20, name);
+
+    let (first, second) = (run("first-run"), run("second-run"));
+    assert!(!first.0.is_empty() && !first.1.is_empty());
+    assert_eq!(first, second);
+}
+
+#[test]
+fn collision_galaxies_are_not_copies() {
+    let setup = GalaxyCollision {
+        galaxy: small_galaxy(3),
+        ..GalaxyCollision::default()
+    };
+    let bodies = setup.create_bodies().unwrap();
+    let (primary, secondary) = bodies.split_at(170);
+
+    // Equal masses, so identical draws would differ only by the orbit offset.
+    let offset = |a: &Body, b: &Body| b.position - a.position;
+    assert!(primary.iter().zip(secondary).skip(1).any(|(a, b)| offset(a, b) != offset(&primary[0], &secondary[0])));
+}
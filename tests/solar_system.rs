@@ -0,0 +1,66 @@
+use wgpu_test::{create_solar_system, solar_system_index, Simulation};
+
+/// Time for `body` to sweep a full turn around the Sun, interpolated between steps.
+fn sidereal_period(simulation: &mut Simulation, body: usize) -> f64 {
+    let sun = solar_system_index("Sun", false).unwrap();
+    let angle = |simulation: &Simulation| {
+        let offset = simulation.bodies()[body].position - simulation.bodies()[sun].position;
+        offset.y.atan2(offset.x) as f64
+    };
+
+    let mut previous = angle(simulation);
+    let mut swept = 0.0;
+    loop {
+        let previous_time = simulation.time();
+        simulation.update();
+        let current = angle(simulation);
+
+        let mut step = current - previous;
+        if step < -std::f64::consts::PI {
+            step += std::f64::consts::TAU;
+        }
+        previous = current;
+
+        if swept + step >= std::f64::consts::TAU {
+            let fraction = (std::f64::consts::TAU - swept) / step;
+            return previous_time + fraction * (simulation.time() - previous_time);
+        }
+        swept += step;
+    }
+}
+
+#[test]
+fn earth_year_is_365_days() {
+    let mut simulation = Simulation::new_solar_system(false);
+    let period = sidereal_period(&mut simulation, solar_system_index("Earth", false).unwrap());
+
+    assert!((period - 365.25).abs() < 0.5, "Earth's period was {period} days");
+}
+
+#[test]
+fn moon_orbits_earth_in_a_sidereal_month() {
+    let mut simulation = Simulation::new_solar_system(true);
+    let earth = solar_system_index("Earth", true).unwrap();
+    let moon = solar_system_index("Moon", true).unwrap();
+    assert_eq!(simulation.bodies().len(), moon + 1);
+
+    let elements = simulation.orbital_elements(moon, earth);
+    let mu = simulation.gravitational_constant() * (simulation.bodies()[earth].mass + simulation.bodies()[moon].mass);
+    assert!((elements.period(mu) - 27.32).abs() < 0.1);
+
+    for _ in 0..1000 {
+        simulation.update();
+    }
+    let elements = simulation.orbital_elements(moon, earth);
+    assert!((elements.semi_major_axis - 0.00257).abs() < 1e-4);
+}
+
+#[test]
+fn bodies_start_in_the_barycentric_frame() {
+    let bodies = create_solar_system(false);
+    let momentum = bodies.iter().map(|body| body.speed * body.mass).fold(cgmath::Vector2::new(0.0, 0.0), |a, b| a + b);
+
+    assert_eq!(bodies.len(), 9);
+    assert_eq!(solar_system_index("Moon", false), None);
+    assert!(momentum.x.abs() < 1e-9 && momentum.y.abs() < 1e-9);
+}
@@ -0,0 +1,56 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{Body, OrbitalElements, Simulation, G};
+
+fn massive_pair() -> Vec<Body> {
+    vec![
+        Body::new_sp(Vector2::new(-1.0, 0.0), 5.0, Vector2::new(0.0, -5.0), 100.0),
+        Body::new_sp(Vector2::new(1.0, 0.0), 5.0, Vector2::new(0.0, 5.0), 100.0),
+    ]
+}
+
+#[test]
+fn test_particles_do_not_pull_on_massive_bodies() {
+    let mut plain = Simulation::from_bodies(massive_pair());
+
+    let mut bodies = massive_pair();
+    for i in 0..20 {
+        let angle = i as f32 * 0.3;
+        bodies.push(Body::new_test_particle(Vector2::new(angle.cos(), angle.sin()) * 3.0, Vector2::new(0.0, 0.0)));
+    }
+    let mut traced = Simulation::from_bodies(bodies);
+
+    for _ in 0..500 {
+        plain.update();
+        traced.update();
+    }
+
+    for (a, b) in plain.bodies().iter().zip(traced.bodies()) {
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.speed, b.speed);
+    }
+    assert!(traced.bodies()[2..].iter().all(|body| body.speed.magnitude() > 0.0));
+}
+
+#[test]
+fn test_particle_follows_a_kepler_orbit() {
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    let (position, speed) = OrbitalElements::circular(1.0, 0.0).to_state_vectors(G);
+    let tracer = simulation.add_body(Body::new_test_particle(position, speed));
+
+    for _ in 0..1000 {
+        simulation.update();
+    }
+
+    assert_eq!(simulation.bodies()[0].position, Vector2::new(0.0, 0.0));
+    assert!((simulation.bodies()[tracer].position.magnitude() - 1.0).abs() < 1e-2);
+    assert_eq!(simulation.kinetic_energy(), 0.0);
+}
+
+#[test]
+fn test_particles_render_differently() {
+    let massive = Body::new(Vector2::new(0.0, 0.0), 5.0, 100.0).to_circle();
+    let tracer = Body::new_test_particle(Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0)).to_circle();
+
+    assert_ne!(massive.color, tracer.color);
+    assert!(tracer.radius > 0.0);
+}
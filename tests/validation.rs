@@ -0,0 +1,86 @@
+use cgmath::{InnerSpace, Vector2};
+use wgpu_test::{create_galaxy, reference_error, time_reversal_error, Body, GalaxyModel, GasDrag, GasVelocity, Integrator, OrbitalElements, Simulation, Timestep};
+
+fn kepler(dt: f32) -> Simulation {
+    let mut simulation = Simulation::from_bodies(vec![Body::new(Vector2::new(0.0, 0.0), 1.0, 100.0)]);
+    simulation.set_gravitational_constant(1.0);
+    let elements = OrbitalElements { eccentricity: 0.5, ..OrbitalElements::circular(1.0, 0.5) };
+    simulation.add_body_on_orbit(0, &elements, 1e-3, 100.0);
+    simulation.set_timestep(Timestep::Fixed(dt)).unwrap();
+    simulation
+}
+
+#[test]
+fn leapfrog_retraces_its_steps() {
+    let model = GalaxyModel {
+        disk_bodies: 100,
+        bulge_bodies: 20,
+        halo_bodies: 30,
+        ..GalaxyModel::default()
+    };
+    let bodies = create_galaxy(&model, 1.0, 0.05);
+    let mut simulation = Simulation::from_bodies(bodies);
+    simulation.set_gravitational_constant(1.0);
+    simulation.set_softening(0.05);
+    simulation.set_timestep(Timestep::Fixed(0.01)).unwrap();
+    let speeds = simulation.bodies().iter().map(|body| body.speed).collect::<Vec<_>>();
+
+    let report = time_reversal_error(&mut simulation, 100).unwrap();
+    assert_eq!(report.errors.len(), 150);
+    assert!(report.max_error < 1e-4, "max error {}", report.max_error);
+    assert!(report.energy_error < 1e-4, "energy error {}", report.energy_error);
+    // Back where it started and moving the same way again.
+    for (body, speed) in simulation.bodies().iter().zip(speeds) {
+        assert!((body.speed - speed).magnitude() < 1e-3);
+    }
+}
+
+#[test]
+fn drag_breaks_reversibility() {
+    let mut simulation = kepler(0.01);
+    simulation.bodies_mut()[1].size = 1.0;
+    simulation.add_body_force(Box::new(GasDrag {
+        gas_density: 1.0,
+        thermal_speed: 1.0,
+        mean_free_path: 10.0,
+        velocity: GasVelocity::Uniform(Vector2::new(0.0, 0.0)),
+    }))
+    .unwrap();
+
+    let report = time_reversal_error(&mut simulation, 200).unwrap();
+    assert!(report.max_error > 1e-2, "max error {}", report.max_error);
+}
+
+#[test]
+fn integrators_converge_to_a_fine_hermite_reference() {
+    let period = OrbitalElements::circular(1.0, 0.0).period(1.001);
+    let error = |integrator: Integrator, steps: f32| {
+        let mut simulation = kepler(period / steps);
+        simulation.set_integrator(integrator).unwrap();
+        let mut reference = kepler(period / 4000.0);
+        reference.set_integrator(Integrator::Hermite).unwrap();
+        reference_error(&mut simulation, &mut reference, period as f64)
+    };
+
+    // Leapfrog is second order: twice the steps, a quarter of the error.
+    let (coarse, fine) = (error(Integrator::Leapfrog, 200.0), error(Integrator::Leapfrog, 400.0));
+    let ratio = coarse.max_error / fine.max_error;
+    assert!(ratio > 3.4 && ratio < 4.6, "errors {} and {}", coarse.max_error, fine.max_error);
+
+    let hermite = error(Integrator::Hermite, 100.0);
+    assert!(hermite.max_error < 1e-3, "hermite error {}", hermite.max_error);
+    assert!(hermite.energy_error < 1e-3, "hermite energy error {}", hermite.energy_error);
+}
+
+#[test]
+fn removed_bodies_are_reported_by_id() {
+    let mut simulation = kepler(0.01);
+    simulation.remove_bodies(&[0]);
+    let mut reference = kepler(0.01);
+
+    let report = reference_error(&mut simulation, &mut reference, 0.1);
+    assert_eq!(report.removed, vec![reference.bodies()[0].id]);
+    // The orbiting body is still paired with itself, not with the central one.
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0] < 0.1, "error {}", report.errors[0]);
+}